@@ -1,7 +1,11 @@
+use crate::archive_version_control::ArchiveVersionControl;
+use crate::cache::{get_mirror_path, Cache};
 use crate::default_manifest::DEFAULT_MANIFEST_FILE;
-use crate::project::{Project, ProjectAction, ProjectFileAction};
+use crate::hg_version_control::HgVersionControl;
+use crate::manifest_diff::normalize_path;
+use crate::project::{Project, ProjectAction, ProjectFileAction, Vcs};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 use std::fmt::Display;
 use std::fs;
 use std::fs::File;
@@ -10,15 +14,26 @@ use std::io::Write;
 use std::os::unix::fs::symlink;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::available_parallelism;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::{channel, Sender};
+use tokio::sync::{Notify, Semaphore};
 
-use crate::version_control::GitVersionControl;
+use crate::sync_report::{ProjectReport, SyncAction, SyncReport};
+use crate::topic::Topics;
+use crate::version_control::{FetchStats, GitVersionControl, Retry};
+use crate::workspace_state::{ProjectState, WorkspaceState};
 #[cfg(target_os = "windows")]
 use std::os::windows::fs::symlink_file as symlink;
 
 // Application description
 pub const APP_NAME: &str = "Colligo";
 
+/// Directory, next to the manifest, where Colligo keeps the workspace state.
+pub const STATE_DIR: &str = ".colligo";
+
 // Command line arguments
 pub const GENERATE_MANIFEST: &str = "generate";
 
@@ -32,14 +47,154 @@ pub const LIGHT: &str = "light";
 pub const QUIET: &str = "quiet";
 pub const FORCE: &str = "force";
 pub const HTTPS: &str = "https";
+pub const PRUNE: &str = "prune";
+pub const JOBS: &str = "jobs";
+pub const JOBS_NETWORK: &str = "jobs-network";
+pub const JOBS_CHECKOUT: &str = "jobs-checkout";
+pub const RETRIES: &str = "retries";
+pub const TIMEOUT: &str = "timeout";
+pub const FAIL_FAST: &str = "fail-fast";
+pub const KEEP_GOING: &str = "keep-going";
+pub const FAILED: &str = "failed";
+pub const JUNIT: &str = "junit";
+pub const BACKEND: &str = "backend";
+pub const BACKEND_GIT: &str = "git";
+pub const BACKEND_LIBGIT2: &str = "libgit2";
+pub const CACHE: &str = "cache";
+pub const FROM_MIRROR: &str = "from-mirror";
 
 pub const LIST: &str = "list";
 pub const STATUS: &str = "status";
 
+// Subcommands
+pub const DIFF: &str = "diff";
+pub const LOG: &str = "log";
+pub const START: &str = "start";
+pub const ABANDON: &str = "abandon";
+pub const PUSH: &str = "push";
+pub const COMMIT: &str = "commit";
+pub const TAG: &str = "tag";
+pub const IMPORT: &str = "import";
+pub const ADD: &str = "add";
+pub const REMOVE: &str = "remove";
+pub const SET: &str = "set";
+pub const GC: &str = "gc";
+pub const DEEPEN: &str = "deepen";
+pub const MIRROR: &str = "mirror";
+
+// Subcommand arguments
+pub const OLD_MANIFEST: &str = "old";
+pub const NEW_MANIFEST: &str = "new";
+pub const BRANCH: &str = "branch";
+pub const PROJECTS: &str = "projects";
+pub const DRY_RUN: &str = "dry-run";
+pub const MESSAGE: &str = "message";
+pub const TAG_NAME: &str = "name";
+pub const AT_REVISION: &str = "at-revision";
+pub const IMPORT_DIR: &str = "dir";
+pub const MIRROR_DIR: &str = "dir";
+pub const DEPTH: &str = "depth";
+pub const FULL: &str = "full";
+pub const REMOTE: &str = "remote";
+pub const OUTPUT: &str = "output";
+pub const PROJECT: &str = "project";
+pub const PROJECT_PATH: &str = "path";
+pub const REVISION: &str = "revision";
+pub const ATTRIBUTES: &str = "attributes";
+pub const FORMAT: &str = "format";
+pub const FORMAT_TEXT: &str = "text";
+pub const FORMAT_JSON: &str = "json";
+pub const FORMAT_MARKDOWN: &str = "markdown";
+
 #[derive(Clone)]
 pub enum DwlMode {
     HTTPS,
     SSH,
+    /// Fetch the git repositories from the mirrors created by `mirror` in this directory.
+    /// The Mercurial projects are fetched with HTTPS.
+    Mirror(PathBuf),
+}
+
+/// Implementation of the version control operations of `sync`, `pin` and the status.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Backend {
+    /// The `git` command line
+    #[default]
+    Git,
+    /// libgit2, without spawning `git`
+    #[cfg(feature = "libgit2")]
+    Libgit2,
+}
+
+/// Maximum number of projects processed at the same time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Jobs {
+    /// Fetches and pushes, limited to not overload the servers
+    pub network: usize,
+    /// Operations on the local checkouts
+    pub checkout: usize,
+}
+
+impl Jobs {
+    pub fn new(jobs: usize) -> Self {
+        let jobs = jobs.max(1);
+        Self {
+            network: jobs,
+            checkout: jobs,
+        }
+    }
+}
+
+impl Default for Jobs {
+    /// One job per CPU.
+    fn default() -> Self {
+        Self::new(available_parallelism().map(|n| n.get()).unwrap_or(1))
+    }
+}
+
+/// Shared flag to stop an operation in progress, e.g. on Ctrl-C.
+#[derive(Debug, Clone, Default)]
+pub struct Cancellation {
+    cancelled: Arc<AtomicBool>,
+    notify: Arc<Notify>,
+}
+
+impl Cancellation {
+    /// Cancellation triggered by the first Ctrl-C. The second one exits immediately.
+    pub fn on_ctrl_c() -> Self {
+        let cancellation = Self::default();
+        let trigger = cancellation.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                eprintln!("Interrupted, waiting for the operations in progress. Press Ctrl-C again to exit now.");
+                trigger.cancel();
+            }
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130);
+            }
+        });
+        cancellation
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Wait until cancelled.
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
 }
 
 pub trait ManifestParser {
@@ -53,9 +208,37 @@ pub trait ManifestParser {
 /// VersionControl is a trait that defines the methods to interact with a version control system.
 /// The manifest file path is used to avoid changing the working directory which may results in
 /// unexpected behavior when multiple threads are used.
-/// When using the lightweight option, the revision MUST be a branch or a tag. Commit ID are not supported.
+/// When using the lightweight option, the revision MUST be a branch, a tag or, with the git backend,
+/// a full commit ID. The commit is fetched alone if the server allows it, else with all the branches.
 #[async_trait::async_trait]
 pub trait XVersionControl: Send + Sync {
+    /// Create the repository of a project, or update its remote if it already exists.
+    async fn init(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        mode: &DwlMode,
+    ) -> Result<(), ManifestError>;
+
+    /// Download the manifest revision without changing the working tree.
+    async fn fetch(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        pb: Option<&ProgressBar>,
+        lightweight: bool,
+    ) -> Result<FetchStats, ManifestError>;
+
+    /// Update the working tree to the fetched revision, without accessing the remote.
+    async fn update(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        pb: Option<&ProgressBar>,
+        force: bool,
+        lightweight: bool,
+    ) -> Result<(), ManifestError>;
+
     /// Clone a project from a URI to a path.
     async fn clone(
         &self,
@@ -64,7 +247,11 @@ pub trait XVersionControl: Send + Sync {
         mode: &DwlMode,
         pb: Option<&ProgressBar>,
         lightweight: bool,
-    ) -> Result<(), ManifestError>;
+    ) -> Result<(), ManifestError> {
+        self.init(manifest_dir, project, mode).await?;
+        self.checkout(manifest_dir, project, pb, false, lightweight)
+            .await
+    }
 
     /// Update a project to a specific revision.
     async fn checkout(
@@ -73,7 +260,12 @@ pub trait XVersionControl: Send + Sync {
         project: &Project,
         pb: Option<&ProgressBar>,
         force: bool,
-    ) -> Result<(), ManifestError>;
+        lightweight: bool,
+    ) -> Result<(), ManifestError> {
+        self.fetch(manifest_dir, project, pb, lightweight).await?;
+        self.update(manifest_dir, project, pb, force, lightweight)
+            .await
+    }
 
     /// Status of a project.
     async fn get_commit_id(
@@ -106,6 +298,24 @@ pub enum ManifestError {
     FailedToSync(String),
     FailedToSaveFile(String),
     MissingDependency(String),
+    FailedToFetch(String),
+    FailedToGetLog(String),
+    FailedToCreateBranch(String),
+    FailedToDeleteBranch(String),
+    FailedToAccessState(String),
+    ProjectNotFound(String),
+    FailedToPush(String),
+    FailedToCommit(String),
+    FailedToTag(String),
+    FailedToPrune(String),
+    FailedToImport(String),
+    FailedToEditManifest(String),
+    Interrupted(String),
+    UnsupportedBackend(String),
+    FailedToDownload(String),
+    FailedToUnpack(String),
+    FailedToCleanCache(String),
+    FailedToDeepen(String),
 }
 
 impl Display for ManifestError {
@@ -153,6 +363,60 @@ impl Display for ManifestError {
             ManifestError::FailedToGenerateDefaultManifest(e) => {
                 write!(f, "Failed to generate default manifest file: {}", e)
             }
+            ManifestError::FailedToFetch(e) => {
+                write!(f, "Failed to fetch repository: {}", e)
+            }
+            ManifestError::FailedToGetLog(e) => {
+                write!(f, "Failed to get commit log: {}", e)
+            }
+            ManifestError::FailedToCreateBranch(e) => {
+                write!(f, "Failed to create branch: {}", e)
+            }
+            ManifestError::FailedToDeleteBranch(e) => {
+                write!(f, "Failed to delete branch: {}", e)
+            }
+            ManifestError::FailedToAccessState(e) => {
+                write!(f, "Failed to access workspace state: {}", e)
+            }
+            ManifestError::ProjectNotFound(e) => {
+                write!(f, "Project not found in manifest: {}", e)
+            }
+            ManifestError::FailedToPush(e) => {
+                write!(f, "Failed to push: {}", e)
+            }
+            ManifestError::FailedToCommit(e) => {
+                write!(f, "Failed to commit: {}", e)
+            }
+            ManifestError::FailedToTag(e) => {
+                write!(f, "Failed to tag: {}", e)
+            }
+            ManifestError::FailedToPrune(e) => {
+                write!(f, "Failed to prune: {}", e)
+            }
+            ManifestError::FailedToImport(e) => {
+                write!(f, "Failed to import: {}", e)
+            }
+            ManifestError::FailedToEditManifest(e) => {
+                write!(f, "Failed to edit manifest: {}", e)
+            }
+            ManifestError::Interrupted(e) => {
+                write!(f, "Interrupted, projects not finished: {}", e)
+            }
+            ManifestError::UnsupportedBackend(e) => {
+                write!(f, "Unsupported backend: {}", e)
+            }
+            ManifestError::FailedToDownload(e) => {
+                write!(f, "Failed to download archive: {}", e)
+            }
+            ManifestError::FailedToUnpack(e) => {
+                write!(f, "Failed to unpack archive: {}", e)
+            }
+            ManifestError::FailedToCleanCache(e) => {
+                write!(f, "Failed to clean cache: {}", e)
+            }
+            ManifestError::FailedToDeepen(e) => {
+                write!(f, "Failed to deepen repository: {}", e)
+            }
         }
     }
 }
@@ -164,6 +428,21 @@ pub struct ManifestInstance {
     file: String,
     /// Vector with all projects
     projects: Vec<Project>,
+    /// Parallelism of the operations on the projects
+    jobs: Jobs,
+    /// Retries of the fetches failing with a transient error
+    retry: Retry,
+    /// Maximum duration of each git invocation
+    timeout: Option<Duration>,
+    /// Stop the synchronization, e.g. on Ctrl-C
+    cancellation: Cancellation,
+    /// Cancel the synchronization on the first error
+    fail_fast: bool,
+    backend: Backend,
+    /// Used instead of the backend when set
+    vcs: Option<Arc<dyn XVersionControl>>,
+    /// Directory of the mirrors shared by the workspaces, used by `sync` when set
+    cache_dir: Option<PathBuf>,
 }
 
 impl ManifestInstance {
@@ -179,6 +458,14 @@ impl ManifestInstance {
             filename,
             file,
             projects: Vec::new(),
+            jobs: Jobs::default(),
+            retry: Retry::default(),
+            timeout: None,
+            cancellation: Cancellation::default(),
+            fail_fast: false,
+            backend: Backend::default(),
+            vcs: None,
+            cache_dir: None,
         })
     }
 
@@ -194,6 +481,115 @@ impl ManifestInstance {
         &self.projects
     }
 
+    pub fn get_jobs(&self) -> Jobs {
+        self.jobs
+    }
+
+    pub fn set_jobs(&mut self, jobs: Jobs) {
+        self.jobs = jobs;
+    }
+
+    pub fn get_retry(&self) -> Retry {
+        self.retry
+    }
+
+    pub fn set_retry(&mut self, retry: Retry) {
+        self.retry = retry;
+    }
+
+    pub fn get_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn set_cancellation(&mut self, cancellation: Cancellation) {
+        self.cancellation = cancellation;
+    }
+
+    pub fn set_fail_fast(&mut self, fail_fast: bool) {
+        self.fail_fast = fail_fast;
+    }
+
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.backend = backend;
+    }
+
+    /// Borrow the objects of the git repositories from mirrors in `cache_dir`, updated by `sync`.
+    /// Only supported by the git backend, the libgit2 backend warns and fetches everything.
+    pub fn set_cache_dir(&mut self, cache_dir: Option<PathBuf>) {
+        self.cache_dir = cache_dir;
+    }
+
+    /// Use `vcs` instead of the backend. The retries, timeout and cancellation are not applied to it.
+    pub fn set_version_control(&mut self, vcs: Arc<dyn XVersionControl>) {
+        self.vcs = Some(vcs);
+    }
+
+    /// Implementation to synchronize `project`: archive, hg or the backend, configured with the
+    /// retries, timeout and cancellation, unless another one was set.
+    pub fn get_version_control(&self, project: &Project) -> Arc<dyn XVersionControl> {
+        self.create_version_control(project, None)
+    }
+
+    fn create_version_control(
+        &self,
+        project: &Project,
+        cache: Option<Arc<Cache>>,
+    ) -> Arc<dyn XVersionControl> {
+        if let Some(vcs) = &self.vcs {
+            return vcs.clone();
+        }
+        if project.get_archive().is_some() {
+            let mut archive = ArchiveVersionControl::new();
+            archive.set_retry(self.retry);
+            archive.set_timeout(self.timeout);
+            archive.set_cancellation(self.cancellation.clone());
+            return Arc::new(archive);
+        }
+        match (project.get_vcs(), self.backend) {
+            (Vcs::Hg, _) => {
+                let mut hg = HgVersionControl::new();
+                hg.set_retry(self.retry);
+                hg.set_timeout(self.timeout);
+                hg.set_cancellation(self.cancellation.clone());
+                Arc::new(hg)
+            }
+            (Vcs::Git, Backend::Git) => {
+                let mut git = GitVersionControl::new();
+                git.set_retry(self.retry);
+                git.set_timeout(self.timeout);
+                git.set_cancellation(self.cancellation.clone());
+                git.set_cache(cache);
+                Arc::new(git)
+            }
+            #[cfg(feature = "libgit2")]
+            (Vcs::Git, Backend::Libgit2) => {
+                if let Some(cache) = cache {
+                    log::warn!(
+                        "{}, the {} backend does not borrow objects from {}, fetching everything",
+                        project.get_path(),
+                        BACKEND_LIBGIT2,
+                        cache.get_dir().display()
+                    );
+                }
+                let mut libgit2 = crate::libgit2_version_control::Libgit2VersionControl::new();
+                libgit2.set_retry(self.retry);
+                libgit2.set_timeout(self.timeout);
+                libgit2.set_cancellation(self.cancellation.clone());
+                Arc::new(libgit2)
+            }
+        }
+    }
+
+    /// Keep only the projects at `paths`, e.g. to synchronize again the failed ones.
+    pub fn retain_projects(&mut self, paths: &[String]) {
+        self.projects
+            .retain(|p| paths.contains(&normalize_path(p.get_path())));
+    }
+
     pub fn parse(&mut self) -> Result<(), ManifestError> {
         let parser = crate::xml_parser::XmlParser::new();
         self.projects = parser.parse(&self.file)?;
@@ -207,6 +603,21 @@ impl ManifestInstance {
         quiet: bool,
         force: bool,
     ) -> Result<(), ManifestError> {
+        self.sync_report(mode, lightweight, quiet, force)
+            .await
+            .into_result()
+    }
+
+    /// Synchronize all projects and report what was done to each one.
+    pub async fn sync_report(
+        &self,
+        mode: &DwlMode,
+        lightweight: bool,
+        quiet: bool,
+        force: bool,
+    ) -> SyncReport {
+        let started = Instant::now();
+
         // Prepare progress bar
         let multi_progress = MultiProgress::new();
         let style = ProgressStyle::default_bar()
@@ -232,7 +643,17 @@ impl ManifestInstance {
         let (tx, mut rx) = channel(255);
 
         // Handle
-        let mut _handles = Vec::new();
+        let mut handles = Vec::new();
+
+        // Limit the number of simultaneous connections and checkouts
+        let network = Arc::new(Semaphore::new(self.jobs.network));
+        let checkout = Arc::new(Semaphore::new(self.jobs.checkout));
+
+        // Each mirror is updated once, even if shared by several projects. Without the cache, the
+        // repositories checked out at several paths share a mirror in the workspace.
+        let cache = self.cache_dir.clone().map(|dir| Arc::new(Cache::new(dir)));
+        let store = Arc::new(Cache::new_workspace_store(&self.get_manifest_dir()));
+        let shared = self.get_shared_repositories();
 
         // Spawn a thread for each project
         for project in self.projects.iter() {
@@ -241,84 +662,310 @@ impl ManifestInstance {
             let dir = self.get_manifest_dir();
             let project = project.clone();
             let mode = mode.clone();
+            let network = network.clone();
+            let checkout = checkout.clone();
+            let repository = (project.get_uri().clone(), project.get_name().clone());
+            let project_cache = match &cache {
+                Some(cache) => Some(cache.clone()),
+                None if shared.contains(&repository) => Some(store.clone()),
+                None => None,
+            };
+            let vcs = self.create_version_control(&project, project_cache);
+            let cancellation = self.cancellation.clone();
+            let path = project.get_path().clone();
 
             let handle = tokio::task::spawn(async move {
                 let mut result;
+                let mut report = ProjectReport::new(project.get_path().clone());
+
+                // A repository created by this synchronization is removed if interrupted
+                let created = !dir.join(project.get_path()).exists();
+
+                // No new work is started once cancelled
+                {
+                    let _permit = checkout.acquire().await.expect("Semaphore closed");
+                    let stage = Instant::now();
+                    result = match cancellation.is_cancelled() {
+                        true => Err(ManifestError::Interrupted(project.get_path().clone())),
+                        false => vcs.init(&dir, &project, &mode).await,
+                    };
+                    if result.is_ok() && !created {
+                        report.old_commit = vcs.get_commit_id(&dir, &project).await.ok();
+                    }
+                    report.duration += stage.elapsed();
+                    send_result(&tx, result.clone()).await;
+                }
 
-                let vcs = GitVersionControl::new();
-
-                result = vcs.init(&dir, &project, &mode).await;
-                send_result(&tx, result.clone()).await;
+                if result.is_ok() {
+                    let _permit = network.acquire().await.expect("Semaphore closed");
+                    let stage = Instant::now();
+                    let fetched = match cancellation.is_cancelled() {
+                        true => Err(ManifestError::Interrupted(project.get_path().clone())),
+                        false => vcs.fetch(&dir, &project, pb.as_ref(), lightweight).await,
+                    };
+                    report.record_fetch(fetched.as_ref());
+                    result = fetched.map(|_| ());
+                    report.duration += stage.elapsed();
+                    send_result(&tx, result.clone()).await;
+                }
 
+                let _permit = checkout.acquire().await.expect("Semaphore closed");
+                let stage = Instant::now();
                 if result.is_ok() {
-                    result = vcs
-                        .checkout(&dir, &project, pb.as_ref(), force, lightweight)
-                        .await;
+                    result = match cancellation.is_cancelled() {
+                        true => Err(ManifestError::Interrupted(project.get_path().clone())),
+                        false => {
+                            vcs.update(&dir, &project, pb.as_ref(), force, lightweight)
+                                .await
+                        }
+                    };
                     send_result(&tx, result.clone()).await;
                 }
 
+                if created && matches!(result, Err(ManifestError::Interrupted(_))) {
+                    let _ = fs::remove_dir_all(dir.join(project.get_path()));
+                }
+
+                // Resolve the commit before the actions, they may delete the project
+                if result.is_ok() {
+                    report.new_commit = vcs.get_commit_id(&dir, &project).await.ok();
+                }
+
                 if result.is_ok() {
                     result = execute_actions(&dir, &project);
                     send_result(&tx, result.clone()).await;
                 }
+                report.duration += stage.elapsed();
 
                 if let Some(pb) = pb {
+                    let attempts = report.attempts;
+                    if result.is_ok() && attempts > 1 {
+                        let path = project.get_path();
+                        pb.set_message(format!("{path} complete after {attempts} attempts"));
+                    }
                     pb.finish();
                 }
+
+                report.action = match &result {
+                    Ok(_) if created => SyncAction::Cloned,
+                    Ok(_) if report.old_commit == report.new_commit => SyncAction::UpToDate,
+                    Ok(_) => SyncAction::Updated,
+                    Err(ManifestError::Interrupted(_)) => SyncAction::Cancelled,
+                    Err(_) => SyncAction::Failed,
+                };
+                match result {
+                    Ok(_) => {
+                        report.actions = project
+                            .get_actions()
+                            .iter()
+                            .map(|action| action.to_string())
+                            .collect();
+                    }
+                    Err(e) => report.error = Some(e),
+                }
+
+                let state = match report.error {
+                    None => Some(ProjectState::new(&project, report.new_commit.clone())),
+                    Some(_) => None,
+                };
+                (report, state)
             });
 
-            _handles.push(handle);
+            handles.push((path, handle));
         }
         drop(tx);
 
-        // Wait for task to finish
-        // Save error messages if any
-        let mut errors: Vec<String> = Vec::new();
-        while let Some(error) = rx.recv().await {
-            if let Err(e) = error {
-                errors.push(format!("{e}"))
+        // Stop the other projects on the first error, their errors would only add noise
+        while let Some(result) = rx.recv().await {
+            match result {
+                Err(ManifestError::Interrupted(_)) | Ok(_) => {}
+                Err(_) => {
+                    if self.fail_fast {
+                        self.cancellation.cancel();
+                    }
+                }
             }
         }
 
-        // If there is any error, return it
-        if !errors.is_empty() {
-            // Save all error message in string, separated by a new line
-            let mut error_msg = String::new();
-            error_msg.push_str("\n\n");
+        let mut report = SyncReport::default();
+        let mut synced = Vec::new();
+        for (path, handle) in handles {
+            match handle.await {
+                Ok((project_report, state)) => {
+                    report.projects.push(project_report);
+                    synced.extend(state);
+                }
+                Err(e) => {
+                    let mut project_report = ProjectReport::new(path.clone());
+                    let msg = format!("{path}\n{e}\n");
+                    project_report.error = Some(ManifestError::FailedToSync(msg));
+                    report.projects.push(project_report);
+                }
+            }
+        }
 
-            for msg in errors.iter() {
-                error_msg.push_str(msg);
-                error_msg.push('\n');
+        // Keep track of what was synced for the next commands
+        if let Err(e) = self.record_sync(synced, report.get_failed()) {
+            report.error = Some(e);
+        }
+
+        report.duration = started.elapsed();
+        report
+    }
+
+    /// Create or update a bare mirror of each git repository of the manifest in `mirror_dir`,
+    /// in `<host>/<name>.git`, to synchronize workspaces with `DwlMode::Mirror`. The archives
+    /// and Mercurial projects are not mirrored. Each mirror is reported as a project.
+    pub async fn mirror_report(&self, mirror_dir: &Path, mode: &DwlMode) -> SyncReport {
+        let started = Instant::now();
+        let network = Arc::new(Semaphore::new(self.jobs.network));
+
+        let mut repositories = HashSet::new();
+        let mut handles = Vec::new();
+        for project in self.projects.iter() {
+            if !project.is_git() {
+                continue;
+            }
+            let mirror = get_mirror_path(mirror_dir, project);
+            if !repositories.insert(mirror.clone()) {
+                continue;
             }
 
-            return Err(ManifestError::FailedToSync(error_msg));
+            let path = mirror
+                .strip_prefix(mirror_dir)
+                .unwrap_or(&mirror)
+                .display()
+                .to_string();
+            let mut git = GitVersionControl::new();
+            git.set_retry(self.retry);
+            git.set_timeout(self.timeout);
+            git.set_cancellation(self.cancellation.clone());
+            let mirror_dir = mirror_dir.to_path_buf();
+            let project = project.clone();
+            let mode = mode.clone();
+            let network = network.clone();
+            let cancellation = self.cancellation.clone();
+            let fail_fast = self.fail_fast;
+
+            handles.push(tokio::task::spawn(async move {
+                let _permit = network.acquire().await.expect("Semaphore closed");
+                let mut report = ProjectReport::new(path);
+                let created = !mirror.join("HEAD").exists();
+                let stage = Instant::now();
+                let result = match cancellation.is_cancelled() {
+                    true => Err(ManifestError::Interrupted(report.path.clone())),
+                    false => git.mirror(&mirror_dir, &project, &mode, None).await,
+                };
+                report.duration = stage.elapsed();
+                report.action = match &result {
+                    Ok(_) if created => SyncAction::Cloned,
+                    Ok((_, true)) => SyncAction::Updated,
+                    Ok((_, false)) => SyncAction::UpToDate,
+                    Err(ManifestError::Interrupted(_)) => SyncAction::Cancelled,
+                    Err(_) => SyncAction::Failed,
+                };
+                report.record_fetch(result.as_ref().map(|(stats, _)| stats));
+                if let Err(e) = result {
+                    if fail_fast && !matches!(e, ManifestError::Interrupted(_)) {
+                        cancellation.cancel();
+                    }
+                    report.error = Some(e);
+                }
+                report
+            }));
+        }
+
+        let mut report = SyncReport::default();
+        for handle in handles {
+            match handle.await {
+                Ok(project_report) => report.projects.push(project_report),
+                Err(e) => report.error = Some(ManifestError::FailedToSync(e.to_string())),
+            }
         }
+        report.duration = started.elapsed();
+        report
+    }
 
-        Ok(())
+    /// Uri and name of the git repositories checked out by several projects. The sparse, shallow
+    /// and partial clones download only the objects they need, not the whole repository.
+    fn get_shared_repositories(&self) -> HashSet<(String, String)> {
+        let mut seen = HashSet::new();
+        let mut shared = HashSet::new();
+        for project in self.projects.iter() {
+            if !project.is_git()
+                || !project.get_sparse_paths().is_empty()
+                || project.get_clone_depth().is_some()
+                || project.get_filter().is_some()
+            {
+                continue;
+            }
+            let repository = (project.get_uri().clone(), project.get_name().clone());
+            if !seen.insert(repository.clone()) {
+                shared.insert(repository);
+            }
+        }
+        shared
     }
 
     pub async fn pin(&self) -> Result<Self, ManifestError> {
         let mut projects: Vec<Project> = Vec::new();
-        let vcs = GitVersionControl::new();
         let manifest_dir = self.get_manifest_dir();
 
+        let checkout = Arc::new(Semaphore::new(self.jobs.checkout));
+        let mut handles = Vec::with_capacity(self.projects.len());
         for project in self.projects.iter() {
-            let commit_id = vcs.get_commit_id(manifest_dir.as_path(), project).await?;
-            let pinned_project = project.pin(commit_id);
+            let dir = manifest_dir.clone();
+            let project = project.clone();
+            let checkout = checkout.clone();
+            let vcs = self.get_version_control(&project);
+
+            handles.push(tokio::task::spawn(async move {
+                let _permit = checkout.acquire().await.expect("Semaphore closed");
+                let commit_id = vcs.get_commit_id(&dir, &project).await?;
+                Ok::<Project, ManifestError>(project.pin(commit_id))
+            }));
+        }
+
+        for handle in handles {
+            let pinned_project = handle.await.expect("Failed to join pin task")?;
             projects.push(pinned_project);
         }
 
         let parser = crate::xml_parser::XmlParser::new();
         let file = parser.compose(&projects)?;
 
-        Ok(Self {
+        Ok(self.with_projects(file, projects))
+    }
+
+    /// Same manifest and settings, with the `projects` composed into `file`.
+    fn with_projects(&self, file: String, projects: Vec<Project>) -> Self {
+        Self {
             filename: self.filename.clone(),
             file,
             projects,
-        })
+            jobs: self.jobs,
+            retry: self.retry,
+            timeout: self.timeout,
+            cancellation: self.cancellation.clone(),
+            fail_fast: self.fail_fast,
+            backend: self.backend,
+            vcs: self.vcs.clone(),
+            cache_dir: self.cache_dir.clone(),
+        }
     }
 
-    fn get_manifest_dir(&self) -> PathBuf {
+    fn record_sync(
+        &self,
+        synced: Vec<ProjectState>,
+        failed: Vec<String>,
+    ) -> Result<(), ManifestError> {
+        let manifest_dir = self.get_manifest_dir();
+        let mut state = WorkspaceState::load(&manifest_dir)?;
+        state.record_sync(&self.file, synced, failed);
+        state.save(&manifest_dir)
+    }
+
+    pub fn get_manifest_dir(&self) -> PathBuf {
         let file_path = Path::new(&self.filename);
         let abs_path = file_path.canonicalize().unwrap_or("./".into());
         let workdir = abs_path.parent().unwrap_or("./".as_ref());
@@ -524,6 +1171,219 @@ fn copy_directory(src: &Path, dest: &Path) -> std::io::Result<()> {
     Ok(())
 }
 
+/// Return the projects matching the given paths or names. All projects are returned when no
+/// selector is given.
+pub fn select_projects(
+    manifest: &ManifestInstance,
+    selectors: &[String],
+) -> Result<Vec<Project>, ManifestError> {
+    if selectors.is_empty() {
+        return Ok(manifest.get_projects().clone());
+    }
+
+    let mut projects = Vec::with_capacity(selectors.len());
+    for selector in selectors {
+        let selected = normalize_path(selector);
+        let project = manifest
+            .get_projects()
+            .iter()
+            .find(|p| normalize_path(p.get_path()) == selected)
+            .or_else(|| {
+                manifest
+                    .get_projects()
+                    .iter()
+                    .find(|p| p.get_name() == selector)
+            })
+            .ok_or_else(|| ManifestError::ProjectNotFound(selector.clone()))?;
+        projects.push(project.clone());
+    }
+    Ok(projects)
+}
+
+/// Return the git projects matching the given paths or names, like `select_projects`. The archives
+/// and Mercurial projects are skipped.
+pub fn select_git_projects(
+    manifest: &ManifestInstance,
+    selectors: &[String],
+) -> Result<Vec<Project>, ManifestError> {
+    let projects = select_projects(manifest, selectors)?;
+    Ok(projects.into_iter().filter(Project::is_git).collect())
+}
+
+/// Commit created in a project.
+pub struct ProjectCommit {
+    pub path: String,
+    pub commit_id: String,
+}
+
+/// Commit the modified tracked files of the selected git projects, or all of them if none is
+/// selected, with the same message. Projects without modification are skipped.
+pub async fn commit_projects(
+    manifest: &ManifestInstance,
+    message: &str,
+    selectors: &[String],
+) -> Result<Vec<ProjectCommit>, ManifestError> {
+    let projects = select_git_projects(manifest, selectors)?;
+    let manifest_dir = manifest.get_manifest_dir();
+    let vcs = GitVersionControl::new();
+
+    let mut commits = Vec::new();
+    for project in projects.iter() {
+        if !vcs.is_modified(&manifest_dir, project).await? {
+            continue;
+        }
+
+        let commit_id = vcs.commit(&manifest_dir, project, message).await?;
+        commits.push(ProjectCommit {
+            path: project.get_path().clone(),
+            commit_id,
+        });
+    }
+    Ok(commits)
+}
+
+/// Create the annotated tag `name` in the selected git projects, or all of them if none is
+/// selected.
+/// The tag is created at the current HEAD, or at the manifest revision when `at_revision` is set.
+/// Nothing is tagged if any selected project has modified files, and the tags already created are
+/// deleted when a project fails to be tagged.
+/// Return the manifest with the revision of the tagged projects pinned to the tag.
+pub async fn tag_projects(
+    manifest: &ManifestInstance,
+    name: &str,
+    message: Option<&str>,
+    selectors: &[String],
+    at_revision: bool,
+) -> Result<ManifestInstance, ManifestError> {
+    let selected = select_git_projects(manifest, selectors)?;
+    let manifest_dir = manifest.get_manifest_dir();
+    let vcs = GitVersionControl::new();
+
+    // Refuse to tag a workspace with local modifications
+    let mut dirty = Vec::new();
+    for project in selected.iter() {
+        if vcs.is_modified(&manifest_dir, project).await? {
+            dirty.push(project.get_path().clone());
+        }
+    }
+    if !dirty.is_empty() {
+        let msg = format!(
+            "{}, repository is dirty, please commit or stash your changes",
+            dirty.join(", ")
+        );
+        return Err(ManifestError::FailedToTag(msg));
+    }
+
+    // Resolve every target first, most failures happen before anything is tagged
+    let mut targets = Vec::with_capacity(selected.len());
+    for project in selected.iter() {
+        let revision = match at_revision {
+            true => project.get_revision().as_str(),
+            false => "HEAD",
+        };
+        let target = vcs
+            .resolve_revision(&manifest_dir, project, revision)
+            .await
+            .ok_or_else(|| {
+                let msg = format!("{}, unable to find revision {revision}", project.get_path());
+                ManifestError::FailedToTag(msg)
+            })?;
+        targets.push(target);
+    }
+
+    let message = message.unwrap_or(name);
+    for (index, (project, target)) in selected.iter().zip(targets.iter()).enumerate() {
+        if let Err(e) = vcs.tag(&manifest_dir, project, name, message, target).await {
+            // Delete the tags already created, the command can be run again
+            for tagged in selected.iter().take(index) {
+                let _ = vcs.delete_tag(&manifest_dir, tagged, name).await;
+            }
+            return Err(e);
+        }
+    }
+
+    let projects: Vec<Project> = manifest
+        .get_projects()
+        .iter()
+        .map(|project| {
+            let is_selected = selected
+                .iter()
+                .any(|p| normalize_path(p.get_path()) == normalize_path(project.get_path()));
+            if is_selected {
+                project.pin(name.to_string())
+            } else {
+                project.clone()
+            }
+        })
+        .collect();
+
+    let parser = crate::xml_parser::XmlParser::new();
+    let file = parser.compose(&projects)?;
+
+    Ok(manifest.with_projects(file, projects))
+}
+
+/// Push the tag `name` of the selected git projects, or all of them if none is selected.
+pub async fn push_tag(
+    manifest: &ManifestInstance,
+    name: &str,
+    selectors: &[String],
+    mode: &DwlMode,
+) -> Result<(), ManifestError> {
+    let projects = select_git_projects(manifest, selectors)?;
+    let manifest_dir = manifest.get_manifest_dir();
+    let vcs = GitVersionControl::new();
+
+    for project in projects.iter() {
+        vcs.push_tag(&manifest_dir, project, mode, name).await?;
+    }
+    Ok(())
+}
+
+/// Outcome of deepening one project.
+pub struct DeepenResult {
+    pub path: String,
+    /// Whether anything was fetched, or the error
+    pub result: Result<bool, ManifestError>,
+}
+
+/// Fetch `depth` more commits of the history of the selected git projects, or all of them if none
+/// is selected, in parallel. Without depth, convert them to full clones.
+pub async fn deepen_projects(
+    manifest: &ManifestInstance,
+    depth: Option<u32>,
+    selectors: &[String],
+) -> Result<Vec<DeepenResult>, ManifestError> {
+    let projects = select_git_projects(manifest, selectors)?;
+    let manifest_dir = manifest.get_manifest_dir();
+
+    let network = Arc::new(Semaphore::new(manifest.jobs.network));
+    let mut handles = Vec::with_capacity(projects.len());
+    for project in projects {
+        let dir = manifest_dir.clone();
+        let network = network.clone();
+        let mut vcs = GitVersionControl::new();
+        vcs.set_retry(manifest.retry);
+        vcs.set_timeout(manifest.timeout);
+        vcs.set_cancellation(manifest.cancellation.clone());
+
+        handles.push(tokio::task::spawn(async move {
+            let _permit = network.acquire().await.expect("Semaphore closed");
+            let result = vcs.deepen(&dir, &project, depth, None).await;
+            DeepenResult {
+                path: project.get_path().clone(),
+                result,
+            }
+        }));
+    }
+
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        results.push(handle.await.expect("Failed to join deepen task"));
+    }
+    Ok(results)
+}
+
 pub fn list_projects_path(manifest: &ManifestInstance, workdir: &Path) -> Vec<String> {
     let mut output = Vec::with_capacity(manifest.get_projects().len());
 
@@ -550,16 +1410,38 @@ pub async fn get_projects_status(manifest: &ManifestInstance, workdir: &Path) ->
 
     let manifest_dir = manifest.get_manifest_dir();
     let manifest_dir_path = Path::new(&manifest_dir);
-    let vcs = GitVersionControl::new();
+    let topics = Topics::load(&manifest_dir).unwrap_or_default();
 
+    // Check the projects in parallel, the output keeps the manifest order
+    let checkout = Arc::new(Semaphore::new(manifest.get_jobs().checkout));
+    let mut handles = Vec::with_capacity(manifest.get_projects().len());
     for project in manifest.get_projects() {
-        let status = match vcs.is_modified(&manifest_dir, project).await {
+        let dir = manifest_dir.clone();
+        let project = project.clone();
+        let checkout = checkout.clone();
+        let vcs = manifest.get_version_control(&project);
+
+        handles.push(tokio::task::spawn(async move {
+            let _permit = checkout.acquire().await.expect("Semaphore closed");
+            vcs.is_modified(&dir, &project).await
+        }));
+    }
+
+    for (project, handle) in manifest.get_projects().iter().zip(handles) {
+        let is_modified = handle.await.expect("Failed to join status task");
+        let mut status = match is_modified {
             Ok(false) => "".to_string(),
             Ok(true) => " (modified)".to_string(),
             Err(ManifestError::FailedToDetermineIfRepoIsModified(e)) => e,
             Err(_) => "Unknown error".to_string(),
         };
 
+        let project_topics = topics.get_topics(project.get_path());
+        if !project_topics.is_empty() {
+            let project_topics: Vec<&str> = project_topics.iter().map(|t| t.as_str()).collect();
+            status = format!(" [{}]{}", project_topics.join(", "), status);
+        }
+
         let repo_abs_path = manifest_dir_path.join(project.get_path());
         let rel_path = pathdiff::diff_paths(repo_abs_path, workdir)
             .unwrap_or("./".into())
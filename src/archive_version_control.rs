@@ -0,0 +1,524 @@
+use crate::application::{Cancellation, DwlMode, ManifestError, XVersionControl, STATE_DIR};
+use crate::project::{Archive, Project};
+use crate::version_control::{FetchStats, Retry};
+use flate2::read::GzDecoder;
+use indicatif::ProgressBar;
+use log::debug;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use xz2::read::XzDecoder;
+
+/// File written next to the unpacked content, holding the SHA-256 of the archive
+pub const ARCHIVE_MARKER: &str = ".colligo-archive";
+/// Downloaded archives, in the state directory, named after their SHA-256
+const ARCHIVES_DIR: &str = "archives";
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+const ZIP_MAGIC: &[u8] = &[b'P', b'K', 0x03, 0x04];
+
+/// Synchronize the `<archive>` projects: download the archive, verify its hash and unpack it.
+pub struct ArchiveVersionControl {
+    retry: Retry,
+    /// Maximum duration of each download
+    timeout: Option<Duration>,
+    /// Stop the downloads in progress when cancelled
+    cancellation: Cancellation,
+}
+
+impl Default for ArchiveVersionControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Error of a download, retried when transient.
+struct DownloadError {
+    message: String,
+    retryable: bool,
+}
+
+impl From<io::Error> for DownloadError {
+    fn from(e: io::Error) -> Self {
+        Self {
+            message: e.to_string(),
+            retryable: false,
+        }
+    }
+}
+
+impl ArchiveVersionControl {
+    pub fn new() -> Self {
+        Self {
+            retry: Retry::default(),
+            timeout: None,
+            cancellation: Cancellation::default(),
+        }
+    }
+
+    pub fn set_retry(&mut self, retry: Retry) {
+        self.retry = retry;
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn set_cancellation(&mut self, cancellation: Cancellation) {
+        self.cancellation = cancellation;
+    }
+
+    async fn download(
+        &self,
+        url: &str,
+        file: &Path,
+        pb: Option<&ProgressBar>,
+    ) -> Result<(), DownloadError> {
+        let url = url.to_string();
+        let file = file.to_path_buf();
+        let timeout = self.timeout;
+        let pb = pb.cloned();
+        tokio::task::spawn_blocking(move || download(&url, &file, timeout, pb.as_ref()))
+            .await
+            .unwrap_or_else(|e| {
+                Err(DownloadError {
+                    message: e.to_string(),
+                    retryable: false,
+                })
+            })
+    }
+}
+
+#[async_trait::async_trait]
+impl XVersionControl for ArchiveVersionControl {
+    async fn init(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        _mode: &DwlMode,
+    ) -> Result<(), ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+        fs::create_dir_all(&repo_path).map_err(|e| {
+            let msg = format!("{}\n{e}\n", project.get_path());
+            ManifestError::FailedToInitialize(msg)
+        })
+    }
+
+    /// Download the archive to the state directory, unless already unpacked or downloaded.
+    /// Transient network errors are retried.
+    async fn fetch(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        pb: Option<&ProgressBar>,
+        _lightweight: bool,
+    ) -> Result<FetchStats, ManifestError> {
+        let archive = get_archive(project)?;
+        let path = project.get_path();
+        let repo_path = manifest_dir.join(path);
+        let file = get_archive_file(manifest_dir, archive);
+
+        if get_unpacked_hash(&repo_path).as_ref() == Some(&archive.sha256)
+            || hash_file(&file).ok().as_ref() == Some(&archive.sha256)
+        {
+            return Ok(FetchStats::default());
+        }
+
+        debug!("Downloading {} into {}", archive.url, file.display());
+
+        let error = |e: String| {
+            let msg = format!("{path}\n{e}\n");
+            ManifestError::FailedToDownload(msg)
+        };
+        let parent = file.parent().expect("Archive file without parent");
+        fs::create_dir_all(parent).map_err(|e| error(e.to_string()))?;
+        let partial = file.with_extension("part");
+
+        let mut attempt = 1;
+        loop {
+            if let Some(pb) = pb {
+                pb.set_message(format!("{path} download"));
+            }
+
+            let result = tokio::select! {
+                result = self.download(&archive.url, &partial, pb) => result,
+                _ = self.cancellation.cancelled() => {
+                    return Err(ManifestError::Interrupted(path.clone()));
+                }
+            };
+
+            match result {
+                Ok(_) => break,
+                Err(e) if e.retryable && attempt <= self.retry.retries => {
+                    let delay = self.retry.get_delay(attempt);
+                    debug!(
+                        "Downloading {path} failed, retry in {delay:?}: {}",
+                        e.message
+                    );
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => {}
+                        _ = self.cancellation.cancelled() => {
+                            return Err(ManifestError::Interrupted(path.clone()));
+                        }
+                    }
+                    attempt += 1;
+                }
+                Err(e) => {
+                    if let Some(pb) = pb {
+                        pb.set_message(format!("{path} ERROR"));
+                    }
+                    let _ = fs::remove_file(&partial);
+                    let mut msg = format!("{}\n{}", archive.url, e.message);
+                    if attempt > 1 {
+                        msg.push_str(&format!("\nFailed after {attempt} attempts"));
+                    }
+                    return Err(error(msg));
+                }
+            }
+        }
+
+        // Never keep an archive which does not match the manifest
+        let sha256 = hash_file(&partial).map_err(|e| error(e.to_string()))?;
+        if sha256 != archive.sha256 {
+            let _ = fs::remove_file(&partial);
+            if let Some(pb) = pb {
+                pb.set_message(format!("{path} ERROR"));
+            }
+            let msg = format!(
+                "Checksum mismatch for {}, expected {} but got {sha256}",
+                archive.url, archive.sha256
+            );
+            return Err(error(msg));
+        }
+        fs::rename(&partial, &file).map_err(|e| error(e.to_string()))?;
+
+        if let Some(pb) = pb {
+            pb.set_position(100);
+            pb.set_message(format!("{path} complete"));
+        }
+        Ok(FetchStats {
+            attempts: attempt,
+            bytes: fs::metadata(&file).map(|m| m.len()).ok(),
+        })
+    }
+
+    /// Replace the content of the project with the archive, unless it is already unpacked.
+    /// A directory which was not unpacked from an archive is only replaced with `force`.
+    async fn update(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        pb: Option<&ProgressBar>,
+        force: bool,
+        _lightweight: bool,
+    ) -> Result<(), ManifestError> {
+        let archive = get_archive(project)?;
+        let path = project.get_path();
+        let repo_path = manifest_dir.join(path);
+        let file = get_archive_file(manifest_dir, archive);
+
+        // Forcing restores the modified files, if the archive is still downloaded
+        let unpacked = get_unpacked_hash(&repo_path);
+        if unpacked.as_ref() == Some(&archive.sha256) && (!force || !file.exists()) {
+            if let Some(pb) = pb {
+                pb.set_message(format!("{path} complete"));
+                pb.finish();
+            }
+            return Ok(());
+        }
+
+        let is_empty = fs::read_dir(&repo_path)
+            .map(|mut entries| entries.next().is_none())
+            .unwrap_or(true);
+        if !force && unpacked.is_none() && !is_empty {
+            if let Some(pb) = pb {
+                pb.set_message(format!("{path} ERROR"));
+            }
+            let msg = format!(
+                "{path}, directory was not unpacked from an archive, use --force to replace it"
+            );
+            return Err(ManifestError::FailedToUnpack(msg));
+        }
+
+        debug!("Unpacking {} into {}", archive.url, repo_path.display());
+
+        if let Some(pb) = pb {
+            pb.set_message(format!("{path} unpack"));
+        }
+
+        let archive = archive.clone();
+        let result = tokio::task::spawn_blocking(move || replace(&file, &repo_path, &archive))
+            .await
+            .unwrap_or_else(|e| Err(io::Error::other(e.to_string())));
+
+        if let Err(e) = result {
+            if let Some(pb) = pb {
+                pb.set_message(format!("{path} ERROR"));
+            }
+            let msg = format!("{path}\n{e}\n");
+            return Err(ManifestError::FailedToUnpack(msg));
+        }
+
+        if let Some(pb) = pb {
+            pb.set_message(format!("{path} complete"));
+            pb.finish();
+        }
+        Ok(())
+    }
+
+    /// The SHA-256 of the unpacked archive.
+    async fn get_commit_id(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+    ) -> Result<String, ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+        get_unpacked_hash(&repo_path).ok_or_else(|| {
+            let msg = format!("{}\nNo archive unpacked\n", project.get_path());
+            ManifestError::FailedToGetCommitId(msg)
+        })
+    }
+
+    /// The unpacked files are not tracked, they are only restored with `force`.
+    async fn is_modified(
+        &self,
+        _manifest_dir: &Path,
+        _project: &Project,
+    ) -> Result<bool, ManifestError> {
+        Ok(false)
+    }
+}
+
+fn get_archive(project: &Project) -> Result<&Archive, ManifestError> {
+    project.get_archive().ok_or_else(|| {
+        let msg = format!("{}, project is not an archive", project.get_path());
+        ManifestError::FailedToDownload(msg)
+    })
+}
+
+fn get_archive_file(manifest_dir: &Path, archive: &Archive) -> PathBuf {
+    manifest_dir
+        .join(STATE_DIR)
+        .join(ARCHIVES_DIR)
+        .join(&archive.sha256)
+}
+
+fn get_unpacked_hash(repo_path: &Path) -> Option<String> {
+    fs::read_to_string(repo_path.join(ARCHIVE_MARKER))
+        .ok()
+        .map(|sha256| sha256.trim().to_string())
+}
+
+/// SHA-256 of a file, in lowercase hexadecimal.
+pub fn hash_file(file: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(file)?, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+fn download(
+    url: &str,
+    file: &Path,
+    timeout: Option<Duration>,
+    pb: Option<&ProgressBar>,
+) -> Result<(), DownloadError> {
+    if let Some(source) = url.strip_prefix("file://") {
+        fs::copy(source, file)?;
+        return Ok(());
+    }
+
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(DownloadError {
+            message: "Unsupported URL, expected file://, http:// or https://".to_string(),
+            retryable: false,
+        });
+    }
+
+    let mut agent = ureq::AgentBuilder::new();
+    if let Some(timeout) = timeout {
+        agent = agent.timeout(timeout);
+    }
+    let response = agent.build().get(url).call().map_err(|e| {
+        let retryable = match &e {
+            ureq::Error::Status(status, _) => *status == 429 || *status >= 500,
+            ureq::Error::Transport(_) => true,
+        };
+        DownloadError {
+            message: e.to_string(),
+            retryable,
+        }
+    })?;
+
+    let total: Option<u64> = response
+        .header("Content-Length")
+        .and_then(|length| length.parse().ok());
+    let mut reader = response.into_reader();
+    let mut writer = File::create(file)?;
+    let mut buffer = vec![0; 64 * 1024];
+    let mut received = 0;
+    loop {
+        let read = reader.read(&mut buffer).map_err(|e| DownloadError {
+            message: e.to_string(),
+            retryable: true,
+        })?;
+        if read == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..read])?;
+        received += read as u64;
+        if let (Some(pb), Some(total)) = (pb, total.filter(|total| *total > 0)) {
+            pb.set_position(received * 100 / total);
+        }
+    }
+    Ok(())
+}
+
+/// Unpack the archive next to the project, then swap it with the current content.
+fn replace(file: &Path, repo_path: &Path, archive: &Archive) -> io::Result<()> {
+    let mut staging = repo_path.as_os_str().to_owned();
+    staging.push(".unpack");
+    let staging = PathBuf::from(staging);
+
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    fs::create_dir_all(&staging)?;
+
+    if let Err(e) = unpack(file, &staging, archive.strip_components) {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+    fs::write(staging.join(ARCHIVE_MARKER), &archive.sha256)?;
+
+    if repo_path.exists() {
+        fs::remove_dir_all(repo_path)?;
+    }
+    fs::rename(&staging, repo_path)
+}
+
+/// Unpack a tar.gz, tar.xz or zip file, detected from its content, into `dest`.
+pub fn unpack(file: &Path, dest: &Path, strip_components: usize) -> io::Result<()> {
+    let mut magic = [0; 6];
+    let read = File::open(file)?.read(&mut magic)?;
+    let magic = &magic[..read];
+
+    if magic.starts_with(GZIP_MAGIC) {
+        let archive = tar::Archive::new(GzDecoder::new(File::open(file)?));
+        unpack_tar(archive, dest, strip_components)
+    } else if magic.starts_with(XZ_MAGIC) {
+        let archive = tar::Archive::new(XzDecoder::new(File::open(file)?));
+        unpack_tar(archive, dest, strip_components)
+    } else if magic.starts_with(ZIP_MAGIC) {
+        unpack_zip(File::open(file)?, dest, strip_components)
+    } else {
+        let msg = "Unsupported archive format, expected tar.gz, tar.xz or zip";
+        Err(io::Error::new(io::ErrorKind::InvalidData, msg))
+    }
+}
+
+fn unpack_tar<R: Read>(
+    mut archive: tar::Archive<R>,
+    dest: &Path,
+    strip_components: usize,
+) -> io::Result<()> {
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_pax_global_extensions() || entry_type.is_pax_local_extensions() {
+            continue;
+        }
+
+        let Some(target) = strip_path(&entry.path()?, strip_components)? else {
+            continue;
+        };
+        check_symlinks(dest, &target)?;
+        let target = dest.join(target);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Hard links point to another entry of the archive
+        if entry_type.is_hard_link() {
+            let link = entry.link_name()?.map(|link| link.into_owned());
+            if let Some(source) = link.and_then(|link| strip_path(&link, strip_components).ok()?) {
+                check_symlinks(dest, &source)?;
+                fs::hard_link(dest.join(source), &target)?;
+            }
+            continue;
+        }
+        entry.unpack(&target)?;
+    }
+    Ok(())
+}
+
+fn unpack_zip(file: File, dest: &Path, strip_components: usize) -> io::Result<()> {
+    let mut archive = zip::ZipArchive::new(file)?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let Some(name) = entry.enclosed_name() else {
+            let msg = format!("Unsafe path in archive: {}", entry.name());
+            return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+        };
+        let Some(target) = strip_path(&name, strip_components)? else {
+            continue;
+        };
+        let target = dest.join(target);
+
+        if entry.is_dir() {
+            fs::create_dir_all(&target)?;
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        io::copy(&mut entry, &mut File::create(&target)?)?;
+
+        #[cfg(unix)]
+        if let Some(mode) = entry.unix_mode() {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&target, fs::Permissions::from_mode(mode & 0o777))?;
+        }
+    }
+    Ok(())
+}
+
+/// Refuse the entries whose parent directories include a symbolic link unpacked from the archive:
+/// it may point outside of `dest`.
+fn check_symlinks(dest: &Path, path: &Path) -> io::Result<()> {
+    let mut parent = dest.to_path_buf();
+    for component in path.parent().into_iter().flat_map(Path::components) {
+        parent.push(component);
+        if fs::symlink_metadata(&parent).is_ok_and(|metadata| metadata.is_symlink()) {
+            let msg = format!("Unsafe path in archive: {}", path.display());
+            return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+        }
+    }
+    Ok(())
+}
+
+/// Remove the first `strip_components` components of an archive entry path, `None` when nothing
+/// is left. Entries escaping the destination are refused.
+fn strip_path(path: &Path, strip_components: usize) -> io::Result<Option<PathBuf>> {
+    let mut components = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => components.push(name),
+            Component::CurDir => {}
+            _ => {
+                let msg = format!("Unsafe path in archive: {}", path.display());
+                return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+            }
+        }
+    }
+    match components.len() > strip_components {
+        true => Ok(Some(components[strip_components..].iter().collect())),
+        false => Ok(None),
+    }
+}
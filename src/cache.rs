@@ -0,0 +1,133 @@
+use crate::application::{ManifestError, STATE_DIR};
+use crate::project::Project;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::process::Command;
+
+/// Environment variable overriding the cache directory
+pub const CACHE_ENV: &str = "COLLIGO_CACHE";
+const CACHE_DIR: &str = "colligo";
+/// Mirrors of the repositories checked out at several paths of a workspace, in its state directory
+const STORE_DIR: &str = "objects";
+const MIRROR_EXTENSION: &str = "git";
+
+/// Bare mirrors in `<cache>/<host>/<name>.git`, shared by the workspaces of the user or by the
+/// projects of a workspace checking out the same repository. The repositories borrow the objects
+/// of their mirror through `objects/info/alternates`, so that each repository is downloaded once.
+pub struct Cache {
+    dir: PathBuf,
+    /// Whether the mirrors are in the workspace, borrowed through relative paths to move with it
+    in_workspace: bool,
+    /// Whether each mirror was updated by this synchronization, `None` until it is tried
+    updated: Mutex<HashMap<PathBuf, Arc<tokio::sync::Mutex<Option<bool>>>>>,
+}
+
+impl Cache {
+    pub fn new(dir: PathBuf) -> Self {
+        let dir = std::path::absolute(&dir).unwrap_or(dir);
+        Self {
+            dir,
+            in_workspace: false,
+            updated: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Store of the workspace in `manifest_dir`, for the repositories checked out at several paths.
+    pub fn new_workspace_store(manifest_dir: &Path) -> Self {
+        let mut store = Self::new(manifest_dir.join(STATE_DIR).join(STORE_DIR));
+        store.in_workspace = true;
+        store
+    }
+
+    /// `$COLLIGO_CACHE`, else `$XDG_CACHE_HOME/colligo`, else `~/.cache/colligo`.
+    pub fn get_default_dir() -> Option<PathBuf> {
+        if let Some(dir) = env::var_os(CACHE_ENV).filter(|dir| !dir.is_empty()) {
+            return Some(PathBuf::from(dir));
+        }
+        if let Some(dir) = env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+            return Some(PathBuf::from(dir).join(CACHE_DIR));
+        }
+        env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache").join(CACHE_DIR))
+    }
+
+    pub fn get_dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn is_in_workspace(&self) -> bool {
+        self.in_workspace
+    }
+
+    pub fn get_mirror_path(&self, project: &Project) -> PathBuf {
+        get_mirror_path(&self.dir, project)
+    }
+
+    /// Exclusive access to a mirror, holding whether it was updated by this synchronization.
+    pub async fn lock(&self, mirror: &Path) -> tokio::sync::OwnedMutexGuard<Option<bool>> {
+        let entry = self
+            .updated
+            .lock()
+            .expect("Cache lock poisoned")
+            .entry(mirror.to_path_buf())
+            .or_default()
+            .clone();
+        entry.lock_owned().await
+    }
+}
+
+/// Mirror of the repository of `project` in `dir`: `<dir>/<host>/<name>.git`.
+pub fn get_mirror_path(dir: &Path, project: &Project) -> PathBuf {
+    let name = format!("{}.{MIRROR_EXTENSION}", project.get_name());
+    dir.join(project.get_uri()).join(name)
+}
+
+/// Repack the mirrors of the cache. Unreachable objects are kept: the workspaces may still
+/// borrow them. Return the path of each mirror.
+pub async fn gc(cache_dir: &Path) -> Result<Vec<PathBuf>, ManifestError> {
+    let mut mirrors = Vec::new();
+    find_mirrors(cache_dir, &mut mirrors).map_err(|e| {
+        let msg = format!("{}: {e}", cache_dir.display());
+        ManifestError::FailedToCleanCache(msg)
+    })?;
+    mirrors.sort();
+
+    for mirror in mirrors.iter() {
+        let output = Command::new("git")
+            .current_dir(mirror)
+            .args(["gc", "--quiet", "--prune=never"])
+            .output()
+            .await
+            .map_err(|e| ManifestError::FailedToCleanCache(e.to_string()))?;
+        if !output.status.success() {
+            let msg = format!(
+                "{}\n{}",
+                mirror.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            return Err(ManifestError::FailedToCleanCache(msg));
+        }
+    }
+    Ok(mirrors)
+}
+
+fn find_mirrors(dir: &Path, mirrors: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if !dir.exists() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let is_mirror =
+            path.extension().is_some_and(|e| e == MIRROR_EXTENSION) && path.join("HEAD").exists();
+        match is_mirror {
+            true => mirrors.push(path),
+            false => find_mirrors(&path, mirrors)?,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,186 @@
+use crate::application::ManifestError;
+use crate::manifest_diff::{normalize_path, ManifestDiff, ProjectChange};
+use crate::project::Project;
+use crate::version_control::{CommitInfo, GitVersionControl};
+use serde::Serialize;
+use std::path::Path;
+
+/// Commits between the old and new revision of a project.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProjectLog {
+    pub path: String,
+    pub uri: String,
+    pub old: String,
+    pub new: String,
+    /// True when the old revision is not an ancestor of the new one.
+    pub rewind: bool,
+    /// Commits added by the new revision.
+    pub commits: Vec<CommitInfo>,
+    /// Commits of the old revision no longer reachable from the new one. Only set on rewind.
+    pub removed_commits: Vec<CommitInfo>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Changelog {
+    projects: Vec<ProjectLog>,
+}
+
+impl Changelog {
+    /// Collect the commits of every git project whose revision changed between two manifests.
+    /// Repositories are read from the checkouts found in `manifest_dir` and fetched when a
+    /// revision is missing locally. Errors are reported per project.
+    pub async fn new(manifest_dir: &Path, old: &[Project], new: &[Project]) -> Self {
+        let vcs = GitVersionControl::new();
+        let mut projects = Vec::new();
+
+        let diff = ManifestDiff::new(old, new);
+        for change in diff.get_changes() {
+            let ProjectChange::Revision {
+                path,
+                old: old_revision,
+                new: new_revision,
+            } = change
+            else {
+                continue;
+            };
+
+            let project = new
+                .iter()
+                .find(|p| normalize_path(p.get_path()) == *path)
+                .expect("Changed project not found in new manifest");
+            // Archives and Mercurial projects have no git history
+            if !project.is_git() {
+                continue;
+            }
+
+            let mut log = ProjectLog {
+                path: path.clone(),
+                uri: format!("{}/{}", project.get_uri(), project.get_name()),
+                old: old_revision.clone(),
+                new: new_revision.clone(),
+                rewind: false,
+                commits: Vec::new(),
+                removed_commits: Vec::new(),
+                error: None,
+            };
+
+            if let Err(e) = collect_commits(
+                &vcs,
+                manifest_dir,
+                project,
+                &mut log,
+                old_revision,
+                new_revision,
+            )
+            .await
+            {
+                log.error = Some(e.to_string());
+            }
+            projects.push(log);
+        }
+
+        Self { projects }
+    }
+
+    pub fn get_projects(&self) -> &Vec<ProjectLog> {
+        &self.projects
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut output = String::from("# Changelog\n");
+
+        if self.projects.is_empty() {
+            output.push_str("\nNo revision changed.\n");
+            return output;
+        }
+
+        for project in self.projects.iter() {
+            output.push_str(&format!(
+                "\n## {} ({})\n\n`{}` -> `{}`\n\n",
+                project.path, project.uri, project.old, project.new
+            ));
+
+            if let Some(error) = &project.error {
+                output.push_str(&format!("**Error:** {}\n", error.trim()));
+                continue;
+            }
+
+            if project.commits.is_empty() {
+                output.push_str("No new commits.\n");
+            }
+            for commit in project.commits.iter() {
+                output.push_str(&format_commit(commit));
+            }
+
+            if project.rewind {
+                output.push_str(&format!(
+                    "\n**Warning:** non-fast-forward change, {} commit(s) removed:\n\n",
+                    project.removed_commits.len()
+                ));
+                for commit in project.removed_commits.iter() {
+                    output.push_str(&format_commit(commit));
+                }
+            }
+        }
+        output
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Failed to serialize changelog")
+    }
+}
+
+async fn collect_commits(
+    vcs: &GitVersionControl,
+    manifest_dir: &Path,
+    project: &Project,
+    log: &mut ProjectLog,
+    old_revision: &str,
+    new_revision: &str,
+) -> Result<(), ManifestError> {
+    if !manifest_dir.join(project.get_path()).exists() {
+        let msg = format!("{}, project is not checked out", project.get_path());
+        return Err(ManifestError::FailedToGetLog(msg));
+    }
+
+    let mut old = vcs
+        .resolve_revision(manifest_dir, project, old_revision)
+        .await;
+    let mut new = vcs
+        .resolve_revision(manifest_dir, project, new_revision)
+        .await;
+
+    // Fetch only when a revision is unknown locally
+    if old.is_none() || new.is_none() {
+        vcs.fetch_all(manifest_dir, project).await?;
+        old = vcs
+            .resolve_revision(manifest_dir, project, old_revision)
+            .await;
+        new = vcs
+            .resolve_revision(manifest_dir, project, new_revision)
+            .await;
+    }
+
+    let (Some(old), Some(new)) = (old, new) else {
+        let msg = format!(
+            "{}, unable to find revision {} or {}",
+            project.get_path(),
+            old_revision,
+            new_revision
+        );
+        return Err(ManifestError::FailedToGetLog(msg));
+    };
+
+    log.commits = vcs.log(manifest_dir, project, &old, &new).await?;
+    log.rewind = !vcs.is_ancestor(manifest_dir, project, &old, &new).await?;
+    if log.rewind {
+        log.removed_commits = vcs.log(manifest_dir, project, &new, &old).await?;
+    }
+    Ok(())
+}
+
+fn format_commit(commit: &CommitInfo) -> String {
+    let short_id: String = commit.id.chars().take(7).collect();
+    format!("- {} {} ({})\n", short_id, commit.summary, commit.author)
+}
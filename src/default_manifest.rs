@@ -7,6 +7,9 @@ pub const DEFAULT_MANIFEST_FILE: &str = r#"<?xml version="1.0" encoding="UTF-8"
     <project name="repo/name" path="path/folder" revision="branch"/>
     <project name="repo/name" path="folder" revision="tag"/>
 
+    <!-- pushuri is the host used by push instead of uri. It can also be set in default -->
+    <project name="repo/name" path="fork" pushuri="hostname.com"/>
+
     <!-- It is possible to duplicate file using linkfile or copyfile -->
     <!-- It is also possible to copy recursively a directory using copydir -->
     <!-- src path is relative to the project path -->
@@ -0,0 +1,335 @@
+use crate::application::{Cancellation, DwlMode, ManifestError, XVersionControl};
+use crate::project::Project;
+use crate::version_control::{is_retryable, output, FetchStats, Retry};
+use indicatif::ProgressBar;
+use log::debug;
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+use std::time::Duration;
+use tokio::process::Command;
+
+const HGRC: &str = ".hg/hgrc";
+const PATHS_SECTION: &str = "[paths]";
+
+/// Version control backend for the projects with `vcs="hg"`, running the `hg` command.
+pub struct HgVersionControl {
+    retry: Retry,
+    /// Maximum duration of each hg invocation
+    timeout: Option<Duration>,
+    /// Stop the pulls in progress when cancelled
+    cancellation: Cancellation,
+}
+
+impl Default for HgVersionControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HgVersionControl {
+    pub fn new() -> Self {
+        Self {
+            retry: Retry::default(),
+            timeout: None,
+            cancellation: Cancellation::default(),
+        }
+    }
+
+    pub fn set_retry(&mut self, retry: Retry) {
+        self.retry = retry;
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn set_cancellation(&mut self, cancellation: Cancellation) {
+        self.cancellation = cancellation;
+    }
+
+    /// Return true if the project has modified or untracked files.
+    pub async fn has_local_changes(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+    ) -> Result<bool, ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+
+        self.run(&repo_path, &["status"])
+            .await
+            .map(|status| !status.trim().is_empty())
+            .map_err(|e| {
+                let msg = format!("{}\n{e}\n", project.get_path());
+                ManifestError::FailedToDetermineIfRepoIsModified(msg)
+            })
+    }
+
+    /// Return true if the project has changesets not yet pushed, i.e. not in the public phase.
+    pub async fn has_unpushed_commits(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+    ) -> Result<bool, ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+
+        self.run(
+            &repo_path,
+            &["log", "--rev", "not public()", "--template", "{node}\n"],
+        )
+        .await
+        .map(|nodes| !nodes.trim().is_empty())
+        .map_err(|e| {
+            let msg = format!("{}\n{e}\n", project.get_path());
+            ManifestError::FailedToDetermineIfRepoIsModified(msg)
+        })
+    }
+
+    /// Run hg in the repository, returning stdout or the error message.
+    async fn run(&self, repo_path: &Path, args: &[&str]) -> Result<String, String> {
+        let output = output(hg_command().current_dir(repo_path).args(args), self.timeout)
+            .await
+            .map_err(|e| format!("Failed to execute hg: {e}"))?;
+        get_stdout(output)
+    }
+}
+
+#[async_trait::async_trait]
+impl XVersionControl for HgVersionControl {
+    async fn init(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        mode: &DwlMode,
+    ) -> Result<(), ManifestError> {
+        let (url, push_url) = match mode {
+            DwlMode::HTTPS | DwlMode::Mirror(_) => {
+                (project.get_uri_hg_https(), project.get_push_uri_hg_https())
+            }
+            DwlMode::SSH => (project.get_uri_hg_ssh(), project.get_push_uri_hg_ssh()),
+        };
+        let repo_path = manifest_dir.join(project.get_path());
+
+        debug!(
+            "Initializing {} into {}",
+            project.get_name(),
+            repo_path.display()
+        );
+
+        let error = |e: String| {
+            let msg = format!("{}\n{e}\n", project.get_path());
+            ManifestError::FailedToInitialize(msg)
+        };
+
+        if !repo_path.join(".hg").exists() {
+            fs::create_dir_all(&repo_path).map_err(|e| error(e.to_string()))?;
+            self.run(&repo_path, &["init"]).await.map_err(error)?;
+        }
+
+        let hgrc = repo_path.join(HGRC);
+        let config = fs::read_to_string(&hgrc).unwrap_or_default();
+        let config = set_paths(&config, &url, push_url.as_deref());
+        fs::write(&hgrc, config).map_err(|e| error(e.to_string()))
+    }
+
+    /// Pull from the default path. Transient network errors are retried.
+    async fn fetch(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        pb: Option<&ProgressBar>,
+        lightweight: bool,
+    ) -> Result<FetchStats, ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+        let path = project.get_path();
+
+        debug!(
+            "Pulling {} into {} @ {}",
+            project.get_name(),
+            repo_path.display(),
+            project.get_revision()
+        );
+
+        // Only the ancestors of the revision are needed for a lightweight checkout
+        let mut args = vec!["pull"];
+        if lightweight {
+            args.extend(["--rev", project.get_revision()]);
+        }
+
+        let mut attempt = 1;
+        loop {
+            if let Some(pb) = pb {
+                pb.set_message(format!("{path} pull"));
+            }
+
+            let result = tokio::select! {
+                result = self.run(&repo_path, &args) => result,
+                _ = self.cancellation.cancelled() => {
+                    return Err(ManifestError::Interrupted(path.clone()));
+                }
+            };
+
+            match result {
+                Ok(_) => {
+                    if let Some(pb) = pb {
+                        pb.set_position(100);
+                        pb.set_message(format!("{path} complete"));
+                    }
+                    return Ok(FetchStats {
+                        attempts: attempt,
+                        bytes: None,
+                    });
+                }
+                Err(e) if is_retryable(&e) && attempt <= self.retry.retries => {
+                    let delay = self.retry.get_delay(attempt);
+                    debug!("Pulling {path} failed, retry in {delay:?}: {e}");
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => {}
+                        _ = self.cancellation.cancelled() => {
+                            return Err(ManifestError::Interrupted(path.clone()));
+                        }
+                    }
+                    attempt += 1;
+                }
+                Err(e) => {
+                    if let Some(pb) = pb {
+                        pb.set_message(format!("{path} ERROR"));
+                    }
+                    let mut msg = format!("{path}\n{e}\n");
+                    if attempt > 1 {
+                        msg.push_str(&format!("Failed after {attempt} attempts\n"));
+                    }
+                    return Err(ManifestError::FailedToCheckoutRepository(msg));
+                }
+            }
+        }
+    }
+
+    /// Update the working directory to the revision, without accessing the remote.
+    async fn update(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        pb: Option<&ProgressBar>,
+        force: bool,
+        _lightweight: bool,
+    ) -> Result<(), ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+        let path = project.get_path();
+
+        debug!(
+            "Updating {} into {} @ {}",
+            project.get_name(),
+            repo_path.display(),
+            project.get_revision()
+        );
+
+        if let Some(pb) = pb {
+            pb.set_message(format!("{path} update"));
+        }
+
+        // hg would merge the local changes into the revision
+        if !force && self.is_modified(manifest_dir, project).await? {
+            if let Some(pb) = pb {
+                pb.set_message(format!("{path} ERROR"));
+            }
+            let msg = format!("{path}, repository is dirty, please commit or shelve your changes");
+            return Err(ManifestError::FailedToCheckoutRepository(msg));
+        }
+
+        let mut args = vec!["update", "--rev", project.get_revision()];
+        if force {
+            args.push("--clean");
+        }
+        if let Err(e) = self.run(&repo_path, &args).await {
+            if let Some(pb) = pb {
+                pb.set_message(format!("{path} ERROR"));
+            }
+            let msg = format!("{path}\n{e}\n");
+            return Err(ManifestError::FailedToCheckoutRepository(msg));
+        }
+
+        if let Some(pb) = pb {
+            pb.set_message(format!("{path} complete"));
+            pb.finish();
+        }
+        Ok(())
+    }
+
+    async fn get_commit_id(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+    ) -> Result<String, ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+        self.run(&repo_path, &["log", "--rev", ".", "--template", "{node}"])
+            .await
+            .map(|node| node.trim().to_string())
+            .map_err(|e| {
+                let msg = format!("{}\n{e}\n", project.get_path());
+                ManifestError::FailedToGetCommitId(msg)
+            })
+    }
+
+    async fn is_modified(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+    ) -> Result<bool, ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+        // Modified, added, removed and deleted files, like git without the untracked files
+        self.run(
+            &repo_path,
+            &["status", "--modified", "--added", "--removed", "--deleted"],
+        )
+        .await
+        .map(|status| !status.trim().is_empty())
+        .map_err(|e| {
+            let msg = format!("{}\n{e}\n", project.get_path());
+            ManifestError::FailedToDetermineIfRepoIsModified(msg)
+        })
+    }
+}
+
+/// New hg command with the output independent of the user configuration and locale.
+fn hg_command() -> Command {
+    let mut command = Command::new("hg");
+    command.env("HGPLAIN", "1").kill_on_drop(true);
+    command
+}
+
+fn get_stdout(output: Output) -> Result<String, String> {
+    match output.status.success() {
+        true => Ok(String::from_utf8_lossy(&output.stdout).to_string()),
+        false => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+    }
+}
+
+/// Replace the `[paths]` section of an hgrc with the default and default-push paths, keeping the
+/// other sections.
+pub fn set_paths(config: &str, default: &str, default_push: Option<&str>) -> String {
+    let mut lines = Vec::new();
+    let mut in_paths = false;
+    for line in config.lines() {
+        let section = line.trim();
+        if section.starts_with('[') {
+            in_paths = section == PATHS_SECTION;
+        }
+        if !in_paths {
+            lines.push(line.to_string());
+        }
+    }
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    if !lines.is_empty() {
+        lines.push(String::new());
+    }
+
+    lines.push(PATHS_SECTION.to_string());
+    lines.push(format!("default = {default}"));
+    if let Some(default_push) = default_push {
+        lines.push(format!("default-push = {default_push}"));
+    }
+    lines.join("\n") + "\n"
+}
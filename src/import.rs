@@ -0,0 +1,133 @@
+use crate::application::{ManifestError, XVersionControl, STATE_DIR};
+use crate::project::Project;
+use crate::version_control::GitVersionControl;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Split a remote URL into the `uri` and `name` of a project.
+/// HTTPS, SSH (`ssh://git@host/name.git`) and scp-like (`git@host:name.git`) URLs are supported.
+/// The user and port are dropped since the project model only keeps the host.
+pub fn parse_remote_url(url: &str) -> Option<(String, String)> {
+    let url = url.trim().trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+
+    let (host, name) = match url.split_once("://") {
+        Some((_, rest)) => {
+            let (authority, name) = rest.split_once('/')?;
+            let host = authority.rsplit('@').next()?;
+            let host = host.split(':').next()?;
+            (host, name)
+        }
+        None => {
+            let (authority, name) = url.split_once(':')?;
+            if authority.contains('/') {
+                return None;
+            }
+            let host = authority.rsplit('@').next()?;
+            (host, name)
+        }
+    };
+
+    let name = name.trim_start_matches('/');
+    if host.is_empty() || name.is_empty() {
+        return None;
+    }
+    Some((host.to_string(), name.to_string()))
+}
+
+/// Create a project for each git repository found in `dir`, with its path relative to
+/// `manifest_dir`. The `uri` and `name` are read from `remote`, the revision is the checked out
+/// branch, or the commit if HEAD is detached. Repositories nested in another one are ignored.
+/// Return the projects and the repositories which could not be imported, with the reason.
+pub async fn import_projects(
+    dir: &Path,
+    manifest_dir: &Path,
+    remote: &str,
+) -> Result<(Vec<Project>, Vec<String>), ManifestError> {
+    let dir = canonicalize(dir)?;
+    let manifest_dir = canonicalize(manifest_dir)?;
+    if !dir.starts_with(&manifest_dir) {
+        let msg = format!(
+            "{} is not inside the manifest directory {}",
+            dir.display(),
+            manifest_dir.display()
+        );
+        return Err(ManifestError::FailedToImport(msg));
+    }
+
+    let mut repositories = Vec::new();
+    find_repositories(&dir, true, &mut repositories)?;
+    repositories.sort();
+
+    let vcs = GitVersionControl::new();
+    let mut projects = Vec::new();
+    let mut skipped = Vec::new();
+
+    for repository in repositories {
+        let path = repository
+            .strip_prefix(&manifest_dir)
+            .unwrap_or(&repository)
+            .to_string_lossy()
+            .to_string();
+        if path.is_empty() {
+            // The manifest directory is not a project of its own manifest
+            continue;
+        }
+
+        let checkout = Project::new(String::new(), String::new(), String::new(), path.clone());
+        let url = match vcs.get_remote_url(&manifest_dir, &checkout, remote).await {
+            Ok(url) => url,
+            Err(e) => {
+                skipped.push(format!("{path}: {}", e.to_string().trim()));
+                continue;
+            }
+        };
+        let Some((uri, name)) = parse_remote_url(&url) else {
+            skipped.push(format!("{path}: unsupported remote URL {url}"));
+            continue;
+        };
+
+        let revision = match vcs.get_current_branch(&manifest_dir, &checkout).await {
+            Some(branch) => branch,
+            None => vcs.get_commit_id(&manifest_dir, &checkout).await?,
+        };
+
+        projects.push(Project::new(uri, name, revision, path));
+    }
+
+    Ok((projects, skipped))
+}
+
+fn find_repositories(
+    dir: &Path,
+    is_root: bool,
+    repositories: &mut Vec<PathBuf>,
+) -> Result<(), ManifestError> {
+    if dir.join(".git").exists() {
+        repositories.push(dir.to_path_buf());
+        if !is_root {
+            return Ok(());
+        }
+    }
+
+    let entries = fs::read_dir(dir).map_err(|e| {
+        let msg = format!("Failed to read directory {}: {e}", dir.display());
+        ManifestError::FailedToImport(msg)
+    })?;
+
+    for entry in entries.flatten() {
+        let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+        let name = entry.file_name();
+        if is_dir && name != ".git" && name != STATE_DIR {
+            find_repositories(&entry.path(), false, repositories)?;
+        }
+    }
+    Ok(())
+}
+
+fn canonicalize(path: &Path) -> Result<PathBuf, ManifestError> {
+    path.canonicalize().map_err(|e| {
+        let msg = format!("{}: {e}", path.display());
+        ManifestError::FailedToImport(msg)
+    })
+}
@@ -1,6 +1,19 @@
 pub mod application;
+pub mod archive_version_control;
+pub mod cache;
+pub mod changelog;
 pub mod default_manifest;
+pub mod hg_version_control;
+pub mod import;
+#[cfg(feature = "libgit2")]
+pub mod libgit2_version_control;
+pub mod manifest_diff;
+pub mod manifest_edit;
 pub mod project;
+pub mod prune;
+pub mod sync_report;
+pub mod topic;
 pub mod version;
 pub mod version_control;
+pub mod workspace_state;
 pub mod xml_parser;
@@ -0,0 +1,565 @@
+use crate::application::{
+    Cancellation, DwlMode, ManifestError, XVersionControl, BACKEND_GIT, BACKEND_LIBGIT2,
+};
+use crate::project::Project;
+use crate::version_control::{
+    get_push_url, get_url, is_retryable, show_progress, FetchStats, Progress, Retry,
+};
+use git2::build::CheckoutBuilder;
+use git2::{
+    AutotagOption, BranchType, Cred, CredentialType, ErrorClass, ErrorCode, FetchOptions,
+    FetchPrune, Oid, RemoteCallbacks, Repository, Status, StatusOptions,
+};
+use indicatif::ProgressBar;
+use log::debug;
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const ORIGIN: &str = "origin";
+const MAX_CREDENTIAL_ATTEMPTS: u32 = 3;
+
+/// Version control backend built on libgit2, without spawning `git`. The progress is reported by
+/// typed callbacks instead of parsing the output of the command line.
+pub struct Libgit2VersionControl {
+    retry: Retry,
+    /// Maximum duration of each fetch, checked while receiving data
+    timeout: Option<Duration>,
+    /// Stop the fetches in progress when cancelled
+    cancellation: Cancellation,
+}
+
+/// Refuse the projects using an attribute only implemented by the git backend, rather than
+/// checking out the whole repository.
+fn check_attributes(project: &Project) -> Result<(), ManifestError> {
+    let attribute = if !project.get_sparse_paths().is_empty() {
+        "<sparse>"
+    } else if project.get_clone_depth().is_some() {
+        "clone-depth"
+    } else if project.get_filter().is_some() {
+        "filter"
+    } else {
+        return Ok(());
+    };
+    let msg = format!(
+        "{BACKEND_LIBGIT2}, {} uses {attribute}, only supported by the {BACKEND_GIT} backend",
+        project.get_path()
+    );
+    Err(ManifestError::UnsupportedBackend(msg))
+}
+
+/// Error of a fetch attempt.
+struct FetchError {
+    message: String,
+    retryable: bool,
+}
+
+impl Default for Libgit2VersionControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Libgit2VersionControl {
+    pub fn new() -> Self {
+        Self {
+            retry: Retry::default(),
+            timeout: None,
+            cancellation: Cancellation::default(),
+        }
+    }
+
+    pub fn set_retry(&mut self, retry: Retry) {
+        self.retry = retry;
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn set_cancellation(&mut self, cancellation: Cancellation) {
+        self.cancellation = cancellation;
+    }
+}
+
+#[async_trait::async_trait]
+impl XVersionControl for Libgit2VersionControl {
+    async fn init(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        mode: &DwlMode,
+    ) -> Result<(), ManifestError> {
+        check_attributes(project)?;
+        let url = get_url(project, mode);
+        let push_url = get_push_url(project, mode);
+        let repo_path = manifest_dir.join(project.get_path());
+
+        debug!(
+            "Initializing {} into {}",
+            project.get_name(),
+            repo_path.display()
+        );
+
+        blocking(move || {
+            let repo = match Repository::open(&repo_path) {
+                Ok(repo) => repo,
+                Err(_) => Repository::init(&repo_path)?,
+            };
+            match repo.find_remote(ORIGIN) {
+                Ok(remote) if remote.url() == Some(url.as_str()) => {}
+                Ok(_) => repo.remote_set_url(ORIGIN, &url)?,
+                Err(_) => {
+                    repo.remote(ORIGIN, &url)?;
+                }
+            }
+            match push_url {
+                Some(push_url) => repo.remote_set_pushurl(ORIGIN, Some(&push_url)),
+                None => match repo.find_remote(ORIGIN)?.pushurl() {
+                    Some(_) => repo.remote_set_pushurl(ORIGIN, None),
+                    None => Ok(()),
+                },
+            }
+        })
+        .await
+        .map_err(|e| ManifestError::FailedToInitialize(format!("{}\n{e}\n", project.get_path())))
+    }
+
+    /// Fetch the manifest revision from the remote. Transient network errors are retried.
+    async fn fetch(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        pb: Option<&ProgressBar>,
+        lightweight: bool,
+    ) -> Result<FetchStats, ManifestError> {
+        check_attributes(project)?;
+        let repo_path = manifest_dir.join(project.get_path());
+
+        debug!(
+            "Fetching {} into {} @ {}",
+            project.get_name(),
+            repo_path.display(),
+            project.get_revision()
+        );
+
+        let path = project.get_path().clone();
+        let mut attempt = 1;
+        loop {
+            if let Some(pb) = pb {
+                pb.set_message(format!("{path} fetch"));
+            }
+
+            let result = {
+                let repo_path = repo_path.clone();
+                let revision = project.get_revision().clone();
+                let pb = pb.cloned();
+                let path = path.clone();
+                let timeout = self.timeout;
+                let cancellation = self.cancellation.clone();
+                tokio::task::spawn_blocking(move || {
+                    fetch_origin(
+                        &repo_path,
+                        &revision,
+                        lightweight,
+                        pb.as_ref(),
+                        &path,
+                        timeout,
+                        &cancellation,
+                    )
+                })
+                .await
+                .expect("Failed to join fetch task")
+            };
+
+            match result {
+                Ok(bytes) => {
+                    if let Some(pb) = pb {
+                        pb.set_position(100);
+                        pb.set_message(format!("{path} complete"));
+                    }
+                    return Ok(FetchStats {
+                        attempts: attempt,
+                        bytes: Some(bytes),
+                    });
+                }
+                Err(_) if self.cancellation.is_cancelled() => {
+                    return Err(ManifestError::Interrupted(path));
+                }
+                Err(e) if e.retryable && attempt <= self.retry.retries => {
+                    let delay = self.retry.get_delay(attempt);
+                    debug!("Fetching {path} failed, retry in {delay:?}: {}", e.message);
+                    if let Some(pb) = pb {
+                        let retries = self.retry.retries;
+                        pb.set_message(format!("{path} retry {attempt}/{retries}"));
+                    }
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => {}
+                        _ = self.cancellation.cancelled() => {
+                            return Err(ManifestError::Interrupted(path));
+                        }
+                    }
+                    attempt += 1;
+                }
+                Err(e) => {
+                    if let Some(pb) = pb {
+                        pb.set_message(format!("{path} ERROR"));
+                    }
+                    let mut msg = format!("{path}\n{}\n", e.message);
+                    if attempt > 1 {
+                        msg.push_str(&format!("Failed after {attempt} attempts\n"));
+                    }
+                    return Err(ManifestError::FailedToCheckoutRepository(msg));
+                }
+            }
+        }
+    }
+
+    /// Update the working tree to the fetched revision, without accessing the remote.
+    async fn update(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        pb: Option<&ProgressBar>,
+        force: bool,
+        lightweight: bool,
+    ) -> Result<(), ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+
+        debug!(
+            "Checking out {} into {} @ {}",
+            project.get_name(),
+            repo_path.display(),
+            project.get_revision()
+        );
+
+        let path = project.get_path().clone();
+        let revision = project.get_revision().clone();
+        let pb = pb.cloned();
+        if let Some(pb) = &pb {
+            pb.set_message(format!("{path} checkout"));
+        }
+
+        let result = {
+            let pb = pb.clone();
+            let path = path.clone();
+            tokio::task::spawn_blocking(move || {
+                update_working_tree(
+                    &repo_path,
+                    &revision,
+                    force,
+                    lightweight,
+                    pb.as_ref(),
+                    &path,
+                )
+            })
+            .await
+            .expect("Failed to join checkout task")
+        };
+
+        match result {
+            Ok(()) => {
+                if let Some(pb) = pb {
+                    pb.set_message(format!("{path} complete"));
+                    pb.finish();
+                }
+                Ok(())
+            }
+            Err(msg) => {
+                if let Some(pb) = pb {
+                    pb.set_message(format!("{path} ERROR"));
+                }
+                Err(ManifestError::FailedToCheckoutRepository(msg))
+            }
+        }
+    }
+
+    async fn get_commit_id(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+    ) -> Result<String, ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+
+        debug!(
+            "Getting commit id for {} into {}",
+            project.get_name(),
+            repo_path.display()
+        );
+
+        blocking(move || {
+            let repo = Repository::open(&repo_path)?;
+            let commit = repo.head()?.peel_to_commit()?;
+            Ok(commit.id().to_string())
+        })
+        .await
+        .map_err(|e| ManifestError::FailedToGetCommitId(format!("{}\n{e}\n", project.get_path())))
+    }
+
+    async fn is_modified(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+    ) -> Result<bool, ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+
+        blocking(move || {
+            let repo = Repository::open(&repo_path)?;
+            is_dirty(&repo)
+        })
+        .await
+        .map_err(|e| {
+            let msg = format!("{}\n{e}\n", project.get_path());
+            ManifestError::FailedToDetermineIfRepoIsModified(msg)
+        })
+    }
+}
+
+/// Run a libgit2 operation on the blocking thread pool.
+async fn blocking<T, F>(operation: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, git2::Error> + Send + 'static,
+{
+    tokio::task::spawn_blocking(operation)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.message().to_string())
+}
+
+/// Fetch `revision` from origin, all branches and tags unless `lightweight`.
+/// Return the number of bytes received.
+fn fetch_origin(
+    repo_path: &PathBuf,
+    revision: &str,
+    lightweight: bool,
+    pb: Option<&ProgressBar>,
+    path: &str,
+    timeout: Option<Duration>,
+    cancellation: &Cancellation,
+) -> Result<u64, FetchError> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let timed_out = Cell::new(false);
+    let credential_attempts = Cell::new(0);
+
+    let result = (|| {
+        let repo = Repository::open(repo_path)?;
+        let config = repo.config()?;
+        let mut remote = repo.find_remote(ORIGIN)?;
+
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.transfer_progress(|stats| {
+            let progress = match stats.received_objects() < stats.total_objects() {
+                true => Progress::Receiving {
+                    received: stats.received_objects(),
+                    total: stats.total_objects(),
+                    bytes: stats.received_bytes(),
+                },
+                false => Progress::Resolving {
+                    resolved: stats.indexed_deltas(),
+                    total: stats.total_deltas(),
+                },
+            };
+            show_progress(pb, path, &progress);
+
+            // Returning false aborts the fetch
+            if deadline.is_some_and(|deadline| Instant::now() > deadline) {
+                timed_out.set(true);
+                return false;
+            }
+            !cancellation.is_cancelled()
+        });
+        callbacks.credentials(|url, username, allowed| {
+            credential_attempts.set(credential_attempts.get() + 1);
+            if credential_attempts.get() > MAX_CREDENTIAL_ATTEMPTS {
+                return Err(git2::Error::new(
+                    ErrorCode::Auth,
+                    ErrorClass::Net,
+                    format!("Authentication failed for '{url}'"),
+                ));
+            }
+            if allowed.contains(CredentialType::SSH_KEY) {
+                Cred::ssh_key_from_agent(username.unwrap_or("git"))
+            } else if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+                Cred::credential_helper(&config, url, username)
+            } else {
+                Cred::default()
+            }
+        });
+
+        let mut options = FetchOptions::new();
+        options
+            .remote_callbacks(callbacks)
+            .download_tags(AutotagOption::All)
+            .prune(FetchPrune::On);
+
+        if lightweight {
+            options.depth(1);
+            remote.fetch(&[revision], Some(&mut options), None)?;
+        } else {
+            // Empty refspecs use the ones configured for origin
+            let refspecs: [&str; 0] = [];
+            remote.fetch(&refspecs, Some(&mut options), None)?;
+        }
+        Ok(remote.stats().received_bytes() as u64)
+    })();
+
+    result.map_err(|e: git2::Error| {
+        if timed_out.get() {
+            let timeout = timeout.unwrap_or_default().as_secs_f32();
+            return FetchError {
+                message: format!("git timed out after {timeout}s"),
+                retryable: true,
+            };
+        }
+        FetchError {
+            message: e.message().to_string(),
+            retryable: e.code() != ErrorCode::Auth
+                && (e.class() == ErrorClass::Net || is_retryable(e.message())),
+        }
+    })
+}
+
+/// Check out `revision`, a local branch, a branch of origin, a tag or a commit.
+/// A branch is fast-forwarded to its upstream. Local modifications are kept unless `force`.
+fn update_working_tree(
+    repo_path: &PathBuf,
+    revision: &str,
+    force: bool,
+    lightweight: bool,
+    pb: Option<&ProgressBar>,
+    path: &str,
+) -> Result<(), String> {
+    let error = |e: git2::Error| format!("{path}\n{}\n", e.message());
+
+    let repo = Repository::open(repo_path).map_err(error)?;
+
+    let checkout = |oid: Oid, head: Option<&str>| -> Result<(), git2::Error> {
+        let object = repo.find_object(oid, None)?;
+        let mut builder = CheckoutBuilder::new();
+        if force {
+            builder.force();
+        } else {
+            builder.safe();
+        }
+        builder.progress(|_, completed, total| {
+            show_progress(pb, path, &Progress::Checkout { completed, total });
+        });
+        repo.checkout_tree(&object, Some(&mut builder))?;
+        match head {
+            Some(reference) => repo.set_head(reference),
+            None => repo.set_head_detached(oid),
+        }
+    };
+
+    if lightweight && !force {
+        let oid = repo
+            .revparse_single("FETCH_HEAD")
+            .and_then(|o| o.peel_to_commit())
+            .map_err(error)?
+            .id();
+        checkout(oid, None).map_err(error)?;
+    } else {
+        // Create the local branch from origin on the first checkout, like `git checkout`
+        let local = match repo.find_branch(revision, BranchType::Local) {
+            Ok(branch) => Some(branch),
+            Err(_) => match repo.find_branch(&format!("{ORIGIN}/{revision}"), BranchType::Remote) {
+                Ok(remote) => {
+                    let commit = remote.get().peel_to_commit().map_err(error)?;
+                    let mut branch = repo.branch(revision, &commit, false).map_err(error)?;
+                    branch
+                        .set_upstream(Some(&format!("{ORIGIN}/{revision}")))
+                        .map_err(error)?;
+                    Some(branch)
+                }
+                Err(_) => None,
+            },
+        };
+
+        match local {
+            Some(branch) => {
+                let reference = branch
+                    .get()
+                    .name()
+                    .ok_or_else(|| format!("{path}\nInvalid branch name {revision}\n"))?
+                    .to_string();
+                let oid = branch.get().peel_to_commit().map_err(error)?.id();
+                checkout(oid, Some(&reference)).map_err(error)?;
+            }
+            None => {
+                let oid = repo
+                    .revparse_single(revision)
+                    .and_then(|o| o.peel_to_commit())
+                    .map_err(error)?
+                    .id();
+                checkout(oid, None).map_err(error)?;
+            }
+        }
+    }
+
+    if is_dirty(&repo).map_err(error)? {
+        return Err(format!(
+            "{path}, repository is dirty, please commit or stash your changes"
+        ));
+    }
+
+    fast_forward(&repo, force, pb, path).map_err(error)
+}
+
+/// Fast-forward the checked out branch to its upstream, if any.
+fn fast_forward(
+    repo: &Repository,
+    force: bool,
+    pb: Option<&ProgressBar>,
+    path: &str,
+) -> Result<(), git2::Error> {
+    let head = repo.head()?;
+    if !head.is_branch() {
+        return Ok(());
+    }
+    let Some(name) = head.shorthand() else {
+        return Ok(());
+    };
+    let branch = repo.find_branch(name, BranchType::Local)?;
+    let Ok(upstream) = branch.upstream() else {
+        return Ok(());
+    };
+
+    let local = head.peel_to_commit()?.id();
+    let target = upstream.get().peel_to_commit()?.id();
+    if local == target || repo.graph_descendant_of(local, target)? {
+        return Ok(());
+    }
+    if !repo.graph_descendant_of(target, local)? {
+        return Err(git2::Error::from_str(
+            "fatal: Not possible to fast-forward, aborting.",
+        ));
+    }
+
+    let object = repo.find_object(target, None)?;
+    let mut builder = CheckoutBuilder::new();
+    if force {
+        builder.force();
+    } else {
+        builder.safe();
+    }
+    builder.progress(|_, completed, total| {
+        show_progress(pb, path, &Progress::Checkout { completed, total });
+    });
+    repo.checkout_tree(&object, Some(&mut builder))?;
+
+    let reference = head.name().unwrap_or("HEAD").to_string();
+    repo.find_reference(&reference)?
+        .set_target(target, "colligo: fast-forward")?;
+    Ok(())
+}
+
+/// Return true if tracked files are modified, like `git status --untracked-files=no`.
+fn is_dirty(repo: &Repository) -> Result<bool, git2::Error> {
+    let mut options = StatusOptions::new();
+    options.include_untracked(false).include_ignored(false);
+    let statuses = repo.statuses(Some(&mut options))?;
+    Ok(statuses
+        .iter()
+        .any(|entry| entry.status() != Status::CURRENT))
+}
@@ -1,11 +1,34 @@
-use clap::{Arg, ArgAction, Command};
+use clap::builder::RangedU64ValueParser;
+use clap::{Arg, ArgAction, ArgMatches, Command};
 use colligo::application::{
-    assert_dependencies, generate_default_manifest, get_projects_status, list_projects_path,
-    save_file, DwlMode, ManifestInstance, APP_NAME, FORCE, GENERATE_MANIFEST, HTTPS, LIGHT, LIST,
-    MANIFEST_INPUT, MANIFEST_INPUT_DEFAULT, PIN, QUIET, STATUS, SYNC,
+    assert_dependencies, commit_projects, deepen_projects, generate_default_manifest,
+    get_projects_status, list_projects_path, push_tag, save_file, tag_projects, Backend,
+    Cancellation, DwlMode, Jobs, ManifestError, ManifestInstance, ManifestParser, ABANDON, ADD,
+    APP_NAME, ATTRIBUTES, AT_REVISION, BACKEND, BACKEND_GIT, BACKEND_LIBGIT2, BRANCH, CACHE,
+    COMMIT, DEEPEN, DEPTH, DIFF, DRY_RUN, FAILED, FAIL_FAST, FORCE, FORMAT, FORMAT_JSON,
+    FORMAT_MARKDOWN, FORMAT_TEXT, FROM_MIRROR, FULL, GC, GENERATE_MANIFEST, HTTPS, IMPORT,
+    IMPORT_DIR, JOBS, JOBS_CHECKOUT, JOBS_NETWORK, JUNIT, KEEP_GOING, LIGHT, LIST, LOG,
+    MANIFEST_INPUT, MANIFEST_INPUT_DEFAULT, MESSAGE, MIRROR, MIRROR_DIR, NEW_MANIFEST,
+    OLD_MANIFEST, OUTPUT, PIN, PROJECT, PROJECTS, PROJECT_PATH, PRUNE, PUSH, QUIET, REMOTE, REMOVE,
+    RETRIES, REVISION, SET, START, STATUS, SYNC, TAG, TAG_NAME, TIMEOUT,
 };
+use colligo::cache::{gc, Cache, CACHE_ENV};
+use colligo::changelog::Changelog;
+use colligo::import::import_projects;
+use colligo::manifest_diff::ManifestDiff;
+use colligo::manifest_edit::{
+    add_project, parse_assignments, remove_project, set_project_attributes, split_project_spec,
+};
+use colligo::prune::prune_projects;
+use colligo::topic::{abandon_topic, push_topic, start_topic};
+use colligo::version_control::Retry;
+use colligo::workspace_state::WorkspaceState;
+use colligo::xml_parser::XmlParser;
 use simple_logger::SimpleLogger;
 use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 const DEBUG_OPTION: &str = "debug";
 const APP_VERSION: &str = concat!("v", env!("CARGO_PKG_VERSION"), "-", env!("GIT_SHA"));
@@ -40,6 +63,7 @@ async fn main() {
         .long(MANIFEST_INPUT)
         .action(ArgAction::Set)
         .value_name("FILE")
+        .global(true)
         .help("Manifest file to use (default: manifest.xml)");
 
     // Sync option
@@ -53,8 +77,51 @@ async fn main() {
         .long(HTTPS)
         .action(ArgAction::SetTrue)
         .default_value("false")
+        .global(true)
         .help("Use HTTPS instead of SSH");
 
+    // Parallelism options
+    let jobs = Arg::new(JOBS)
+        .short('j')
+        .long(JOBS)
+        .action(ArgAction::Set)
+        .value_name("N")
+        .value_parser(RangedU64ValueParser::<usize>::new().range(1..))
+        .global(true)
+        .help("Number of projects processed in parallel (default: number of CPUs)");
+    let jobs_network = Arg::new(JOBS_NETWORK)
+        .long(JOBS_NETWORK)
+        .action(ArgAction::Set)
+        .value_name("N")
+        .value_parser(RangedU64ValueParser::<usize>::new().range(1..))
+        .global(true)
+        .help("Number of simultaneous fetches and pushes (default: --jobs)");
+    let jobs_checkout = Arg::new(JOBS_CHECKOUT)
+        .long(JOBS_CHECKOUT)
+        .action(ArgAction::Set)
+        .value_name("N")
+        .value_parser(RangedU64ValueParser::<usize>::new().range(1..))
+        .global(true)
+        .help("Number of simultaneous operations on the local checkouts (default: --jobs)");
+
+    // Retries option
+    let retries = Arg::new(RETRIES)
+        .long(RETRIES)
+        .action(ArgAction::Set)
+        .value_name("N")
+        .value_parser(clap::value_parser!(u32))
+        .global(true)
+        .help("Number of retries of a fetch failing with a transient network error (default: 3)");
+
+    // Timeout option
+    let timeout = Arg::new(TIMEOUT)
+        .long(TIMEOUT)
+        .action(ArgAction::Set)
+        .value_name("SECONDS")
+        .value_parser(RangedU64ValueParser::<u64>::new().range(1..))
+        .global(true)
+        .help("Abort a git operation running longer than SECONDS (default: no timeout)");
+
     // Light option
     let light = Arg::new(LIGHT)
         .long(LIGHT)
@@ -74,8 +141,70 @@ async fn main() {
         .long(FORCE)
         .action(ArgAction::SetTrue)
         .default_value("false")
+        .global(true)
         .help("Discard local changes and overwrite them with the remote version.");
 
+    // Prune option
+    let prune = Arg::new(PRUNE)
+        .long(PRUNE)
+        .action(ArgAction::SetTrue)
+        .default_value("false")
+        .help("With --sync, delete the projects removed from the manifest");
+
+    // Fail fast options
+    let fail_fast = Arg::new(FAIL_FAST)
+        .long(FAIL_FAST)
+        .action(ArgAction::SetTrue)
+        .overrides_with(KEEP_GOING)
+        .help("With --sync, stop all projects on the first error");
+
+    let keep_going = Arg::new(KEEP_GOING)
+        .long(KEEP_GOING)
+        .action(ArgAction::SetTrue)
+        .overrides_with(FAIL_FAST)
+        .help("With --sync, synchronize all projects despite errors (default)");
+
+    // Failed option
+    let failed = Arg::new(FAILED)
+        .long(FAILED)
+        .action(ArgAction::SetTrue)
+        .default_value("false")
+        .help(
+            "With --sync, synchronize only the projects which failed in the last synchronization",
+        );
+
+    // Backend option
+    let backend = Arg::new(BACKEND)
+        .long(BACKEND)
+        .action(ArgAction::Set)
+        .value_name("BACKEND")
+        .value_parser([BACKEND_GIT, BACKEND_LIBGIT2])
+        .global(true)
+        .help("Implementation of --sync, --pin and --status: git (default) or libgit2, if built with the libgit2 feature");
+
+    // Cache option
+    let cache = Arg::new(CACHE)
+        .long(CACHE)
+        .action(ArgAction::SetTrue)
+        .default_value("false")
+        .help(format!(
+            "With --sync, share the git objects through mirrors in ${CACHE_ENV}, or ~/.cache/colligo"
+        ));
+
+    // From mirror option
+    let from_mirror = Arg::new(FROM_MIRROR)
+        .long(FROM_MIRROR)
+        .action(ArgAction::Set)
+        .value_name("DIR")
+        .help("Fetch the git projects from the mirrors created in DIR by the mirror command");
+
+    // JUnit option
+    let junit = Arg::new(JUNIT)
+        .long(JUNIT)
+        .action(ArgAction::Set)
+        .value_name("FILE")
+        .help("With --sync, write the result of each project to FILE in JUnit XML format");
+
     // Pin option
     let pin = Arg::new(PIN)
         .long(PIN)
@@ -102,6 +231,262 @@ async fn main() {
         .default_value("false")
         .help("Enable debug logs");
 
+    // Diff subcommand
+    let diff = Command::new(DIFF)
+        .about("Compare the projects of two manifest files")
+        .arg(
+            Arg::new(OLD_MANIFEST)
+                .required(true)
+                .value_name("OLD")
+                .help("Reference manifest file"),
+        )
+        .arg(
+            Arg::new(NEW_MANIFEST)
+                .required(true)
+                .value_name("NEW")
+                .help("Manifest file compared to the reference"),
+        )
+        .arg(format_arg(&[FORMAT_TEXT, FORMAT_JSON]));
+
+    // Log subcommand
+    let log = Command::new(LOG)
+        .about("List the commits of each project whose revision changed between two manifests")
+        .arg(
+            Arg::new(OLD_MANIFEST)
+                .required(true)
+                .value_name("OLD")
+                .help("Pinned manifest of the previous release"),
+        )
+        .arg(
+            Arg::new(NEW_MANIFEST)
+                .required(true)
+                .value_name("NEW")
+                .help("Pinned manifest of the new release"),
+        )
+        .arg(format_arg(&[FORMAT_MARKDOWN, FORMAT_JSON]));
+
+    // Start subcommand
+    let start = Command::new(START)
+        .about("Create and checkout a topic branch at the manifest revision of the projects")
+        .arg(branch_arg())
+        .arg(projects_arg(
+            "Projects path or name (default: all projects)",
+        ));
+
+    // Abandon subcommand
+    let abandon = Command::new(ABANDON)
+        .about("Delete a topic branch from the projects")
+        .arg(branch_arg())
+        .arg(projects_arg(
+            "Projects path or name (default: all projects of the topic)",
+        ));
+
+    // Push subcommand
+    let push = Command::new(PUSH)
+        .about("Push a topic branch of every participating project to its remote")
+        .arg(
+            Arg::new(BRANCH)
+                .value_name("BRANCH")
+                .help("Topic branch name (default: the topic checked out)"),
+        )
+        .arg(
+            Arg::new(DRY_RUN)
+                .long(DRY_RUN)
+                .action(ArgAction::SetTrue)
+                .help("Do everything except actually send the updates"),
+        );
+
+    // Commit subcommand
+    let commit = Command::new(COMMIT)
+        .about("Commit the modified tracked files of each project with the same message")
+        .arg(
+            Arg::new(MESSAGE)
+                .short('m')
+                .long(MESSAGE)
+                .required(true)
+                .action(ArgAction::Set)
+                .value_name("MSG")
+                .help("Commit message"),
+        )
+        .arg(
+            Arg::new(PIN)
+                .long(PIN)
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .help("Save a manifest pinned to the current commit of each project"),
+        )
+        .arg(projects_arg(
+            "Projects path or name (default: all projects)",
+        ));
+
+    // Tag subcommand
+    let tag = Command::new(TAG)
+        .about("Create an annotated tag in each project and emit a manifest pinned to it")
+        .arg(
+            Arg::new(TAG_NAME)
+                .required(true)
+                .value_name("NAME")
+                .help("Tag name"),
+        )
+        .arg(
+            Arg::new(MESSAGE)
+                .short('m')
+                .long(MESSAGE)
+                .action(ArgAction::Set)
+                .value_name("MSG")
+                .help("Tag message (default: tag name)"),
+        )
+        .arg(
+            Arg::new(PUSH)
+                .long(PUSH)
+                .action(ArgAction::SetTrue)
+                .help("Push the tag to the remote of each project"),
+        )
+        .arg(
+            Arg::new(AT_REVISION)
+                .long(AT_REVISION)
+                .action(ArgAction::SetTrue)
+                .help("Tag the manifest revision instead of the current HEAD"),
+        )
+        .arg(
+            Arg::new(PIN)
+                .long(PIN)
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .help("Save the manifest pinned to the tag instead of printing it"),
+        )
+        .arg(projects_arg(
+            "Projects path or name (default: all projects)",
+        ));
+
+    // Prune subcommand
+    let prune_command = Command::new(PRUNE)
+        .about("Delete the projects removed from the manifest since they were synced")
+        .after_help(
+            "Projects with local changes or unpushed commits are kept unless --force is used",
+        );
+
+    // Cache subcommand
+    let cache_command = Command::new(CACHE)
+        .about("Manage the mirrors shared by the workspaces with --sync --cache")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new(GC)
+                .about("Repack the mirrors, keeping the objects the workspaces may borrow"),
+        );
+
+    // Mirror subcommand
+    let mirror = Command::new(MIRROR)
+        .about("Create or update a bare mirror of each git repository of the manifest")
+        .arg(
+            Arg::new(MIRROR_DIR)
+                .required(true)
+                .value_name("DIR")
+                .help("Directory of the mirrors, in <uri>/<name>.git"),
+        )
+        .after_help("Synchronize a workspace from the mirrors with --sync --from-mirror DIR");
+
+    // Deepen subcommand
+    let deepen = Command::new(DEEPEN)
+        .about("Fetch more history of shallow clones, or convert them to full clones")
+        .arg(
+            Arg::new(DEPTH)
+                .long(DEPTH)
+                .action(ArgAction::Set)
+                .value_name("N")
+                .value_parser(RangedU64ValueParser::<u32>::new().range(1..))
+                .conflicts_with(FULL)
+                .help("Number of commits of history to add"),
+        )
+        .arg(
+            Arg::new(FULL)
+                .long(FULL)
+                .action(ArgAction::SetTrue)
+                .help("Fetch the whole history and the objects filtered out (default)"),
+        )
+        .arg(projects_arg(
+            "Projects path or name (default: all projects)",
+        ))
+        .after_help(
+            "The clone-depth and filter attributes, and --light, apply again to the next sync",
+        );
+
+    // Import subcommand
+    let import = Command::new(IMPORT)
+        .about("Create a manifest from the git repositories found in a directory")
+        .arg(
+            Arg::new(IMPORT_DIR)
+                .default_value(".")
+                .value_name("DIR")
+                .help("Directory to scan for git repositories"),
+        )
+        .arg(
+            Arg::new(REMOTE)
+                .long(REMOTE)
+                .action(ArgAction::Set)
+                .default_value("origin")
+                .value_name("NAME")
+                .help("Remote used to get the uri and name of each project"),
+        )
+        .arg(
+            Arg::new(OUTPUT)
+                .short('o')
+                .long(OUTPUT)
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .help("Manifest file to write (default: the --input manifest)"),
+        )
+        .after_help("An existing manifest is only overwritten with --force");
+
+    // Manifest edition subcommands
+    let add = Command::new(ADD)
+        .about("Add a project to the manifest")
+        .arg(
+            Arg::new(PROJECT)
+                .required(true)
+                .value_name("URI/NAME")
+                .help("Host and name of the project, e.g. github.com/team/app"),
+        )
+        .arg(
+            Arg::new(PROJECT_PATH)
+                .long(PROJECT_PATH)
+                .action(ArgAction::Set)
+                .value_name("PATH")
+                .help("Path of the project (default: last component of the name)"),
+        )
+        .arg(
+            Arg::new(REVISION)
+                .long(REVISION)
+                .action(ArgAction::Set)
+                .value_name("REVISION")
+                .help("Revision of the project (default: the manifest default)"),
+        );
+
+    let remove = Command::new(REMOVE)
+        .about("Remove a project from the manifest")
+        .arg(
+            Arg::new(PROJECT_PATH)
+                .required(true)
+                .value_name("PATH")
+                .help("Path of the project"),
+        );
+
+    let set = Command::new(SET)
+        .about("Set attributes of a project of the manifest")
+        .arg(
+            Arg::new(PROJECT_PATH)
+                .required(true)
+                .value_name("PATH")
+                .help("Path of the project"),
+        )
+        .arg(
+            Arg::new(ATTRIBUTES)
+                .required(true)
+                .num_args(1..)
+                .value_name("KEY=VALUE")
+                .help("Attributes to set: uri, name, path, revision, pushuri, vcs, clone-depth or filter"),
+        );
+
     // Application arguments
     let matches = Command::new(APP_NAME)
         .arg(generate_manifest)
@@ -109,12 +494,40 @@ async fn main() {
         .arg(sync)
         .arg(light)
         .arg(https)
+        .arg(jobs)
+        .arg(jobs_network)
+        .arg(jobs_checkout)
+        .arg(retries)
+        .arg(timeout)
+        .arg(backend)
+        .arg(cache)
+        .arg(from_mirror)
         .arg(quiet)
         .arg(force)
+        .arg(prune)
+        .arg(fail_fast)
+        .arg(keep_going)
+        .arg(failed)
+        .arg(junit)
         .arg(pin)
         .arg(list)
         .arg(debug)
         .arg(status)
+        .subcommand(diff)
+        .subcommand(log)
+        .subcommand(start)
+        .subcommand(abandon)
+        .subcommand(push)
+        .subcommand(commit)
+        .subcommand(tag)
+        .subcommand(prune_command)
+        .subcommand(cache_command)
+        .subcommand(mirror)
+        .subcommand(deepen)
+        .subcommand(import)
+        .subcommand(add)
+        .subcommand(remove)
+        .subcommand(set)
         .arg_required_else_help(true)
         .version(APP_VERSION)
         .get_matches();
@@ -135,6 +548,25 @@ async fn main() {
 
     let force = *matches.get_one::<bool>(FORCE).unwrap_or(&false);
 
+    // Manifest input
+    let default_manifest = MANIFEST_INPUT_DEFAULT.to_string();
+    let manifest_path = matches
+        .get_one::<String>(MANIFEST_INPUT)
+        .unwrap_or(&default_manifest);
+
+    // Subcommands that do not need git
+    match matches.subcommand() {
+        Some((DIFF, sub_matches)) => {
+            exit_on_error(diff_manifests(sub_matches));
+            return;
+        }
+        Some((name @ (ADD | REMOVE | SET), sub_matches)) => {
+            exit_on_error(edit_manifest(manifest_path, name, sub_matches, &user));
+            return;
+        }
+        _ => {}
+    }
+
     // Generate manifest
     if let Some(path) = matches.get_one::<String>(GENERATE_MANIFEST) {
         user.message(format!("Generate manifest file: {}", path));
@@ -152,17 +584,58 @@ async fn main() {
     }
 
     // All following commands require a manifest file
-    // Manifest input
-    let default_manifest = MANIFEST_INPUT_DEFAULT.to_string();
-    let manifest_path = matches
-        .get_one::<String>(MANIFEST_INPUT)
-        .unwrap_or(&default_manifest);
+    // Download mode
+    let dwl_mode = match (
+        matches.get_one::<String>(FROM_MIRROR),
+        matches.get_flag(HTTPS),
+    ) {
+        (Some(dir), _) => DwlMode::Mirror(absolute_path(dir)),
+        (None, true) => DwlMode::HTTPS,
+        (None, false) => DwlMode::SSH,
+    };
+
+    // Subcommands working on the workspace described by the manifest
+    if let Some((name, sub_matches)) = matches.subcommand() {
+        let result = match name {
+            LOG => changelog(manifest_path, sub_matches).await,
+            START => start_topic_branch(manifest_path, sub_matches, &user).await,
+            ABANDON => abandon_topic_branch(manifest_path, sub_matches, force, &user).await,
+            PUSH => push_topic_branch(manifest_path, sub_matches, &dwl_mode).await,
+            COMMIT => commit_modified_projects(manifest_path, sub_matches, &user).await,
+            TAG => tag_all_projects(manifest_path, sub_matches, &dwl_mode, &user).await,
+            IMPORT => import_manifest(manifest_path, sub_matches, force, &user).await,
+            CACHE => clean_cache(sub_matches, &user).await,
+            DEEPEN => deepen_repositories(manifest_path, sub_matches, &user).await,
+            MIRROR => mirror_repositories(manifest_path, sub_matches, &dwl_mode, &user).await,
+            PRUNE => match load_manifest(manifest_path) {
+                Ok(manifest) => prune_removed_projects(&manifest, force, &user).await,
+                Err(e) => Err(e),
+            },
+            _ => unreachable!("Unknown subcommand {name}"),
+        };
+        exit_on_error(result);
+        return;
+    }
+
     let mut manifest = match ManifestInstance::try_from(manifest_path) {
         Ok(manifest) => manifest,
         Err(_) => {
             std::process::exit(1);
         }
     };
+    manifest.set_jobs(get_jobs(&matches));
+    manifest.set_retry(get_retry(&matches));
+    manifest.set_timeout(get_timeout(&matches));
+    if matches.get_flag(CACHE) {
+        manifest.set_cache_dir(Cache::get_default_dir());
+    }
+    match get_backend(&matches) {
+        Ok(backend) => manifest.set_backend(backend),
+        Err(error_msg) => {
+            eprintln!("{}", error_msg);
+            std::process::exit(1);
+        }
+    }
 
     // Parse manifest file. Currently only support XML format.
     user.message(format!(
@@ -174,22 +647,45 @@ async fn main() {
         std::process::exit(1);
     }
 
-    // Download mode
-    let dwl_mode = match matches.get_one::<bool>(HTTPS) {
-        Some(true) => DwlMode::HTTPS,
-        _ => DwlMode::SSH,
-    };
-
     // Synchronize all projects
     if let Some(true) = matches.get_one::<bool>(SYNC) {
         let light = *matches.get_one::<bool>(LIGHT).unwrap_or(&false);
 
-        user.message("Synchronize all projects".to_string());
-        if let Err(error_msg) = manifest.sync(&dwl_mode, light, quiet, force).await {
+        let retry_failed = *matches.get_one::<bool>(FAILED).unwrap_or(&false);
+        if retry_failed {
+            match WorkspaceState::load(&manifest.get_manifest_dir()) {
+                Ok(state) => manifest.retain_projects(state.get_failed()),
+                Err(error_msg) => {
+                    eprintln!("{}", error_msg);
+                    std::process::exit(1);
+                }
+            }
+            if manifest.get_projects().is_empty() {
+                user.message("No failed project to synchronize".to_string());
+                return;
+            }
+            user.message("Synchronize failed projects".to_string());
+        } else {
+            user.message("Synchronize all projects".to_string());
+        }
+
+        manifest.set_fail_fast(matches.get_flag(FAIL_FAST));
+        manifest.set_cancellation(Cancellation::on_ctrl_c());
+        let report = manifest.sync_report(&dwl_mode, light, quiet, force).await;
+        user.message(format!("\n{}", report.to_table()));
+        if let Some(junit) = matches.get_one::<String>(JUNIT) {
+            exit_on_error(save_file(junit, &report.to_junit()));
+        }
+        if let Err(error_msg) = report.into_result() {
             eprintln!("{}", error_msg);
+            eprintln!("Use --sync --failed to synchronize the failed projects again");
             std::process::exit(1);
         }
         user.message("Synchronization complete".to_string());
+
+        if let Some(true) = matches.get_one::<bool>(PRUNE) {
+            exit_on_error(prune_removed_projects(&manifest, force, &user).await);
+        }
     }
 
     // Pin manifest
@@ -227,3 +723,406 @@ async fn main() {
         }
     }
 }
+
+/// Output format option. The first format is the default one.
+fn format_arg(formats: &[&'static str]) -> Arg {
+    Arg::new(FORMAT)
+        .long(FORMAT)
+        .action(ArgAction::Set)
+        .value_parser(formats.to_vec())
+        .default_value(formats[0])
+        .help("Output format")
+}
+
+fn branch_arg() -> Arg {
+    Arg::new(BRANCH)
+        .required(true)
+        .value_name("BRANCH")
+        .help("Topic branch name")
+}
+
+fn projects_arg(help: &'static str) -> Arg {
+    Arg::new(PROJECTS)
+        .action(ArgAction::Append)
+        .value_name("PROJECTS")
+        .help(help)
+}
+
+fn get_projects_selection(matches: &ArgMatches) -> Vec<String> {
+    matches
+        .get_many::<String>(PROJECTS)
+        .map(|values| values.cloned().collect())
+        .unwrap_or_default()
+}
+
+fn exit_on_error(result: Result<(), ManifestError>) {
+    if let Err(error_msg) = result {
+        eprintln!("{}", error_msg);
+        std::process::exit(1);
+    }
+}
+
+fn get_jobs(matches: &ArgMatches) -> Jobs {
+    let mut jobs = match matches.get_one::<usize>(JOBS) {
+        Some(jobs) => Jobs::new(*jobs),
+        None => Jobs::default(),
+    };
+    if let Some(network) = matches.get_one::<usize>(JOBS_NETWORK) {
+        jobs.network = *network;
+    }
+    if let Some(checkout) = matches.get_one::<usize>(JOBS_CHECKOUT) {
+        jobs.checkout = *checkout;
+    }
+    jobs
+}
+
+fn get_retry(matches: &ArgMatches) -> Retry {
+    let mut retry = Retry::default();
+    if let Some(retries) = matches.get_one::<u32>(RETRIES) {
+        retry.retries = *retries;
+    }
+    retry
+}
+
+fn get_backend(matches: &ArgMatches) -> Result<Backend, ManifestError> {
+    match matches.get_one::<String>(BACKEND).map(|b| b.as_str()) {
+        #[cfg(feature = "libgit2")]
+        Some(BACKEND_LIBGIT2) if matches.get_flag(CACHE) => {
+            let msg = format!("{BACKEND_LIBGIT2}, --{CACHE} requires the {BACKEND_GIT} backend");
+            Err(ManifestError::UnsupportedBackend(msg))
+        }
+        #[cfg(feature = "libgit2")]
+        Some(BACKEND_LIBGIT2) => Ok(Backend::Libgit2),
+        #[cfg(not(feature = "libgit2"))]
+        Some(BACKEND_LIBGIT2) => {
+            let msg = format!("{BACKEND_LIBGIT2}, Colligo was built without the libgit2 feature");
+            Err(ManifestError::UnsupportedBackend(msg))
+        }
+        _ => Ok(Backend::Git),
+    }
+}
+
+fn get_timeout(matches: &ArgMatches) -> Option<Duration> {
+    matches
+        .get_one::<u64>(TIMEOUT)
+        .map(|seconds| Duration::from_secs(*seconds))
+}
+
+fn load_manifest(path: &String) -> Result<ManifestInstance, ManifestError> {
+    let mut manifest = ManifestInstance::try_from(path)?;
+    manifest.parse()?;
+    Ok(manifest)
+}
+
+fn edit_manifest(
+    manifest_path: &String,
+    command: &str,
+    matches: &ArgMatches,
+    user: &UserMessage,
+) -> Result<(), ManifestError> {
+    let file = fs::read_to_string(manifest_path)
+        .map_err(|e| ManifestError::FailedToReadManifest(format!("{manifest_path}: {e}")))?;
+
+    let (edited, summary) = match command {
+        ADD => {
+            let spec = matches.get_one::<String>(PROJECT).unwrap();
+            let (uri, name) = split_project_spec(spec).ok_or_else(|| {
+                let msg = format!("Expected <uri>/<name>, got {spec}");
+                ManifestError::FailedToEditManifest(msg)
+            })?;
+            let default_path = name.rsplit('/').next().unwrap_or(&name).to_string();
+            let path = matches
+                .get_one::<String>(PROJECT_PATH)
+                .unwrap_or(&default_path);
+            let revision = matches.get_one::<String>(REVISION).map(|r| r.as_str());
+            let edited = add_project(&file, &uri, &name, path, revision)?;
+            (edited, format!("Added {uri}/{name} at {path}"))
+        }
+        REMOVE => {
+            let path = matches.get_one::<String>(PROJECT_PATH).unwrap();
+            (remove_project(&file, path)?, format!("Removed {path}"))
+        }
+        SET => {
+            let path = matches.get_one::<String>(PROJECT_PATH).unwrap();
+            let attributes: Vec<String> = matches
+                .get_many::<String>(ATTRIBUTES)
+                .unwrap_or_default()
+                .cloned()
+                .collect();
+            let edited = set_project_attributes(&file, path, &parse_assignments(&attributes)?)?;
+            (edited, format!("Set {} of {path}", attributes.join(" ")))
+        }
+        _ => unreachable!("Unknown subcommand {command}"),
+    };
+
+    save_file(manifest_path, &edited)?;
+    user.message(summary);
+    Ok(())
+}
+
+fn diff_manifests(matches: &ArgMatches) -> Result<(), ManifestError> {
+    let old = load_manifest(matches.get_one::<String>(OLD_MANIFEST).unwrap())?;
+    let new = load_manifest(matches.get_one::<String>(NEW_MANIFEST).unwrap())?;
+
+    let diff = ManifestDiff::new(old.get_projects(), new.get_projects());
+    match matches.get_one::<String>(FORMAT).map(|f| f.as_str()) {
+        Some(FORMAT_JSON) => println!("{}", diff.to_json()),
+        _ => print!("{}", diff.to_text()),
+    }
+    Ok(())
+}
+
+async fn changelog(manifest_path: &String, matches: &ArgMatches) -> Result<(), ManifestError> {
+    let workspace = ManifestInstance::try_from(manifest_path)?;
+    let old = load_manifest(matches.get_one::<String>(OLD_MANIFEST).unwrap())?;
+    let new = load_manifest(matches.get_one::<String>(NEW_MANIFEST).unwrap())?;
+
+    let changelog = Changelog::new(
+        &workspace.get_manifest_dir(),
+        old.get_projects(),
+        new.get_projects(),
+    )
+    .await;
+    match matches.get_one::<String>(FORMAT).map(|f| f.as_str()) {
+        Some(FORMAT_JSON) => println!("{}", changelog.to_json()),
+        _ => print!("{}", changelog.to_markdown()),
+    }
+    Ok(())
+}
+
+async fn start_topic_branch(
+    manifest_path: &String,
+    matches: &ArgMatches,
+    user: &UserMessage,
+) -> Result<(), ManifestError> {
+    let manifest = load_manifest(manifest_path)?;
+    let branch = matches.get_one::<String>(BRANCH).unwrap();
+
+    let started = start_topic(&manifest, branch, &get_projects_selection(matches)).await?;
+    for path in started {
+        user.message(format!("{path}: {branch} started"));
+    }
+    Ok(())
+}
+
+async fn abandon_topic_branch(
+    manifest_path: &String,
+    matches: &ArgMatches,
+    force: bool,
+    user: &UserMessage,
+) -> Result<(), ManifestError> {
+    let manifest = load_manifest(manifest_path)?;
+    let branch = matches.get_one::<String>(BRANCH).unwrap();
+
+    let abandoned =
+        abandon_topic(&manifest, branch, &get_projects_selection(matches), force).await?;
+    for path in abandoned {
+        user.message(format!("{path}: {branch} abandoned"));
+    }
+    Ok(())
+}
+
+async fn push_topic_branch(
+    manifest_path: &String,
+    matches: &ArgMatches,
+    mode: &DwlMode,
+) -> Result<(), ManifestError> {
+    let mut manifest = load_manifest(manifest_path)?;
+    manifest.set_jobs(get_jobs(matches));
+    manifest.set_timeout(get_timeout(matches));
+    let branch = matches.get_one::<String>(BRANCH).map(|b| b.as_str());
+    let dry_run = *matches.get_one::<bool>(DRY_RUN).unwrap_or(&false);
+
+    let (branch, results) = push_topic(&manifest, branch, mode, dry_run).await?;
+
+    let mut failed = 0;
+    for push in results {
+        match push.result {
+            Ok(summary) => println!("{}: {} {}", push.path, branch, summary),
+            Err(e) => {
+                failed += 1;
+                eprintln!("{}: {}", push.path, e.to_string().trim())
+            }
+        }
+    }
+
+    if failed > 0 {
+        let msg = format!("{failed} project(s) failed to push {branch}");
+        return Err(ManifestError::FailedToPush(msg));
+    }
+    Ok(())
+}
+
+async fn commit_modified_projects(
+    manifest_path: &String,
+    matches: &ArgMatches,
+    user: &UserMessage,
+) -> Result<(), ManifestError> {
+    let manifest = load_manifest(manifest_path)?;
+    let message = matches.get_one::<String>(MESSAGE).unwrap();
+
+    let commits = commit_projects(&manifest, message, &get_projects_selection(matches)).await?;
+    if commits.is_empty() {
+        user.message("No modified project".to_string());
+    }
+    for commit in commits {
+        println!("{} {}", commit.path, commit.commit_id);
+    }
+
+    if let Some(path) = matches.get_one::<String>(PIN) {
+        let pinned = manifest.pin().await?;
+        save_file(path, pinned.get_file())?;
+    }
+    Ok(())
+}
+
+async fn tag_all_projects(
+    manifest_path: &String,
+    matches: &ArgMatches,
+    mode: &DwlMode,
+    user: &UserMessage,
+) -> Result<(), ManifestError> {
+    let manifest = load_manifest(manifest_path)?;
+    let name = matches.get_one::<String>(TAG_NAME).unwrap();
+    let message = matches.get_one::<String>(MESSAGE).map(|m| m.as_str());
+    let at_revision = *matches.get_one::<bool>(AT_REVISION).unwrap_or(&false);
+    let selection = get_projects_selection(matches);
+
+    let pinned = tag_projects(&manifest, name, message, &selection, at_revision).await?;
+
+    if let Some(true) = matches.get_one::<bool>(PUSH) {
+        push_tag(&manifest, name, &selection, mode).await?;
+    }
+
+    match matches.get_one::<String>(PIN) {
+        Some(path) => {
+            save_file(path, pinned.get_file())?;
+            user.message(format!("Manifest pinned to {name} saved to {path}"));
+        }
+        None => print!("{}", pinned.get_file()),
+    }
+    Ok(())
+}
+
+async fn prune_removed_projects(
+    manifest: &ManifestInstance,
+    force: bool,
+    user: &UserMessage,
+) -> Result<(), ManifestError> {
+    let pruned = prune_projects(manifest, force).await?;
+    for path in pruned {
+        user.message(format!("{path}: deleted"));
+    }
+    Ok(())
+}
+
+async fn deepen_repositories(
+    manifest_path: &String,
+    matches: &ArgMatches,
+    user: &UserMessage,
+) -> Result<(), ManifestError> {
+    let mut manifest = load_manifest(manifest_path)?;
+    manifest.set_jobs(get_jobs(matches));
+    manifest.set_retry(get_retry(matches));
+    manifest.set_timeout(get_timeout(matches));
+    manifest.set_cancellation(Cancellation::on_ctrl_c());
+    let depth = matches.get_one::<u32>(DEPTH).copied();
+
+    let results = deepen_projects(&manifest, depth, &get_projects_selection(matches)).await?;
+
+    let mut failed = 0;
+    for deepen in results {
+        match deepen.result {
+            Ok(true) => user.message(format!("{}: deepened", deepen.path)),
+            Ok(false) => user.message(format!("{}: already complete", deepen.path)),
+            Err(e) => {
+                failed += 1;
+                eprintln!("{}: {}", deepen.path, e.to_string().trim())
+            }
+        }
+    }
+
+    if failed > 0 {
+        let msg = format!("{failed} project(s) failed");
+        return Err(ManifestError::FailedToDeepen(msg));
+    }
+    Ok(())
+}
+
+async fn mirror_repositories(
+    manifest_path: &String,
+    matches: &ArgMatches,
+    mode: &DwlMode,
+    user: &UserMessage,
+) -> Result<(), ManifestError> {
+    let mut manifest = load_manifest(manifest_path)?;
+    manifest.set_jobs(get_jobs(matches));
+    manifest.set_retry(get_retry(matches));
+    manifest.set_timeout(get_timeout(matches));
+    manifest.set_cancellation(Cancellation::on_ctrl_c());
+    let dir = absolute_path(matches.get_one::<String>(MIRROR_DIR).unwrap());
+
+    user.message(format!("Mirror the git projects into {}", dir.display()));
+    let report = manifest.mirror_report(&dir, mode).await;
+    user.message(format!("\n{}", report.to_table()));
+    report.into_result()
+}
+
+fn absolute_path(path: &String) -> PathBuf {
+    std::path::absolute(path).unwrap_or(PathBuf::from(path))
+}
+
+async fn clean_cache(matches: &ArgMatches, user: &UserMessage) -> Result<(), ManifestError> {
+    let Some(cache_dir) = Cache::get_default_dir() else {
+        let msg = format!("no cache directory, set ${CACHE_ENV}");
+        return Err(ManifestError::FailedToCleanCache(msg));
+    };
+    match matches.subcommand() {
+        Some((GC, _)) => {
+            let mirrors = gc(&cache_dir).await?;
+            for mirror in mirrors.iter() {
+                user.message(format!("{}: repacked", mirror.display()));
+            }
+            user.message(format!(
+                "{} mirror(s) in {}",
+                mirrors.len(),
+                cache_dir.display()
+            ));
+            Ok(())
+        }
+        _ => unreachable!("Unknown cache subcommand"),
+    }
+}
+
+async fn import_manifest(
+    manifest_path: &String,
+    matches: &ArgMatches,
+    force: bool,
+    user: &UserMessage,
+) -> Result<(), ManifestError> {
+    let dir = matches.get_one::<String>(IMPORT_DIR).unwrap();
+    let remote = matches.get_one::<String>(REMOTE).unwrap();
+    let output = matches.get_one::<String>(OUTPUT).unwrap_or(manifest_path);
+
+    if Path::new(output).exists() && !force {
+        let msg = format!("{output} already exists, use --force to overwrite it");
+        return Err(ManifestError::FailedToImport(msg));
+    }
+
+    let manifest_dir = match Path::new(output).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let (projects, skipped) = import_projects(Path::new(dir), manifest_dir, remote).await?;
+    for message in skipped {
+        eprintln!("Skipped {message}");
+    }
+
+    let manifest = XmlParser::new().compose(&projects)?;
+    save_file(output, &manifest)?;
+    user.message(format!(
+        "{} project(s) imported into {output}",
+        projects.len()
+    ));
+    Ok(())
+}
@@ -0,0 +1,204 @@
+use crate::project::Project;
+use serde::Serialize;
+
+/// A single difference between two manifests, keyed by project path.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum ProjectChange {
+    Added {
+        path: String,
+        uri: String,
+        revision: String,
+    },
+    Removed {
+        path: String,
+        uri: String,
+        revision: String,
+    },
+    Moved {
+        from: String,
+        to: String,
+        uri: String,
+    },
+    Revision {
+        path: String,
+        old: String,
+        new: String,
+    },
+    Uri {
+        path: String,
+        old: String,
+        new: String,
+    },
+    Actions {
+        path: String,
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ManifestDiff {
+    changes: Vec<ProjectChange>,
+}
+
+impl ManifestDiff {
+    /// Compare the projects of two manifests.
+    /// A project removed from one path and added at another path with the same repository is
+    /// reported as moved. Other changes are reported on the path found in the new manifest.
+    pub fn new(old: &[Project], new: &[Project]) -> Self {
+        let mut changes = Vec::new();
+
+        let mut added: Vec<&Project> = new
+            .iter()
+            .filter(|n| !old.iter().any(|o| same_path(o, n)))
+            .collect();
+        let mut removed: Vec<&Project> = old
+            .iter()
+            .filter(|o| !new.iter().any(|n| same_path(o, n)))
+            .collect();
+
+        // Pair removed and added projects pointing to the same repository
+        let mut moved: Vec<(&Project, &Project)> = Vec::new();
+        removed.retain(|o| {
+            match added
+                .iter()
+                .position(|n| get_repository(o) == get_repository(n))
+            {
+                Some(index) => {
+                    moved.push((o, added.remove(index)));
+                    false
+                }
+                None => true,
+            }
+        });
+
+        for project in removed {
+            changes.push(ProjectChange::Removed {
+                path: normalize_path(project.get_path()),
+                uri: get_repository(project),
+                revision: project.get_revision().clone(),
+            });
+        }
+
+        for project in added {
+            changes.push(ProjectChange::Added {
+                path: normalize_path(project.get_path()),
+                uri: get_repository(project),
+                revision: project.get_revision().clone(),
+            });
+        }
+
+        for (o, n) in moved.iter() {
+            changes.push(ProjectChange::Moved {
+                from: normalize_path(o.get_path()),
+                to: normalize_path(n.get_path()),
+                uri: get_repository(n),
+            });
+        }
+
+        // Compare projects present in both manifests, including moved ones
+        let common = new
+            .iter()
+            .filter_map(|n| old.iter().find(|o| same_path(o, n)).map(|o| (o, n)))
+            .chain(moved);
+
+        for (o, n) in common {
+            let path = normalize_path(n.get_path());
+
+            if get_repository(o) != get_repository(n) {
+                changes.push(ProjectChange::Uri {
+                    path: path.clone(),
+                    old: get_repository(o),
+                    new: get_repository(n),
+                });
+            }
+
+            if o.get_revision() != n.get_revision() {
+                changes.push(ProjectChange::Revision {
+                    path: path.clone(),
+                    old: o.get_revision().clone(),
+                    new: n.get_revision().clone(),
+                });
+            }
+
+            if o.get_actions() != n.get_actions() {
+                changes.push(ProjectChange::Actions {
+                    path,
+                    old: o.get_actions().iter().map(|a| a.to_string()).collect(),
+                    new: n.get_actions().iter().map(|a| a.to_string()).collect(),
+                });
+            }
+        }
+
+        Self { changes }
+    }
+
+    pub fn get_changes(&self) -> &Vec<ProjectChange> {
+        &self.changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Human readable report, one change per line.
+    pub fn to_text(&self) -> String {
+        if self.changes.is_empty() {
+            return "No differences\n".to_string();
+        }
+
+        let mut output = String::new();
+        for change in self.changes.iter() {
+            let line = match change {
+                ProjectChange::Added {
+                    path,
+                    uri,
+                    revision,
+                } => format!("+ {path} ({uri} @ {revision})"),
+                ProjectChange::Removed {
+                    path,
+                    uri,
+                    revision,
+                } => format!("- {path} ({uri} @ {revision})"),
+                ProjectChange::Moved { from, to, uri } => format!("> {from} -> {to} ({uri})"),
+                ProjectChange::Revision { path, old, new } => {
+                    format!("~ {path} revision: {old} -> {new}")
+                }
+                ProjectChange::Uri { path, old, new } => format!("~ {path} uri: {old} -> {new}"),
+                ProjectChange::Actions { path, old, new } => format!(
+                    "~ {path} actions: [{}] -> [{}]",
+                    old.join(", "),
+                    new.join(", ")
+                ),
+            };
+            output.push_str(&line);
+            output.push('\n');
+        }
+        output
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Failed to serialize manifest diff")
+    }
+}
+
+/// Remove leading "./" and trailing "/" so "./dev" and "dev/" designate the same project.
+pub fn normalize_path(path: &str) -> String {
+    let mut path = path.trim();
+    while let Some(stripped) = path.strip_prefix("./") {
+        path = stripped;
+    }
+    path.trim_end_matches('/').to_string()
+}
+
+fn same_path(a: &Project, b: &Project) -> bool {
+    normalize_path(a.get_path()) == normalize_path(b.get_path())
+}
+
+fn get_repository(project: &Project) -> String {
+    if let Some(archive) = project.get_archive() {
+        return archive.url.clone();
+    }
+    format!("{}/{}", project.get_uri(), project.get_name())
+}
@@ -0,0 +1,214 @@
+use crate::application::{ManifestError, ManifestParser};
+use crate::manifest_diff::normalize_path;
+use crate::xml_parser::XmlParser;
+use roxmltree::{Document, Node};
+
+/// Attributes of `<project>` which can be changed with `set`.
+pub const PROJECT_ATTRIBUTES: [&str; 8] = [
+    "uri",
+    "name",
+    "path",
+    "revision",
+    "pushuri",
+    "vcs",
+    "clone-depth",
+    "filter",
+];
+
+const INDENT: &str = "    ";
+
+/// Split `<uri>/<name>`, e.g. `github.com/team/app`, into the uri and name of a project.
+pub fn split_project_spec(spec: &str) -> Option<(String, String)> {
+    let spec = spec.trim().trim_end_matches('/');
+    let spec = spec.strip_suffix(".git").unwrap_or(spec);
+    let (uri, name) = spec.split_once('/')?;
+    if uri.is_empty() || name.is_empty() {
+        return None;
+    }
+    Some((uri.to_string(), name.to_string()))
+}
+
+/// Add a project after the last one of the manifest. The revision is omitted when `None`
+/// so that the `<default>` one is used.
+pub fn add_project(
+    file: &str,
+    uri: &str,
+    name: &str,
+    path: &str,
+    revision: Option<&str>,
+) -> Result<String, ManifestError> {
+    let doc = parse(file)?;
+    if find_project(&doc, path).is_some() {
+        let msg = format!("A project already uses the path {path}");
+        return Err(ManifestError::FailedToEditManifest(msg));
+    }
+
+    let mut element = format!(
+        "<project uri=\"{}\" name=\"{}\" path=\"{}\"",
+        escape(uri),
+        escape(name),
+        escape(path)
+    );
+    if let Some(revision) = revision {
+        element.push_str(&format!(" revision=\"{}\"", escape(revision)));
+    }
+    element.push_str("/>");
+
+    let root = doc.root_element();
+    let last_project = root.children().rfind(|n| n.has_tag_name("project"));
+
+    let mut edited = file.to_string();
+    match last_project {
+        Some(last) => {
+            let end = last.range().end;
+            let indent = get_indent(file, last.range().start);
+            edited.insert_str(end, &format!("\n{indent}{element}"));
+        }
+        None if file[root.range()].ends_with("/>") => {
+            // <manifest/> has no closing tag
+            let range = root.range();
+            let open = file[range.clone()].trim_end_matches("/>").trim_end();
+            let replacement = format!("{open}>\n{INDENT}{element}\n</manifest>");
+            edited.replace_range(range, &replacement);
+        }
+        None => {
+            let closing = file[..root.range().end]
+                .rfind("</")
+                .unwrap_or(root.range().end);
+            let line_start = file[..closing].rfind('\n').map(|i| i + 1).unwrap_or(0);
+            if file[line_start..closing].trim().is_empty() {
+                edited.insert_str(line_start, &format!("{INDENT}{element}\n"));
+            } else {
+                edited.insert_str(closing, &format!("\n{INDENT}{element}\n"));
+            }
+        }
+    }
+
+    validate(edited)
+}
+
+/// Remove the project at `path`, including the line it is on when nothing else is on it.
+pub fn remove_project(file: &str, path: &str) -> Result<String, ManifestError> {
+    let doc = parse(file)?;
+    let project = find_project(&doc, path).ok_or_else(|| project_not_found(path))?;
+
+    let mut range = project.range();
+    let line_start = file[..range.start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line_end = file[range.end..]
+        .find('\n')
+        .map(|i| range.end + i + 1)
+        .unwrap_or(file.len());
+    if file[line_start..range.start].trim().is_empty()
+        && file[range.end..line_end].trim().is_empty()
+    {
+        range = line_start..line_end;
+    }
+
+    let mut edited = file.to_string();
+    edited.replace_range(range, "");
+    validate(edited)
+}
+
+/// Set attributes of the project at `path`. Existing attributes are changed in place, the
+/// others are appended to the element.
+pub fn set_project_attributes(
+    file: &str,
+    path: &str,
+    attributes: &[(String, String)],
+) -> Result<String, ManifestError> {
+    let mut edited = file.to_string();
+    let mut path = path.to_string();
+
+    for (key, value) in attributes {
+        if !PROJECT_ATTRIBUTES.contains(&key.as_str()) {
+            let msg = format!(
+                "Unknown attribute {key}, expected one of {}",
+                PROJECT_ATTRIBUTES.join(", ")
+            );
+            return Err(ManifestError::FailedToEditManifest(msg));
+        }
+
+        let doc = parse(&edited)?;
+        let project = find_project(&doc, &path).ok_or_else(|| project_not_found(&path))?;
+        if key == "path" && find_project(&doc, value).is_some_and(|other| other != project) {
+            let msg = format!("A project already uses the path {value}");
+            return Err(ManifestError::FailedToEditManifest(msg));
+        }
+
+        let (range, replacement) = match project.attributes().find(|a| a.name() == key) {
+            Some(attribute) => (attribute.range_value(), escape(value)),
+            None => {
+                let end = project
+                    .attributes()
+                    .map(|a| a.range().end)
+                    .next_back()
+                    .unwrap_or(project.range().start + 1 + project.tag_name().name().len());
+                (end..end, format!(" {key}=\"{}\"", escape(value)))
+            }
+        };
+        edited.replace_range(range, &replacement);
+
+        // Following attributes are set on the moved project
+        if key == "path" {
+            path = value.clone();
+        }
+    }
+
+    validate(edited)
+}
+
+/// Parse `key=value` assignments.
+pub fn parse_assignments(assignments: &[String]) -> Result<Vec<(String, String)>, ManifestError> {
+    assignments
+        .iter()
+        .map(|assignment| match assignment.split_once('=') {
+            Some((key, value)) => Ok((key.trim().to_string(), value.to_string())),
+            None => {
+                let msg = format!("Expected key=value, got {assignment}");
+                Err(ManifestError::FailedToEditManifest(msg))
+            }
+        })
+        .collect()
+}
+
+fn parse(file: &str) -> Result<Document<'_>, ManifestError> {
+    Document::parse(file).map_err(|e| {
+        let msg = format!("Unable to parse XML: {e}");
+        ManifestError::FailedToEditManifest(msg)
+    })
+}
+
+fn find_project<'a, 'input>(doc: &'a Document<'input>, path: &str) -> Option<Node<'a, 'input>> {
+    let path = normalize_path(path);
+    doc.root()
+        .descendants()
+        .filter(|n| n.has_tag_name("project"))
+        .find(|n| n.attribute("path").map(normalize_path) == Some(path.clone()))
+}
+
+fn project_not_found(path: &str) -> ManifestError {
+    ManifestError::ProjectNotFound(path.to_string())
+}
+
+/// Check the edited manifest can still be parsed.
+fn validate(file: String) -> Result<String, ManifestError> {
+    XmlParser::new().parse(&file)?;
+    Ok(file)
+}
+
+fn get_indent(file: &str, position: usize) -> &str {
+    let line_start = file[..position].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let prefix = &file[line_start..position];
+    if prefix.trim().is_empty() {
+        prefix
+    } else {
+        INDENT
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('"', "&quot;")
+}
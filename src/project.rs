@@ -1,11 +1,20 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
 pub const DEFAULT_REVISION: &str = "main";
 pub const DEFAULT_HOST: &str = "github.com";
 
+// Version control systems
+pub const VCS_GIT: &str = "git";
+pub const VCS_HG: &str = "hg";
+
 // Action tags
 const LINKFILE: &str = "linkfile";
 const COPYFILE: &str = "copyfile";
 const COPYDIR: &str = "copydir";
 const DELETE_PROJECT: &str = "delete_project";
+// Sparse checkout tag
+const SPARSE: &str = "sparse";
 
 #[derive(Clone, PartialEq)]
 pub enum ProjectFileAction {
@@ -20,12 +29,78 @@ pub enum ProjectAction {
     DeleteProject,
 }
 
+impl Display for ProjectAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProjectAction::FileAction(ProjectFileAction::LinkFile(src, dest)) => {
+                write!(f, "{LINKFILE} {src} -> {dest}")
+            }
+            ProjectAction::FileAction(ProjectFileAction::CopyFile(src, dest)) => {
+                write!(f, "{COPYFILE} {src} -> {dest}")
+            }
+            ProjectAction::FileAction(ProjectFileAction::CopyDir(src, dest)) => {
+                write!(f, "{COPYDIR} {src} -> {dest}")
+            }
+            ProjectAction::DeleteProject => write!(f, "{DELETE_PROJECT}"),
+        }
+    }
+}
+
+/// Version control system of a project, set with the `vcs` attribute.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Vcs {
+    #[default]
+    Git,
+    /// Mercurial
+    Hg,
+}
+
+impl Vcs {
+    pub fn from_attribute(value: &str) -> Option<Self> {
+        match value {
+            VCS_GIT => Some(Vcs::Git),
+            VCS_HG => Some(Vcs::Hg),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Vcs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Vcs::Git => write!(f, "{VCS_GIT}"),
+            Vcs::Hg => write!(f, "{VCS_HG}"),
+        }
+    }
+}
+
+/// Release archive unpacked instead of a repository, described by an `<archive>` element.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Archive {
+    /// `file://`, `http://` or `https://` URL of a tar.gz, tar.xz or zip file
+    pub url: String,
+    /// Expected SHA-256 of the archive, in hexadecimal
+    pub sha256: String,
+    /// Number of leading path components removed from the archive entries
+    pub strip_components: usize,
+}
+
 #[derive(Clone)]
 pub struct Project {
     uri: String,
     name: String,
     revision: String,
     path: String,
+    push_uri: Option<String>,
+    vcs: Vcs,
+    archive: Option<Archive>,
+    /// Number of commits of history fetched, all if `None`
+    clone_depth: Option<u32>,
+    /// Partial clone filter of the fetches, e.g. `blob:none`
+    filter: Option<String>,
+    /// Directories checked out with a cone mode sparse checkout, everything if empty
+    sparse: Vec<String>,
     actions: Vec<ProjectAction>,
 }
 
@@ -36,20 +111,100 @@ impl Project {
             name,
             revision,
             path,
+            push_uri: None,
+            vcs: Vcs::default(),
+            archive: None,
+            clone_depth: None,
+            filter: None,
+            sparse: Vec::new(),
             actions: Vec::new(),
         }
     }
 
+    /// Project unpacked from an archive, its revision is the archive hash.
+    pub fn new_archive(archive: Archive, path: String) -> Self {
+        let mut project = Self::new(
+            archive.url.clone(),
+            String::new(),
+            archive.sha256.clone(),
+            path,
+        );
+        project.archive = Some(archive);
+        project
+    }
+
     pub fn pin(&self, commit_id: String) -> Self {
         Self {
             uri: self.uri.clone(),
             name: self.name.clone(),
             revision: commit_id,
             path: self.path.clone(),
+            push_uri: self.push_uri.clone(),
+            vcs: self.vcs,
+            archive: self.archive.clone(),
+            clone_depth: self.clone_depth,
+            filter: self.filter.clone(),
+            sparse: self.sparse.clone(),
             actions: self.actions.clone(),
         }
     }
 
+    /// Push to a different host than the one used to fetch.
+    pub fn set_push_uri(&mut self, push_uri: String) {
+        self.push_uri = Some(push_uri);
+    }
+
+    pub fn set_vcs(&mut self, vcs: Vcs) {
+        self.vcs = vcs;
+    }
+
+    pub fn get_vcs(&self) -> Vcs {
+        self.vcs
+    }
+
+    /// Fetch only the last `clone_depth` commits of the history of git repositories.
+    pub fn set_clone_depth(&mut self, clone_depth: u32) {
+        self.clone_depth = Some(clone_depth);
+    }
+
+    pub fn get_clone_depth(&self) -> Option<u32> {
+        self.clone_depth
+    }
+
+    /// Partial clone filter of git repositories, e.g. `blob:limit=1m` or `tree:0`. The objects
+    /// filtered out are downloaded when needed.
+    pub fn set_filter(&mut self, filter: String) {
+        self.filter = Some(filter);
+    }
+
+    pub fn get_filter(&self) -> Option<&String> {
+        self.filter.as_ref()
+    }
+
+    pub fn get_archive(&self) -> Option<&Archive> {
+        self.archive.as_ref()
+    }
+
+    /// Whether the project is checked out from a git repository, not an archive nor Mercurial.
+    pub fn is_git(&self) -> bool {
+        self.archive.is_none() && self.vcs == Vcs::Git
+    }
+
+    pub fn is_sparse(&self, tag: &str) -> bool {
+        matches!(tag, SPARSE)
+    }
+
+    /// Check out only `path`, and the files at the root of the project, from git repositories.
+    pub fn add_sparse_path(&mut self, path: String) {
+        if !self.sparse.contains(&path) {
+            self.sparse.push(path);
+        }
+    }
+
+    pub fn get_sparse_paths(&self) -> &Vec<String> {
+        &self.sparse
+    }
+
     pub fn is_file_action(&self, action: &str) -> bool {
         matches!(action, LINKFILE | COPYFILE | COPYDIR)
     }
@@ -121,4 +276,41 @@ impl Project {
     pub fn get_uri_ssh(&self) -> String {
         format!("git@{}:{}.git", self.uri, self.name)
     }
+
+    /// Mercurial has no `.git` suffix nor scp-like URL.
+    pub fn get_uri_hg_https(&self) -> String {
+        format!("https://{}/{}", self.uri, self.name)
+    }
+
+    pub fn get_uri_hg_ssh(&self) -> String {
+        format!("ssh://hg@{}/{}", self.uri, self.name)
+    }
+
+    pub fn get_push_uri(&self) -> Option<&String> {
+        self.push_uri.as_ref()
+    }
+
+    pub fn get_push_uri_https(&self) -> Option<String> {
+        self.push_uri
+            .as_ref()
+            .map(|uri| format!("https://{}/{}.git", uri, self.name))
+    }
+
+    pub fn get_push_uri_ssh(&self) -> Option<String> {
+        self.push_uri
+            .as_ref()
+            .map(|uri| format!("git@{}:{}.git", uri, self.name))
+    }
+
+    pub fn get_push_uri_hg_https(&self) -> Option<String> {
+        self.push_uri
+            .as_ref()
+            .map(|uri| format!("https://{}/{}", uri, self.name))
+    }
+
+    pub fn get_push_uri_hg_ssh(&self) -> Option<String> {
+        self.push_uri
+            .as_ref()
+            .map(|uri| format!("ssh://hg@{}/{}", uri, self.name))
+    }
 }
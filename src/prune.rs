@@ -0,0 +1,123 @@
+use crate::application::{ManifestError, ManifestInstance};
+use crate::archive_version_control::ARCHIVE_MARKER;
+use crate::hg_version_control::HgVersionControl;
+use crate::manifest_diff::normalize_path;
+use crate::project::{Project, Vcs};
+use crate::version_control::GitVersionControl;
+use crate::workspace_state::WorkspaceState;
+use std::fs;
+use std::path::Path;
+
+/// Delete the checkouts of the projects synced in the workspace but no longer in the manifest.
+/// A project with local changes or unpushed commits is kept unless `force` is set, a project whose
+/// directory contains a project of the manifest is always kept.
+/// Return the path of the deleted projects.
+pub async fn prune_projects(
+    manifest: &ManifestInstance,
+    force: bool,
+) -> Result<Vec<String>, ManifestError> {
+    let manifest_dir = manifest.get_manifest_dir();
+    let mut state = WorkspaceState::load(&manifest_dir)?;
+
+    let mut pruned = Vec::new();
+    let mut refused = Vec::new();
+    let mut kept = Vec::new();
+
+    let stale: Vec<Project> = state
+        .get_stale_projects(manifest.get_projects())
+        .iter()
+        .map(|synced| synced.to_project())
+        .collect();
+
+    for project in stale {
+        let repo_path = manifest_dir.join(project.get_path());
+
+        // Deleting the directory would delete the projects of the manifest inside it
+        let nested = manifest
+            .get_projects()
+            .iter()
+            .map(|current| normalize_path(current.get_path()))
+            .find(|current| Path::new(current).starts_with(project.get_path()));
+        if let Some(nested) = nested {
+            kept.push(format!(
+                "{}, directory contains the project {nested}",
+                project.get_path()
+            ));
+            continue;
+        }
+
+        // The content of an archive is not versioned, it can be unpacked again
+        let is_archive = repo_path.join(ARCHIVE_MARKER).exists();
+
+        if repo_path.exists() && !force && !is_archive {
+            if let Some(reason) = get_keep_reason(&manifest_dir, &project).await {
+                refused.push(reason);
+                continue;
+            }
+        }
+
+        if repo_path.exists() {
+            fs::remove_dir_all(&repo_path).map_err(|e| {
+                let msg = format!("Failed to remove directory {}: {e}", repo_path.display());
+                ManifestError::FailedToPrune(msg)
+            })?;
+            pruned.push(project.get_path().clone());
+        }
+        state.remove_project(project.get_path());
+    }
+
+    state.save(&manifest_dir)?;
+
+    let mut msg = String::new();
+    if !refused.is_empty() {
+        msg += &format!(
+            "\n\n{}\nUse --force to delete them anyway\n",
+            refused.join("\n")
+        );
+    }
+    if !kept.is_empty() {
+        msg += &format!("\n\n{}\nPlease delete them manually\n", kept.join("\n"));
+    }
+    if !msg.is_empty() {
+        return Err(ManifestError::FailedToPrune(msg));
+    }
+    Ok(pruned)
+}
+
+/// Reason to keep the checkout of a project, with local changes or unpushed commits, checked with
+/// its version control system. The projects which cannot be checked are kept as well.
+async fn get_keep_reason(manifest_dir: &Path, project: &Project) -> Option<String> {
+    let path = project.get_path();
+
+    // Without its repository, git or hg would check the enclosing one, if any
+    let metadata = match project.get_vcs() {
+        Vcs::Git => ".git",
+        Vcs::Hg => ".hg",
+    };
+    if !manifest_dir.join(path).join(metadata).exists() {
+        return Some(format!("{path}, not a {} repository", project.get_vcs()));
+    }
+
+    let checks = match project.get_vcs() {
+        Vcs::Git => {
+            let git = GitVersionControl::new();
+            [
+                git.has_local_changes(manifest_dir, project).await,
+                git.has_unpushed_commits(manifest_dir, project).await,
+            ]
+        }
+        Vcs::Hg => {
+            let hg = HgVersionControl::new();
+            [
+                hg.has_local_changes(manifest_dir, project).await,
+                hg.has_unpushed_commits(manifest_dir, project).await,
+            ]
+        }
+    };
+    match checks {
+        [Err(e), _] | [_, Err(e)] => Some(e.to_string().trim().to_string()),
+        [Ok(true), _] => Some(format!("{path}, repository has local changes")),
+        [_, Ok(true)] => Some(format!("{path}, repository has unpushed commits")),
+        _ => None,
+    }
+}
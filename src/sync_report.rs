@@ -0,0 +1,315 @@
+use crate::application::ManifestError;
+use crate::version_control::FetchStats;
+use std::fmt::Display;
+use std::time::Duration;
+
+/// What the synchronization did to a project.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyncAction {
+    /// The repository did not exist and was cloned
+    Cloned,
+    /// The checked out commit changed
+    Updated,
+    /// The project was already at the manifest revision
+    UpToDate,
+    Failed,
+    /// Stopped or not started, because of Ctrl-C or `--fail-fast`
+    Cancelled,
+}
+
+impl Display for SyncAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncAction::Cloned => write!(f, "cloned"),
+            SyncAction::Updated => write!(f, "updated"),
+            SyncAction::UpToDate => write!(f, "up to date"),
+            SyncAction::Failed => write!(f, "failed"),
+            SyncAction::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+/// Outcome of the synchronization of one project.
+#[derive(Debug, Clone)]
+pub struct ProjectReport {
+    pub path: String,
+    pub action: SyncAction,
+    /// Commit checked out before the synchronization
+    pub old_commit: Option<String>,
+    /// Commit checked out after the synchronization
+    pub new_commit: Option<String>,
+    /// Time spent working on the project, without waiting for the other projects
+    pub duration: Duration,
+    /// Bytes received by the fetch, when reported by git
+    pub bytes_fetched: Option<u64>,
+    /// Number of fetch attempts
+    pub attempts: u32,
+    /// Project actions executed, e.g. `linkfile README.md -> doc/README.md`
+    pub actions: Vec<String>,
+    pub error: Option<ManifestError>,
+}
+
+impl ProjectReport {
+    pub fn new(path: String) -> Self {
+        Self {
+            path,
+            action: SyncAction::Failed,
+            old_commit: None,
+            new_commit: None,
+            duration: Duration::ZERO,
+            bytes_fetched: None,
+            attempts: 0,
+            actions: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Record the statistics of the fetch, or the number of attempts reported by its error.
+    pub fn record_fetch(&mut self, result: Result<&FetchStats, &ManifestError>) {
+        match result {
+            Ok(stats) => {
+                self.attempts = stats.attempts;
+                self.bytes_fetched = stats.bytes;
+            }
+            Err(ManifestError::Interrupted(_)) => {}
+            Err(e) => self.attempts = get_failed_attempts(e),
+        }
+    }
+}
+
+/// Outcome of the synchronization of a manifest, with the projects in the manifest order.
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    pub projects: Vec<ProjectReport>,
+    pub duration: Duration,
+    /// Error not related to a project, e.g. failing to record the workspace state
+    pub error: Option<ManifestError>,
+}
+
+impl SyncReport {
+    pub fn is_success(&self) -> bool {
+        self.error.is_none() && self.projects.iter().all(|p| p.error.is_none())
+    }
+
+    /// Paths of the projects which failed or were cancelled.
+    pub fn get_failed(&self) -> Vec<String> {
+        self.projects
+            .iter()
+            .filter(|p| p.error.is_some())
+            .map(|p| p.path.clone())
+            .collect()
+    }
+
+    pub fn count(&self, action: SyncAction) -> usize {
+        self.projects.iter().filter(|p| p.action == action).count()
+    }
+
+    /// Fold the errors into a single one, as reported by `ManifestInstance::sync`.
+    /// Without any failure, the cancelled projects are reported as `Interrupted`.
+    pub fn into_result(self) -> Result<(), ManifestError> {
+        let mut errors = Vec::new();
+        let mut unfinished = Vec::new();
+        for project in self.projects {
+            match project.error {
+                Some(ManifestError::Interrupted(path)) => unfinished.push(path),
+                Some(e) => errors.push(format!("{e}")),
+                None => {}
+            }
+        }
+        if let Some(e) = self.error {
+            errors.push(format!("{e}"));
+        }
+
+        // If there is any error, return it
+        if !errors.is_empty() {
+            // Save all error message in string, separated by a new line
+            let mut error_msg = String::new();
+            error_msg.push_str("\n\n");
+
+            for msg in errors.iter() {
+                error_msg.push_str(msg);
+                error_msg.push('\n');
+            }
+
+            if !unfinished.is_empty() {
+                error_msg.push_str(&format!("\nCancelled: {}\n", unfinished.join(", ")));
+            }
+
+            return Err(ManifestError::FailedToSync(error_msg));
+        }
+
+        if !unfinished.is_empty() {
+            let error_msg = format!("\n\n{}\n", unfinished.join("\n"));
+            return Err(ManifestError::Interrupted(error_msg));
+        }
+
+        Ok(())
+    }
+
+    /// Table with a line per project, followed by the totals.
+    pub fn to_table(&self) -> String {
+        let header = [
+            "PROJECT", "RESULT", "OLD", "NEW", "TIME", "FETCHED", "ATTEMPTS", "ACTIONS",
+        ];
+        let mut rows: Vec<[String; 8]> = vec![header.map(String::from)];
+        for project in self.projects.iter() {
+            rows.push([
+                project.path.clone(),
+                project.action.to_string(),
+                short_commit(project.old_commit.as_ref()),
+                short_commit(project.new_commit.as_ref()),
+                format!("{:.1}s", project.duration.as_secs_f32()),
+                project
+                    .bytes_fetched
+                    .map(format_bytes)
+                    .unwrap_or("-".into()),
+                match project.attempts {
+                    0 => "-".to_string(),
+                    attempts => attempts.to_string(),
+                },
+                project.actions.len().to_string(),
+            ]);
+        }
+
+        let mut widths = [0; 8];
+        for row in rows.iter() {
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let mut table = String::new();
+        for row in rows.iter() {
+            let line = row
+                .iter()
+                .zip(widths.iter())
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .collect::<Vec<_>>()
+                .join("  ");
+            table.push_str(line.trim_end());
+            table.push('\n');
+        }
+
+        table.push_str(&format!(
+            "\n{} projects in {:.1}s: {} cloned, {} updated, {} up to date, {} failed, {} cancelled\n",
+            self.projects.len(),
+            self.duration.as_secs_f32(),
+            self.count(SyncAction::Cloned),
+            self.count(SyncAction::Updated),
+            self.count(SyncAction::UpToDate),
+            self.count(SyncAction::Failed),
+            self.count(SyncAction::Cancelled),
+        ));
+        table
+    }
+
+    /// JUnit XML with a test case per project, so that CI servers show the result of each one.
+    pub fn to_junit(&self) -> String {
+        let failures = self.count(SyncAction::Failed);
+        let skipped = self.count(SyncAction::Cancelled);
+        let time = self.duration.as_secs_f64();
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuites name=\"colligo\" tests=\"{}\" failures=\"{failures}\" skipped=\"{skipped}\" time=\"{time:.3}\">\n",
+            self.projects.len()
+        ));
+        xml.push_str(&format!(
+            "    <testsuite name=\"sync\" tests=\"{}\" failures=\"{failures}\" skipped=\"{skipped}\" time=\"{time:.3}\">\n",
+            self.projects.len()
+        ));
+
+        for project in self.projects.iter() {
+            xml.push_str(&format!(
+                "        <testcase classname=\"sync\" name=\"{}\" time=\"{:.3}\">\n",
+                escape(&project.path),
+                project.duration.as_secs_f64()
+            ));
+            match (&project.action, &project.error) {
+                (SyncAction::Cancelled, _) => {
+                    xml.push_str("            <skipped message=\"cancelled\"/>\n");
+                }
+                (_, Some(e)) => {
+                    let text = e.to_string();
+                    let message = text.trim().lines().last().unwrap_or_default();
+                    let mut output = text.trim().to_string();
+                    if project.attempts > 0 {
+                        output.push_str(&format!("\nattempts: {}", project.attempts));
+                    }
+                    xml.push_str(&format!(
+                        "            <failure message=\"{}\">{}</failure>\n",
+                        escape(message),
+                        escape(&output)
+                    ));
+                }
+                (action, None) => {
+                    let mut output = format!(
+                        "{action} {}",
+                        project.new_commit.as_deref().unwrap_or_default()
+                    );
+                    if project.attempts > 0 {
+                        output.push_str(&format!("\nattempts: {}", project.attempts));
+                    }
+                    for executed in project.actions.iter() {
+                        output.push('\n');
+                        output.push_str(executed);
+                    }
+                    xml.push_str(&format!(
+                        "            <system-out>{}</system-out>\n",
+                        escape(output.trim())
+                    ));
+                }
+            }
+            xml.push_str("        </testcase>\n");
+        }
+
+        xml.push_str("    </testsuite>\n</testsuites>\n");
+        xml
+    }
+}
+
+/// Number of attempts of a failed fetch, as reported at the end of its error after retries.
+fn get_failed_attempts(error: &ManifestError) -> u32 {
+    error
+        .to_string()
+        .lines()
+        .rev()
+        .find_map(|line| {
+            let attempts = line.trim().strip_prefix("Failed after ")?;
+            attempts.strip_suffix(" attempts")?.parse().ok()
+        })
+        .unwrap_or(1)
+}
+
+fn short_commit(commit: Option<&String>) -> String {
+    match commit {
+        Some(commit) => commit.chars().take(8).collect(),
+        None => "-".to_string(),
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{bytes} B"),
+        _ => format!("{value:.1} {}", UNITS[unit]),
+    }
+}
+
+/// Escape text and attribute values, dropping the control characters XML does not allow.
+fn escape(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_control() || matches!(c, '\n' | '\t'))
+        .collect::<String>()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
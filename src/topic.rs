@@ -0,0 +1,226 @@
+use crate::application::{
+    select_git_projects, select_projects, DwlMode, ManifestError, ManifestInstance,
+};
+use crate::manifest_diff::normalize_path;
+use crate::project::Project;
+use crate::version_control::GitVersionControl;
+use crate::workspace_state::WorkspaceState;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Topic branches created with `start` and the projects participating in each of them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Topics {
+    branches: BTreeMap<String, Vec<String>>,
+}
+
+impl Topics {
+    /// Load the topics of the workspace. An empty set is returned if no topic was ever started.
+    pub fn load(manifest_dir: &Path) -> Result<Self, ManifestError> {
+        WorkspaceState::load(manifest_dir).map(|state| state.get_topics().clone())
+    }
+
+    pub fn save(&self, manifest_dir: &Path) -> Result<(), ManifestError> {
+        let mut state = WorkspaceState::load(manifest_dir)?;
+        state.set_topics(self.clone());
+        state.save(manifest_dir)
+    }
+
+    /// Path of the projects participating in a topic.
+    pub fn get_projects(&self, branch: &str) -> Option<&Vec<String>> {
+        self.branches.get(branch)
+    }
+
+    /// Topics in which a project participates.
+    pub fn get_topics(&self, path: &str) -> Vec<&String> {
+        let path = normalize_path(path);
+        self.branches
+            .iter()
+            .filter(|(_, paths)| paths.contains(&path))
+            .map(|(branch, _)| branch)
+            .collect()
+    }
+
+    fn add(&mut self, branch: &str, path: &str) {
+        let path = normalize_path(path);
+        let paths = self.branches.entry(branch.to_string()).or_default();
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+
+    fn remove(&mut self, branch: &str, path: &str) {
+        let path = normalize_path(path);
+        if let Some(paths) = self.branches.get_mut(branch) {
+            paths.retain(|p| *p != path);
+            if paths.is_empty() {
+                self.branches.remove(branch);
+            }
+        }
+    }
+}
+
+/// Create and checkout `branch` at the manifest revision of the selected git projects, or all of
+/// them if none is selected. Return the path of the participating projects.
+pub async fn start_topic(
+    manifest: &ManifestInstance,
+    branch: &str,
+    selectors: &[String],
+) -> Result<Vec<String>, ManifestError> {
+    let projects = select_git_projects(manifest, selectors)?;
+    let manifest_dir = manifest.get_manifest_dir();
+    let vcs = GitVersionControl::new();
+
+    let mut topics = Topics::load(&manifest_dir)?;
+    let mut started = Vec::with_capacity(projects.len());
+    let mut result = Ok(());
+
+    for project in projects.iter() {
+        result = vcs.start_branch(&manifest_dir, project, branch).await;
+        if result.is_err() {
+            break;
+        }
+        topics.add(branch, project.get_path());
+        started.push(project.get_path().clone());
+    }
+
+    // Record the projects started even if one of them failed
+    topics.save(&manifest_dir)?;
+    result.map(|_| started)
+}
+
+/// Delete `branch` in the selected git projects, or in all participating projects if none is
+/// selected. A branch with unpushed commits is kept unless `force` is set. Return the path of the
+/// projects that left the topic.
+pub async fn abandon_topic(
+    manifest: &ManifestInstance,
+    branch: &str,
+    selectors: &[String],
+    force: bool,
+) -> Result<Vec<String>, ManifestError> {
+    let manifest_dir = manifest.get_manifest_dir();
+    let vcs = GitVersionControl::new();
+    let mut topics = Topics::load(&manifest_dir)?;
+
+    let projects: Vec<Project> = if selectors.is_empty() {
+        let participants = topics.get_projects(branch).cloned().ok_or_else(|| {
+            ManifestError::FailedToDeleteBranch(format!("Unknown topic {branch}"))
+        })?;
+        select_git_projects(manifest, &participants)?
+    } else {
+        select_git_projects(manifest, selectors)?
+    };
+
+    let mut abandoned = Vec::with_capacity(projects.len());
+    let mut result = Ok(());
+
+    for project in projects.iter() {
+        result = vcs
+            .delete_branch(&manifest_dir, project, branch, force)
+            .await;
+        if result.is_err() {
+            break;
+        }
+        topics.remove(branch, project.get_path());
+        abandoned.push(project.get_path().clone());
+    }
+
+    topics.save(&manifest_dir)?;
+    result.map(|_| abandoned)
+}
+
+/// Outcome of pushing a topic branch in one project.
+pub struct PushResult {
+    pub path: String,
+    /// Push status reported by git, ex: "[new branch]", or the error.
+    pub result: Result<String, ManifestError>,
+}
+
+/// Push `branch` of every participating project to origin, in parallel. When no branch is given,
+/// the topic checked out in the workspace is pushed. Return the pushed branch with the result of
+/// each project.
+pub async fn push_topic(
+    manifest: &ManifestInstance,
+    branch: Option<&str>,
+    mode: &DwlMode,
+    dry_run: bool,
+) -> Result<(String, Vec<PushResult>), ManifestError> {
+    let manifest_dir = manifest.get_manifest_dir();
+    let topics = Topics::load(&manifest_dir)?;
+
+    let branch = match branch {
+        Some(branch) => branch.to_string(),
+        None => get_current_topic(manifest, &topics).await?,
+    };
+
+    let participants = topics
+        .get_projects(&branch)
+        .cloned()
+        .ok_or_else(|| ManifestError::FailedToPush(format!("Unknown topic {branch}")))?;
+    let projects = select_projects(manifest, &participants)?;
+
+    let network = Arc::new(Semaphore::new(manifest.get_jobs().network));
+    let timeout = manifest.get_timeout();
+    let mut handles = Vec::with_capacity(projects.len());
+    for project in projects {
+        let dir = manifest_dir.clone();
+        let mode = mode.clone();
+        let branch = branch.clone();
+        let network = network.clone();
+
+        handles.push(tokio::task::spawn(async move {
+            let _permit = network.acquire().await.expect("Semaphore closed");
+            let mut vcs = GitVersionControl::new();
+            vcs.set_timeout(timeout);
+            let result = vcs.push(&dir, &project, &mode, &branch, dry_run).await;
+            PushResult {
+                path: project.get_path().clone(),
+                result,
+            }
+        }));
+    }
+
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        results.push(handle.await.expect("Failed to join push task"));
+    }
+    Ok((branch, results))
+}
+
+/// Return the only topic checked out in at least one of its projects.
+async fn get_current_topic(
+    manifest: &ManifestInstance,
+    topics: &Topics,
+) -> Result<String, ManifestError> {
+    let manifest_dir = manifest.get_manifest_dir();
+    let vcs = GitVersionControl::new();
+    let mut current = Vec::new();
+
+    for (branch, paths) in topics.branches.iter() {
+        for project in select_projects(manifest, paths)? {
+            if vcs
+                .get_current_branch(&manifest_dir, &project)
+                .await
+                .as_ref()
+                == Some(branch)
+            {
+                current.push(branch.clone());
+                break;
+            }
+        }
+    }
+
+    match current.len() {
+        1 => Ok(current.remove(0)),
+        0 => Err(ManifestError::FailedToPush(
+            "No topic branch is checked out".to_string(),
+        )),
+        _ => Err(ManifestError::FailedToPush(format!(
+            "Several topic branches are checked out ({}), please specify one",
+            current.join(", ")
+        ))),
+    }
+}
@@ -1,17 +1,146 @@
-use crate::application::{DwlMode, ManifestError};
+use crate::application::{Cancellation, DwlMode, ManifestError, XVersionControl};
+use crate::cache::{get_mirror_path, Cache};
 use crate::project::Project;
 use indicatif::ProgressBar;
-use log::debug;
+use log::{debug, warn};
 use regex::Regex;
-use std::path::Path;
-use std::process::Stdio;
+use serde::Serialize;
+use std::fmt::Display;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Output, Stdio};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
 use tokio::io::{AsyncBufReadExt, AsyncReadExt};
 use tokio::process::Command;
 
 const DISPLAY_STATUS_SIZE: usize = 3;
+/// Partial clone filter of the lightweight clones and sparse checkouts
+const BLOB_FILTER: &str = "blob:none";
 const PROGRESS_REFRESH_RATE_MS: u64 = 100;
+const DEFAULT_RETRIES: u32 = 3;
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+/// Percentage of the progress output of git
+static PERCENT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(\d+)%").unwrap());
+/// Size received by `git fetch`, e.g. "Receiving objects: 100% (3/3), 1.20 MiB"
+static RECEIVED: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"Receiving objects:[^\r\n]*?, ([\d.]+) (bytes|KiB|MiB|GiB)").unwrap()
+});
+/// HTTP 5xx, e.g. "The requested URL returned error: 503" or "RPC failed; HTTP 502"
+static HTTP_5XX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(returned error|http)\W*5\d\d\b").unwrap());
 
-pub struct GitVersionControl {}
+pub struct GitVersionControl {
+    retry: Retry,
+    /// Maximum duration of each git invocation
+    timeout: Option<Duration>,
+    /// Stop the fetches in progress when cancelled
+    cancellation: Cancellation,
+    /// Mirrors the repositories borrow their objects from
+    cache: Option<Arc<Cache>>,
+}
+
+/// Progress of an operation, reported by the backends which do not parse the output of `git`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Progress {
+    /// Objects received from the remote
+    Receiving {
+        received: usize,
+        total: usize,
+        bytes: usize,
+    },
+    /// Deltas resolved once the objects are received
+    Resolving { resolved: usize, total: usize },
+    /// Files written to the working tree
+    Checkout { completed: usize, total: usize },
+}
+
+impl Progress {
+    pub fn get_percent(&self) -> u64 {
+        let (done, total) = match *self {
+            Progress::Receiving {
+                received, total, ..
+            } => (received, total),
+            Progress::Resolving { resolved, total } => (resolved, total),
+            Progress::Checkout { completed, total } => (completed, total),
+        };
+        match total {
+            0 => 100,
+            _ => (done * 100 / total) as u64,
+        }
+    }
+}
+
+impl Display for Progress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Progress::Receiving { .. } => write!(f, "Receiving objects"),
+            Progress::Resolving { .. } => write!(f, "Resolving deltas"),
+            Progress::Checkout { .. } => write!(f, "Updating files"),
+        }
+    }
+}
+
+/// Show the progress of the project at `path` in its progress bar.
+pub fn show_progress(pb: Option<&ProgressBar>, path: &str, progress: &Progress) {
+    if let Some(pb) = pb {
+        pb.set_position(progress.get_percent());
+        pb.set_message(format!("{path} {progress}"));
+    }
+}
+
+/// Outcome of a successful fetch.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FetchStats {
+    /// Number of attempts, more than one if transient errors were retried
+    pub attempts: u32,
+    /// Bytes received, when reported by git
+    pub bytes: Option<u64>,
+}
+
+/// How many times a network operation failing with a transient error is retried.
+/// The delay before each retry is doubled, starting from `delay`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Retry {
+    pub retries: u32,
+    pub delay: Duration,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            retries: DEFAULT_RETRIES,
+            delay: Duration::from_secs(1),
+        }
+    }
+}
+
+impl Retry {
+    pub(crate) fn get_delay(&self, attempt: u32) -> Duration {
+        let delay = self.delay.saturating_mul(2u32.saturating_pow(attempt - 1));
+        delay.min(MAX_RETRY_DELAY)
+    }
+}
+
+/// Error of a command run by `process_command`.
+struct CommandError {
+    message: String,
+    retryable: bool,
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Commit description as reported by `git log`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommitInfo {
+    pub id: String,
+    pub author: String,
+    pub date: String,
+    pub summary: String,
+}
 
 impl Default for GitVersionControl {
     fn default() -> Self {
@@ -21,19 +150,339 @@ impl Default for GitVersionControl {
 
 impl GitVersionControl {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            retry: Retry::default(),
+            timeout: None,
+            cancellation: Cancellation::default(),
+            cache: None,
+        }
+    }
+
+    pub fn set_retry(&mut self, retry: Retry) {
+        self.retry = retry;
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn set_cancellation(&mut self, cancellation: Cancellation) {
+        self.cancellation = cancellation;
     }
 
-    pub async fn init(
+    pub fn set_cache(&mut self, cache: Option<Arc<Cache>>) {
+        self.cache = cache;
+    }
+
+    /// Create the mirror of the project in the cache and let the repository borrow its objects.
+    /// The cache is only an optimization: on error, the repository fetches everything itself.
+    async fn init_mirror(&self, cache: &Cache, repo_path: &Path, project: &Project, url: &str) {
+        let mirror = cache.get_mirror_path(project);
+        let _lock = cache.lock(&mirror).await;
+
+        if let Err(e) = init_mirror(&mirror, url, self.timeout).await {
+            warn!("Failed to initialize the cache {}: {e}", mirror.display());
+            return;
+        }
+        // The automatic gc of the fetches would prune the objects still borrowed by the projects
+        if let Err(e) = run_git(&mirror, self.timeout, &["config", "gc.auto", "0"]).await {
+            warn!("Failed to initialize the cache {}: {e}", mirror.display());
+            return;
+        }
+
+        // The store of the workspace is borrowed relative to `.git/objects`, the workspace may move
+        let repo_objects = repo_path.join(".git/objects");
+        let objects = mirror.join("objects");
+        let absolute = objects.display().to_string();
+        let entry = match cache.is_in_workspace() {
+            true => get_relative_path(&repo_objects, &objects)
+                .display()
+                .to_string(),
+            false => absolute.clone(),
+        };
+
+        let alternates = repo_objects.join("info/alternates");
+        let current = tokio::fs::read_to_string(&alternates)
+            .await
+            .unwrap_or_default();
+        let mut lines: Vec<&str> = current
+            .lines()
+            .filter(|line| *line != absolute && *line != entry)
+            .collect();
+        lines.push(&entry);
+        let content = format!("{}\n", lines.join("\n"));
+        if content != current {
+            if let Err(e) = tokio::fs::write(&alternates, content).await {
+                warn!("Failed to write {}: {e}", alternates.display());
+            }
+        }
+    }
+
+    /// Fetch the mirror of the project, once per synchronization. Return the mirror, unless
+    /// it could not be updated, with the statistics of the fetch done by this project, if any.
+    async fn update_mirror(
+        &self,
+        cache: &Cache,
+        project: &Project,
+        pb: Option<&ProgressBar>,
+    ) -> Result<Option<(PathBuf, Option<FetchStats>)>, ManifestError> {
+        let mirror = cache.get_mirror_path(project);
+        let mut updated = cache.lock(&mirror).await;
+        match *updated {
+            Some(true) => return Ok(Some((mirror, None))),
+            Some(false) => return Ok(None),
+            None if !mirror.exists() => return Ok(None),
+            None => {}
+        }
+
+        debug!("Updating cache {}", mirror.display());
+
+        let args = ["fetch", "--progress", "--prune", "origin"].map(String::from);
+        let display_status = [
+            format!("{} fetch cache", project.get_path()),
+            format!("{} fetch", project.get_path()),
+            format!("{} ERROR", project.get_path()),
+        ];
+        let result = self
+            .fetch_with_retry(&mirror, &args, project, pb, &display_status)
+            .await;
+        *updated = Some(result.is_ok());
+        match result {
+            Ok(stats) => Ok(Some((mirror, Some(stats)))),
+            // The next synchronization updates the mirror
+            Err(ManifestError::Interrupted(path)) => {
+                *updated = None;
+                Err(ManifestError::Interrupted(path))
+            }
+            Err(e) => {
+                warn!("Failed to update the cache {}: {e}", mirror.display());
+                Ok(None)
+            }
+        }
+    }
+
+    /// Create or update the bare mirror of the repository of `project` in `mirror_dir`, with all
+    /// its refs. Return the statistics of the fetch and whether it changed any ref.
+    pub async fn mirror(
+        &self,
+        mirror_dir: &Path,
+        project: &Project,
+        mode: &DwlMode,
+        pb: Option<&ProgressBar>,
+    ) -> Result<(FetchStats, bool), ManifestError> {
+        let mirror = get_mirror_path(mirror_dir, project);
+        let url = get_url(project, mode);
+
+        debug!("Mirroring {} into {}", url, mirror.display());
+
+        let map_err = |e: String| {
+            let msg = format!("{}\n{}\n", mirror.display(), e);
+            ManifestError::FailedToInitialize(msg)
+        };
+        init_mirror(&mirror, &url, self.timeout)
+            .await
+            .map_err(map_err)?;
+        let refs = ["for-each-ref", "--format=%(objectname) %(refname)"];
+        let old_refs = run_git(&mirror, self.timeout, &refs)
+            .await
+            .map_err(map_err)?;
+
+        let args = ["fetch", "--progress", "--prune", "origin"].map(String::from);
+        let display_status = [
+            format!("{} fetch", project.get_path()),
+            format!("{} complete", project.get_path()),
+            format!("{} ERROR", project.get_path()),
+        ];
+        let stats = self
+            .fetch_with_retry(&mirror, &args, project, pb, &display_status)
+            .await?;
+
+        let new_refs = run_git(&mirror, self.timeout, &refs)
+            .await
+            .map_err(map_err)?;
+        Ok((stats, old_refs != new_refs))
+    }
+
+    /// Fetch `depth` more commits of the history of the project, keeping its partial clone filter.
+    /// Without depth, fetch the whole history and the objects filtered out, converting a shallow
+    /// or partial clone to a full one. Return false if there was nothing to fetch.
+    pub async fn deepen(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        depth: Option<u32>,
+        pb: Option<&ProgressBar>,
+    ) -> Result<bool, ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+
+        debug!("Deepening {} by {:?}", repo_path.display(), depth);
+
+        if !repo_path.join(".git").exists() {
+            let msg = format!("{} is not synchronized", project.get_path());
+            return Err(ManifestError::FailedToDeepen(msg));
+        }
+
+        let display_status = [
+            format!("{} deepen", project.get_path()),
+            format!("{} complete", project.get_path()),
+            format!("{} ERROR", project.get_path()),
+        ];
+        let result = match depth {
+            Some(depth) => {
+                // The boundary commits of a shallow clone are listed in `.git/shallow`
+                let shallow = repo_path.join(".git/shallow");
+                let boundary = tokio::fs::read_to_string(&shallow).await.ok();
+                if boundary.is_none() {
+                    return Ok(false);
+                }
+                let args = get_origin_fetch_args(&[&format!("--deepen={depth}")]);
+                match self
+                    .fetch_with_retry(&repo_path, &args, project, pb, &display_status)
+                    .await
+                {
+                    Ok(_) => Ok(tokio::fs::read_to_string(&shallow).await.ok() != boundary),
+                    Err(e) => Err(e),
+                }
+            }
+            None => {
+                self.complete(&repo_path, project, pb, &display_status)
+                    .await
+            }
+        };
+        match result {
+            Err(ManifestError::FailedToCheckoutRepository(msg)) => {
+                Err(ManifestError::FailedToDeepen(msg))
+            }
+            result => result,
+        }
+    }
+
+    /// Fetch the whole history of the shallow clone in `repo_path`, then the objects filtered
+    /// out of the partial clone. Return false when the repository is already complete.
+    async fn complete(
+        &self,
+        repo_path: &Path,
+        project: &Project,
+        pb: Option<&ProgressBar>,
+        display_status: &[String; DISPLAY_STATUS_SIZE],
+    ) -> Result<bool, ManifestError> {
+        let shallow = run_git(
+            repo_path,
+            self.timeout,
+            &["rev-parse", "--is-shallow-repository"],
+        )
+        .await
+        .is_ok_and(|value| value == "true");
+        let filter = get_partial_clone_filter(repo_path, self.timeout).await;
+        if !shallow && filter.is_none() {
+            return Ok(false);
+        }
+
+        // Fetched with the filter still configured, refetching while unshallowing would fail
+        // on the blobs of the older commits
+        if shallow {
+            let args = get_origin_fetch_args(&["--tags", "--prune", "--unshallow"]);
+            self.fetch_with_retry(repo_path, &args, project, pb, display_status)
+                .await?;
+        }
+
+        if let Some(filter) = filter {
+            // The configured filter would apply to the fetch
+            unset_partial_clone_filter(repo_path, self.timeout).await;
+            let args = get_origin_fetch_args(&["--tags", "--prune", "--refetch"]);
+            let result = self
+                .fetch_with_retry(repo_path, &args, project, pb, display_status)
+                .await;
+            // Without the filter, the objects missing would not be downloaded when needed
+            if result.is_err() {
+                set_partial_clone_filter(repo_path, &filter, self.timeout).await?;
+            }
+            result?;
+        }
+        Ok(true)
+    }
+
+    /// Run `git` with the fetch `args` in `repo_path`, retrying on transient network errors.
+    async fn fetch_with_retry(
+        &self,
+        repo_path: &Path,
+        args: &[String],
+        project: &Project,
+        pb: Option<&ProgressBar>,
+        display_status: &[String; DISPLAY_STATUS_SIZE],
+    ) -> Result<FetchStats, ManifestError> {
+        let mut attempt = 1;
+        loop {
+            let mut command = git_command();
+            command
+                .current_dir(repo_path)
+                .args(args)
+                .stderr(Stdio::piped())
+                .stdout(Stdio::null());
+
+            let result = process_command(
+                &mut command,
+                pb,
+                display_status,
+                self.timeout,
+                Some(&self.cancellation),
+            )
+            .await;
+            match result {
+                Ok(stderr) => {
+                    return Ok(FetchStats {
+                        attempts: attempt,
+                        bytes: get_received_bytes(&stderr),
+                    })
+                }
+                Err(_) if self.cancellation.is_cancelled() => {
+                    return Err(ManifestError::Interrupted(project.get_path().clone()));
+                }
+                Err(e) if e.retryable && attempt <= self.retry.retries => {
+                    let delay = self.retry.get_delay(attempt);
+                    debug!(
+                        "Fetching {} failed, retry in {:?}: {}",
+                        project.get_path(),
+                        delay,
+                        e
+                    );
+                    if let Some(pb) = pb {
+                        pb.set_message(format!(
+                            "{} retry {}/{}",
+                            project.get_path(),
+                            attempt,
+                            self.retry.retries
+                        ));
+                    }
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => {}
+                        _ = self.cancellation.cancelled() => {
+                            return Err(ManifestError::Interrupted(project.get_path().clone()));
+                        }
+                    }
+                    attempt += 1;
+                }
+                Err(e) => {
+                    let mut msg = format!("{}\n{}\n", project.get_path(), e);
+                    if attempt > 1 {
+                        msg.push_str(&format!("Failed after {attempt} attempts\n"));
+                    }
+                    return Err(ManifestError::FailedToCheckoutRepository(msg));
+                }
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl XVersionControl for GitVersionControl {
+    async fn init(
         &self,
         manifest_dir: &Path,
         project: &Project,
         mode: &DwlMode,
     ) -> Result<(), ManifestError> {
-        let url = match mode {
-            DwlMode::HTTPS => project.get_uri_https(),
-            DwlMode::SSH => project.get_uri_ssh(),
-        };
+        let url = get_url(project, mode);
 
         let repo_path = manifest_dir.join(project.get_path());
 
@@ -43,13 +492,100 @@ impl GitVersionControl {
             repo_path.display()
         );
 
-        init_repository(&repo_path).await?;
-        init_origin(&repo_path, &url).await?;
+        init_repository(&repo_path, self.timeout).await?;
+        init_origin(&repo_path, &url, self.timeout).await?;
+        init_push_url(
+            &repo_path,
+            get_push_url(project, mode).as_deref(),
+            self.timeout,
+        )
+        .await
+        .map_err(ManifestError::FailedToInitialize)?;
+
+        if let Some(cache) = &self.cache {
+            self.init_mirror(cache, &repo_path, project, &url).await;
+        }
 
         Ok(())
     }
 
-    pub async fn checkout(
+    /// Fetch the manifest revision from the remote. Transient network errors are retried.
+    async fn fetch(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        pb: Option<&ProgressBar>,
+        lightweight: bool,
+    ) -> Result<FetchStats, ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+
+        debug!(
+            "Fetching {} into {} @ {}",
+            project.get_name(),
+            repo_path.display(),
+            project.get_revision()
+        );
+
+        // Filter out blob for lightweight clone and sparse checkout, the blobs of the sparse
+        // paths are downloaded by the checkout. The cache would download everything.
+        let filter = match project.get_filter() {
+            Some(filter) => Some(filter.as_str()),
+            None if lightweight || !project.get_sparse_paths().is_empty() => Some(BLOB_FILTER),
+            None => None,
+        };
+        let mut mirror = None;
+        if let Some(filter) = filter {
+            set_partial_clone_filter(&repo_path, filter, self.timeout).await?;
+        } else if let (Some(cache), None) = (&self.cache, project.get_clone_depth()) {
+            mirror = self.update_mirror(cache, project, pb).await?;
+        }
+
+        // Fetch, retrying on transient network errors
+        let mut args = get_fetch_args(
+            &repo_path,
+            lightweight,
+            project.get_revision(),
+            project.get_clone_depth(),
+            self.timeout,
+        )
+        .await?;
+
+        // The objects are already in the mirror, only the refs are fetched, from the mirror
+        let mut mirror_stats = None;
+        if let Some((mirror, stats)) = mirror {
+            mirror_stats = stats;
+            args.pop();
+            args.push(mirror.display().to_string());
+            args.push("+refs/heads/*:refs/remotes/origin/*".to_string());
+        }
+
+        let display_status = [
+            format!("{} fetch", project.get_path()),
+            format!("{} complete", project.get_path()),
+            format!("{} ERROR", project.get_path()),
+        ];
+
+        let mut result = self
+            .fetch_with_retry(&repo_path, &args, project, pb, &display_status)
+            .await;
+
+        // Servers refuse the commits not advertised, unless `uploadpack.allowReachableSHA1InWant`
+        let shallow = lightweight || project.get_clone_depth().is_some();
+        if shallow && is_commit_id(project.get_revision()) {
+            if let Err(ManifestError::FailedToCheckoutRepository(e)) = &result {
+                debug!("Fetching the commit alone failed, fetching everything: {e}");
+                let revision = project.get_revision();
+                let args = get_fetch_args(&repo_path, false, revision, None, self.timeout).await?;
+                result = self
+                    .fetch_with_retry(&repo_path, &args, project, pb, &display_status)
+                    .await;
+            }
+        }
+        Ok(mirror_stats.unwrap_or(result?))
+    }
+
+    /// Update the working tree to the fetched revision, without accessing the remote.
+    async fn update(
         &self,
         manifest_dir: &Path,
         project: &Project,
@@ -66,15 +602,23 @@ impl GitVersionControl {
             project.get_revision()
         );
 
-        // Filter out blob for lightweight clone
-        if lightweight {
-            filter_out_blob(&repo_path).await?;
-        }
-
-        // Fetch
-        let args = get_fetch_args(&repo_path, lightweight, project.get_revision()).await?;
+        // Restrict the working tree to the sparse paths before the checkout
+        set_sparse_paths(&repo_path, project.get_sparse_paths(), self.timeout)
+            .await
+            .map_err(|e| {
+                let msg = format!("{}\n{}\n", project.get_path(), e);
+                ManifestError::FailedToCheckoutRepository(msg)
+            })?;
 
-        let mut command = Command::new("git");
+        // Checkout
+        let args = if force {
+            ["checkout", project.get_revision(), "--progress", "--force"].to_vec()
+        } else if lightweight && !is_commit_id(project.get_revision()) {
+            ["checkout", "FETCH_HEAD", "--progress"].to_vec()
+        } else {
+            ["checkout", project.get_revision(), "--progress"].to_vec()
+        };
+        let mut command = local_git_command();
         command
             .current_dir(&repo_path)
             .args(args)
@@ -82,11 +626,12 @@ impl GitVersionControl {
             .stdout(Stdio::null());
 
         let display_status = [
-            format!("{} fetch", project.get_path()),
+            format!("{} checkout", project.get_path()),
             format!("{} complete", project.get_path()),
             format!("{} ERROR", project.get_path()),
         ];
-        match process_command(&mut command, pb, &display_status).await {
+        let result = process_command(&mut command, pb, &display_status, self.timeout, None).await;
+        match result {
             Ok(_) => {}
             Err(e) => {
                 let msg = format!("{}\n{}\n", project.get_path(), e);
@@ -94,160 +639,639 @@ impl GitVersionControl {
             }
         }
 
-        // Checkout
-        let args = if force {
-            ["checkout", project.get_revision(), "--progress", "--force"].to_vec()
-        } else if lightweight {
-            ["checkout", "FETCH_HEAD", "--progress"].to_vec()
-        } else {
-            ["checkout", project.get_revision(), "--progress"].to_vec()
-        };
-        let mut command = Command::new("git");
-        command
-            .current_dir(&repo_path)
-            .args(args)
-            .stderr(Stdio::piped())
-            .stdout(Stdio::null());
+        // Check if repository is dirty
+        let output = output(
+            local_git_command().current_dir(&repo_path).args([
+                "status",
+                "--porcelain",
+                "--untracked-files=no",
+            ]),
+            self.timeout,
+        )
+        .await;
+
+        match output {
+            Ok(output) => {
+                let message = String::from_utf8_lossy(&output.stdout).to_string();
+                if !message.is_empty() {
+                    if let Some(pb) = pb {
+                        pb.set_message(format!("{} ERROR", project.get_path()));
+                    }
+                    let msg = format!(
+                        "{}, repository is dirty, please commit or stash your changes",
+                        project.get_path()
+                    );
+                    return Err(ManifestError::FailedToCheckoutRepository(msg));
+                }
+            }
+            Err(e) => {
+                let msg = format!(
+                    "{}. Failed to check repository status: \n{}\n",
+                    project.get_path(),
+                    e
+                );
+                return Err(ManifestError::FailedToCheckoutRepository(msg));
+            }
+        }
+
+        if is_branch(manifest_dir, project, self.timeout).await {
+            let mut command = local_git_command();
+            command
+                .current_dir(&repo_path)
+                .args(["merge", "--ff-only", "--progress"])
+                .stderr(Stdio::piped())
+                .stdout(Stdio::null());
+
+            let display_status = [
+                format!("{} merge", project.get_path()),
+                format!("{} complete", project.get_path()),
+                format!("{} ERROR", project.get_path()),
+            ];
+
+            let result =
+                process_command(&mut command, pb, &display_status, self.timeout, None).await;
+            match result {
+                Ok(_) => {}
+                Err(e) => {
+                    let msg = format!("{}\n{}\n", project.get_path(), e);
+                    return Err(ManifestError::FailedToCheckoutRepository(msg));
+                }
+            }
+        }
+
+        if let Some(pb) = pb {
+            pb.finish();
+        }
+        Ok(())
+    }
+
+    async fn get_commit_id(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+    ) -> Result<String, ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+
+        debug!(
+            "Getting commit id for {} into {}",
+            project.get_name(),
+            repo_path.display()
+        );
+
+        let output = output(
+            git_command()
+                .current_dir(&repo_path)
+                .args(["rev-parse", "HEAD"]),
+            self.timeout,
+        )
+        .await;
+
+        match output {
+            Ok(output) => {
+                let message = String::from_utf8_lossy(&output.stdout)
+                    .to_string()
+                    .replace(['\n', '\r'], "");
+                Ok(message)
+            }
+            Err(e) => {
+                let msg = format!("{path}\n{e}\n", path = project.get_path());
+                Err(ManifestError::FailedToGetCommitId(msg))
+            }
+        }
+    }
+
+    async fn is_modified(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+    ) -> Result<bool, ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+
+        let output = output(
+            git_command().current_dir(&repo_path).args([
+                "status",
+                "--porcelain",
+                "--untracked-files=no",
+            ]),
+            self.timeout,
+        )
+        .await;
+
+        match output {
+            Ok(output) => {
+                let message = String::from_utf8_lossy(&output.stdout).to_string();
+                Ok(!message.is_empty())
+            }
+            Err(e) => {
+                let msg = format!("{path}\n{e}\n", path = project.get_path());
+                Err(ManifestError::FailedToDetermineIfRepoIsModified(msg))
+            }
+        }
+    }
+}
+
+impl GitVersionControl {
+    /// Return true if the project has modified or untracked files.
+    pub async fn has_local_changes(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+    ) -> Result<bool, ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+
+        run_git(&repo_path, self.timeout, &["status", "--porcelain"])
+            .await
+            .map(|output| !output.is_empty())
+            .map_err(|e| {
+                let msg = format!("{}\n{}\n", project.get_path(), e);
+                ManifestError::FailedToDetermineIfRepoIsModified(msg)
+            })
+    }
+
+    /// Return true if a local branch, or a detached HEAD, has commits not found on any remote
+    /// branch or tag.
+    pub async fn has_unpushed_commits(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+    ) -> Result<bool, ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+
+        // HEAD is detached after synchronizing to a tag or a commit id, unborn before the first
+        // checkout
+        let mut args = vec!["rev-list", "--count", "--branches"];
+        let head = run_git(&repo_path, self.timeout, &["rev-parse", "--verify", "HEAD"]).await;
+        if head.is_ok() {
+            args.push("HEAD");
+        }
+        args.extend(["--not", "--remotes", "--tags"]);
+
+        run_git(&repo_path, self.timeout, &args)
+            .await
+            .map(|count| count != "0")
+            .map_err(|e| {
+                let msg = format!("{}\n{}\n", project.get_path(), e);
+                ManifestError::FailedToDetermineIfRepoIsModified(msg)
+            })
+    }
+
+    /// Return the commit id of a revision, trying the remote branch if no local reference matches.
+    pub async fn resolve_revision(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        revision: &str,
+    ) -> Option<String> {
+        let repo_path = manifest_dir.join(project.get_path());
+
+        for candidate in [revision.to_string(), format!("origin/{revision}")] {
+            let output = output(
+                git_command()
+                    .current_dir(&repo_path)
+                    .args(["rev-parse", "--verify", "--quiet"])
+                    .arg(format!("{candidate}^{{commit}}")),
+                self.timeout,
+            )
+            .await;
+
+            if let Ok(output) = output {
+                if output.status.success() {
+                    return Some(String::from_utf8_lossy(&output.stdout).trim().to_string());
+                }
+            }
+        }
+        None
+    }
+
+    /// Fetch all branches and tags from origin, without updating the working tree.
+    pub async fn fetch_all(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+    ) -> Result<(), ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+
+        debug!(
+            "Fetching {} into {}",
+            project.get_name(),
+            repo_path.display()
+        );
+
+        run_git(
+            &repo_path,
+            self.timeout,
+            &["fetch", "--quiet", "--tags", "origin"],
+        )
+        .await
+        .map_err(|e| {
+            let msg = format!("{}\n{}\n", project.get_path(), e);
+            ManifestError::FailedToFetch(msg)
+        })?;
+        Ok(())
+    }
+
+    /// Return true if `ancestor` is reachable from `descendant`.
+    pub async fn is_ancestor(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        ancestor: &str,
+        descendant: &str,
+    ) -> Result<bool, ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+
+        let output = output(
+            git_command().current_dir(&repo_path).args([
+                "merge-base",
+                "--is-ancestor",
+                ancestor,
+                descendant,
+            ]),
+            self.timeout,
+        )
+        .await
+        .map_err(|e| {
+            let msg = format!("{}\n{}\n", project.get_path(), e);
+            ManifestError::FailedToGetLog(msg)
+        })?;
+
+        // Exit code 1 means not an ancestor, any other error code is a failure
+        match output.status.code() {
+            Some(0) => Ok(true),
+            Some(1) => Ok(false),
+            _ => {
+                let msg = format!(
+                    "{}\n{}\n",
+                    project.get_path(),
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+                Err(ManifestError::FailedToGetLog(msg))
+            }
+        }
+    }
+
+    /// List the commits reachable from `to` but not from `from`, newest first.
+    pub async fn log(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<CommitInfo>, ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+
+        let range = format!("{from}..{to}");
+        let output = run_git(
+            &repo_path,
+            self.timeout,
+            &["log", "--format=%H%x1f%an%x1f%aI%x1f%s", &range],
+        )
+        .await
+        .map_err(|e| {
+            let msg = format!("{}\n{}\n", project.get_path(), e);
+            ManifestError::FailedToGetLog(msg)
+        })?;
+
+        let commits = output
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split('\x1f');
+                Some(CommitInfo {
+                    id: fields.next()?.to_string(),
+                    author: fields.next()?.to_string(),
+                    date: fields.next()?.to_string(),
+                    summary: fields.next().unwrap_or("").to_string(),
+                })
+            })
+            .collect();
+
+        Ok(commits)
+    }
+
+    /// Return the checked out branch, or None when HEAD is detached.
+    pub async fn get_current_branch(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+    ) -> Option<String> {
+        let repo_path = manifest_dir.join(project.get_path());
+
+        run_git(
+            &repo_path,
+            self.timeout,
+            &["symbolic-ref", "--short", "--quiet", "HEAD"],
+        )
+        .await
+        .ok()
+    }
+
+    /// Return the fetch URL of `remote`.
+    pub async fn get_remote_url(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        remote: &str,
+    ) -> Result<String, ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+
+        run_git(&repo_path, self.timeout, &["remote", "get-url", remote])
+            .await
+            .map_err(ManifestError::FailedToImport)
+    }
+
+    /// Create and checkout a local branch at the manifest revision.
+    /// If the branch already exists, it is checked out without being moved.
+    pub async fn start_branch(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        branch: &str,
+    ) -> Result<(), ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+
+        debug!(
+            "Starting branch {} in {} @ {}",
+            branch,
+            repo_path.display(),
+            project.get_revision()
+        );
+
+        let local_ref = format!("refs/heads/{branch}");
+        let exists = run_git(
+            &repo_path,
+            self.timeout,
+            &["rev-parse", "--verify", "--quiet", &local_ref],
+        )
+        .await
+        .is_ok();
+
+        let result = if exists {
+            run_git(&repo_path, self.timeout, &["checkout", "--quiet", branch]).await
+        } else {
+            let start_point = self
+                .resolve_revision(manifest_dir, project, project.get_revision())
+                .await
+                .ok_or_else(|| {
+                    let msg = format!(
+                        "{}, unable to find revision {}",
+                        project.get_path(),
+                        project.get_revision()
+                    );
+                    ManifestError::FailedToCreateBranch(msg)
+                })?;
+            run_git(
+                &repo_path,
+                self.timeout,
+                &["checkout", "--quiet", "-b", branch, &start_point],
+            )
+            .await
+        };
+
+        result.map_err(|e| {
+            let msg = format!("{}\n{}\n", project.get_path(), e);
+            ManifestError::FailedToCreateBranch(msg)
+        })?;
+        Ok(())
+    }
+
+    /// Stage the modifications of tracked files and commit them. Return the new commit id.
+    pub async fn commit(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        message: &str,
+    ) -> Result<String, ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+
+        debug!("Committing {}", repo_path.display());
+
+        let map_err = |e: String| {
+            let msg = format!("{}\n{}\n", project.get_path(), e);
+            ManifestError::FailedToCommit(msg)
+        };
+
+        run_git(
+            &repo_path,
+            self.timeout,
+            &["commit", "--all", "--quiet", "-m", message],
+        )
+        .await
+        .map_err(map_err)?;
+        run_git(&repo_path, self.timeout, &["rev-parse", "HEAD"])
+            .await
+            .map_err(map_err)
+    }
+
+    /// Create an annotated tag on `target`.
+    pub async fn tag(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        name: &str,
+        message: &str,
+        target: &str,
+    ) -> Result<(), ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+
+        debug!("Tagging {} in {} @ {}", name, repo_path.display(), target);
+
+        run_git(
+            &repo_path,
+            self.timeout,
+            &["tag", "--annotate", "-m", message, name, target],
+        )
+        .await
+        .map_err(|e| {
+            let msg = format!("{}\n{}\n", project.get_path(), e);
+            ManifestError::FailedToTag(msg)
+        })?;
+        Ok(())
+    }
+
+    /// Delete a local tag.
+    pub async fn delete_tag(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        name: &str,
+    ) -> Result<(), ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
+
+        debug!("Deleting tag {} in {}", name, repo_path.display());
 
-        let display_status = [
-            format!("{} checkout", project.get_path()),
-            format!("{} complete", project.get_path()),
-            format!("{} ERROR", project.get_path()),
-        ];
-        match process_command(&mut command, pb, &display_status).await {
-            Ok(_) => {}
-            Err(e) => {
+        run_git(&repo_path, self.timeout, &["tag", "--delete", name])
+            .await
+            .map_err(|e| {
                 let msg = format!("{}\n{}\n", project.get_path(), e);
-                return Err(ManifestError::FailedToCheckoutRepository(msg));
-            }
-        }
-
-        // Check if repository is dirty
-        let output = Command::new("git")
-            .current_dir(&repo_path)
-            .args(["status", "--porcelain", "--untracked-files=no"])
-            .output()
-            .await;
+                ManifestError::FailedToTag(msg)
+            })?;
+        Ok(())
+    }
 
-        match output {
-            Ok(output) => {
-                let message = String::from_utf8_lossy(&output.stdout).to_string();
-                if !message.is_empty() {
-                    if let Some(pb) = pb {
-                        pb.set_message(format!("{} ERROR", project.get_path()));
-                    }
-                    let msg = format!(
-                        "{}, repository is dirty, please commit or stash your changes",
-                        project.get_path()
-                    );
-                    return Err(ManifestError::FailedToCheckoutRepository(msg));
-                }
-            }
-            Err(e) => {
-                let msg = format!(
-                    "{}. Failed to check repository status: \n{}\n",
-                    project.get_path(),
-                    e
-                );
-                return Err(ManifestError::FailedToCheckoutRepository(msg));
-            }
-        }
+    /// Push a tag to origin, using the push URI of the project if any.
+    pub async fn push_tag(
+        &self,
+        manifest_dir: &Path,
+        project: &Project,
+        mode: &DwlMode,
+        name: &str,
+    ) -> Result<(), ManifestError> {
+        let repo_path = manifest_dir.join(project.get_path());
 
-        if is_branch(manifest_dir, project).await {
-            let mut command = Command::new("git");
-            command
-                .current_dir(&repo_path)
-                .args(["merge", "--ff-only", "--progress"])
-                .stderr(Stdio::piped())
-                .stdout(Stdio::null());
+        debug!("Pushing tag {} from {}", name, repo_path.display());
 
-            let display_status = [
-                format!("{} merge", project.get_path()),
-                format!("{} complete", project.get_path()),
-                format!("{} ERROR", project.get_path()),
-            ];
+        let map_err = |e: String| {
+            let msg = format!("{}\n{}\n", project.get_path(), e);
+            ManifestError::FailedToPush(msg)
+        };
 
-            match process_command(&mut command, pb, &display_status).await {
-                Ok(_) => {}
-                Err(e) => {
-                    let msg = format!("{}\n{}\n", project.get_path(), e);
-                    return Err(ManifestError::FailedToCheckoutRepository(msg));
-                }
-            }
-        }
+        init_push_url(
+            &repo_path,
+            get_push_url(project, mode).as_deref(),
+            self.timeout,
+        )
+        .await
+        .map_err(map_err)?;
 
-        if let Some(pb) = pb {
-            pb.finish();
-        }
+        let refspec = format!("refs/tags/{name}:refs/tags/{name}");
+        run_git(
+            &repo_path,
+            self.timeout,
+            &["push", "--quiet", "origin", &refspec],
+        )
+        .await
+        .map_err(map_err)?;
         Ok(())
     }
 
-    pub async fn get_commit_id(
+    /// Push a local branch to origin, using the push URI of the project if any.
+    /// Return the push status reported by git, ex: "[new branch]" or "[up to date]".
+    pub async fn push(
         &self,
         manifest_dir: &Path,
         project: &Project,
+        mode: &DwlMode,
+        branch: &str,
+        dry_run: bool,
     ) -> Result<String, ManifestError> {
         let repo_path = manifest_dir.join(project.get_path());
 
-        debug!(
-            "Getting commit id for {} into {}",
-            project.get_name(),
-            repo_path.display()
-        );
+        debug!("Pushing {} from {}", branch, repo_path.display());
 
-        let output = Command::new("git")
-            .current_dir(&repo_path)
-            .args(["rev-parse", "HEAD"])
-            .output()
-            .await;
+        let map_err = |e: String| {
+            let msg = format!("{}\n{}\n", project.get_path(), e);
+            ManifestError::FailedToPush(msg)
+        };
 
-        match output {
-            Ok(output) => {
-                let message = String::from_utf8_lossy(&output.stdout)
-                    .to_string()
-                    .replace(['\n', '\r'], "");
-                Ok(message)
-            }
-            Err(e) => {
-                let msg = format!("{path}\n{e}\n", path = project.get_path());
-                Err(ManifestError::FailedToGetCommitId(msg))
-            }
+        // The push URI may have been added to the manifest since the last sync
+        init_push_url(
+            &repo_path,
+            get_push_url(project, mode).as_deref(),
+            self.timeout,
+        )
+        .await
+        .map_err(map_err)?;
+
+        let refspec = format!("refs/heads/{branch}:refs/heads/{branch}");
+        let mut args = vec!["push", "--porcelain", "--set-upstream"];
+        if dry_run {
+            args.push("--dry-run");
         }
+        args.extend(["origin", &refspec]);
+
+        let output = run_git(&repo_path, self.timeout, &args)
+            .await
+            .map_err(map_err)?;
+
+        // Porcelain output: <flag> \t <from>:<to> \t <summary>
+        let summary = output
+            .lines()
+            .find(|line| line.contains(&refspec))
+            .and_then(|line| line.split('\t').nth(2))
+            .unwrap_or("")
+            .to_string();
+        Ok(summary)
     }
 
-    pub async fn is_modified(
+    /// Delete a local branch. If the branch is checked out, the manifest revision is checked out
+    /// first. A branch with commits found neither on a remote, a tag nor the manifest revision is
+    /// kept unless `force` is set.
+    pub async fn delete_branch(
         &self,
         manifest_dir: &Path,
         project: &Project,
-    ) -> Result<bool, ManifestError> {
+        branch: &str,
+        force: bool,
+    ) -> Result<(), ManifestError> {
         let repo_path = manifest_dir.join(project.get_path());
 
-        let output = Command::new("git")
-            .current_dir(&repo_path)
-            .args(["status", "--porcelain", "--untracked-files=no"])
-            .output()
-            .await;
+        debug!("Deleting branch {} in {}", branch, repo_path.display());
 
-        match output {
-            Ok(output) => {
-                let message = String::from_utf8_lossy(&output.stdout).to_string();
-                Ok(!message.is_empty())
+        let map_err = |e: String| {
+            let msg = format!("{}\n{}\n", project.get_path(), e);
+            ManifestError::FailedToDeleteBranch(msg)
+        };
+
+        if !force {
+            let mut args = vec![
+                "rev-list",
+                "--count",
+                branch,
+                "--not",
+                "--remotes",
+                "--tags",
+            ];
+            let revision = self
+                .resolve_revision(manifest_dir, project, project.get_revision())
+                .await;
+            if let Some(revision) = revision.as_deref() {
+                args.push(revision);
             }
-            Err(e) => {
-                let msg = format!("{path}\n{e}\n", path = project.get_path());
-                Err(ManifestError::FailedToDetermineIfRepoIsModified(msg))
+            let count = run_git(&repo_path, self.timeout, &args)
+                .await
+                .map_err(map_err)?;
+            if count != "0" {
+                let msg = format!(
+                    "{}, {branch} has unpushed commits\nUse --force to delete it anyway\n",
+                    project.get_path()
+                );
+                return Err(ManifestError::FailedToDeleteBranch(msg));
             }
         }
+
+        if self
+            .get_current_branch(manifest_dir, project)
+            .await
+            .as_deref()
+            == Some(branch)
+        {
+            run_git(
+                &repo_path,
+                self.timeout,
+                &["checkout", "--quiet", project.get_revision()],
+            )
+            .await
+            .map_err(map_err)?;
+        }
+
+        run_git(
+            &repo_path,
+            self.timeout,
+            &["branch", "--quiet", "-D", branch],
+        )
+        .await
+        .map_err(map_err)?;
+        Ok(())
     }
 }
 
-unsafe impl Sync for GitVersionControl {}
-
 async fn process_command(
     command: &mut Command,
     pb: Option<&ProgressBar>,
     message: &[String; DISPLAY_STATUS_SIZE],
-) -> Result<(), String> {
+    timeout: Option<Duration>,
+    cancellation: Option<&Cancellation>,
+) -> Result<String, CommandError> {
     const START_INDEX: usize = 0;
     const COMPLETE_INDEX: usize = 1;
     const ERROR_INDEX: usize = 2;
@@ -257,8 +1281,6 @@ async fn process_command(
 
     let mut stderr_capture: Vec<String> = Vec::new();
 
-    let re = Regex::new(r"(\d+)%").unwrap();
-
     if let Some(pb) = pb {
         pb.set_message(message[START_INDEX].clone());
     }
@@ -267,6 +1289,7 @@ async fn process_command(
         tokio::time::interval(tokio::time::Duration::from_millis(PROGRESS_REFRESH_RATE_MS));
     let mut stderr = tokio::io::BufReader::new(child.stderr.take().expect("Failed to take stderr"));
     let mut read_buffer = [0u8; BUFFER_SIZE];
+    let deadline = timeout.map(|timeout| tokio::time::Instant::now() + timeout);
 
     loop {
         // Read stream from stderr. We cannot use lines since git is using \r to overwrite the line.
@@ -280,7 +1303,7 @@ async fn process_command(
                     let info = msg.split(":").next().unwrap_or("").trim().to_string();
 
                     // Capture progress percentage and update progress bar
-                    if let Some(cap) = re.captures(&msg) {
+                    if let Some(cap) = PERCENT.captures(&msg) {
                         if let Some(match_) = cap.get(1) {
                             if let Ok(progress) = match_.as_str().parse::<u64>() {
                                 if let Some(pb) = pb {
@@ -294,6 +1317,22 @@ async fn process_command(
                 }
             }
             _ = interval.tick() => {}
+            _ = sleep_until(deadline) => {
+                let _ = child.kill().await;
+                if let Some(pb) = pb {
+                    pb.set_message(message[ERROR_INDEX].clone());
+                }
+                let message = timed_out(command, timeout.unwrap_or_default()).to_string();
+                return Err(CommandError { message, retryable: true });
+            }
+            _ = wait_cancelled(cancellation) => {
+                let _ = child.kill().await;
+                if let Some(pb) = pb {
+                    pb.set_message(message[ERROR_INDEX].clone());
+                }
+                let message = "Interrupted".to_string();
+                return Err(CommandError { message, retryable: false });
+            }
             exit_status = child.wait() => {
                 match exit_status {
                     Ok(exit_status) => {
@@ -316,18 +1355,20 @@ async fn process_command(
                                 stderr_capture.push(line);
                             }
 
+                            let retryable = is_retryable(&stderr_capture.concat());
                             let start = stderr_capture.iter().position(|line| line.contains("fatal") || line.contains("error"));
                             if let Some(start) = start {
                                 let error_message: String = stderr_capture
                                 .iter()
                                 .skip(start)
                                 .fold(String::new(), |acc, line| acc + line);
-                                return Err(error_message);
+                                return Err(CommandError { message: error_message, retryable });
                             }
-                            return Err("Failed to capture git error message".to_string());
+                            let message = "Failed to capture git error message".to_string();
+                            return Err(CommandError { message, retryable });
                         }
                     },
-                    Err(e) => return Err(e.to_string()),
+                    Err(e) => return Err(CommandError { message: e.to_string(), retryable: false }),
                 }
             }
         }
@@ -337,16 +1378,158 @@ async fn process_command(
         }
     }
 
-    Ok(())
+    // Keep the end of the output, written just before exiting
+    let mut remaining = String::new();
+    let _ = stderr.read_to_string(&mut remaining).await;
+    stderr_capture.push(remaining);
+
+    Ok(stderr_capture.concat())
+}
+
+/// Number of bytes received according to the progress output of `git fetch`. The size is only
+/// displayed by git for large transfers, `None` is returned when objects were received without it.
+fn get_received_bytes(stderr: &str) -> Option<u64> {
+    match RECEIVED.captures_iter(stderr).last() {
+        Some(cap) => {
+            let value = cap[1].parse::<f64>().ok()?;
+            let unit = match &cap[2] {
+                "KiB" => 1024.0,
+                "MiB" => 1024.0 * 1024.0,
+                "GiB" => 1024.0 * 1024.0 * 1024.0,
+                _ => 1.0,
+            };
+            Some((value * unit) as u64)
+        }
+        None if stderr.contains("Receiving objects") => None,
+        None => Some(0),
+    }
+}
+
+/// New git command, killed if it is dropped before completion.
+fn git_command() -> Command {
+    let mut command = Command::new("git");
+    command.kill_on_drop(true);
+    command
+}
+
+/// New git command which does not receive the Ctrl-C sent to Colligo, so that an operation on
+/// the working tree is not interrupted half-way.
+fn local_git_command() -> Command {
+    let mut command = git_command();
+    #[cfg(unix)]
+    command.process_group(0);
+    command
+}
+
+/// Run a command to completion, killing it after `timeout`.
+pub(crate) async fn output(
+    command: &mut Command,
+    timeout: Option<Duration>,
+) -> std::io::Result<Output> {
+    match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, command.output()).await {
+            Ok(output) => output,
+            Err(_) => Err(timed_out(command, timeout)),
+        },
+        None => command.output().await,
+    }
+}
+
+fn timed_out(command: &Command, timeout: Duration) -> std::io::Error {
+    let program = command.as_std().get_program().to_string_lossy();
+    let msg = format!("{program} timed out after {}s", timeout.as_secs_f32());
+    std::io::Error::new(std::io::ErrorKind::TimedOut, msg)
+}
+
+async fn sleep_until(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn wait_cancelled(cancellation: Option<&Cancellation>) {
+    match cancellation {
+        Some(cancellation) => cancellation.cancelled().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Full SHA-1 or SHA-256 commit id, which may be fetched alone. Abbreviated ids may not.
+pub fn is_commit_id(revision: &str) -> bool {
+    matches!(revision.len(), 40 | 64) && revision.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Whether a git error is caused by a transient network failure, worth retrying.
+/// Authentication errors and missing revisions are not.
+pub fn is_retryable(error: &str) -> bool {
+    const PERMANENT: [&str; 7] = [
+        "authentication failed",
+        "permission denied",
+        "could not read username",
+        "repository not found",
+        "does not appear to be a git repository",
+        "couldn't find remote ref",
+        "not our ref",
+    ];
+    const TRANSIENT: [&str; 8] = [
+        "connection reset",
+        "timed out",
+        "couldn't connect to server",
+        "the remote end hung up",
+        "early eof",
+        "unexpected disconnect",
+        "temporary failure in name resolution",
+        "connection refused",
+    ];
+
+    let error = error.to_lowercase();
+    if PERMANENT.iter().any(|pattern| error.contains(pattern)) {
+        return false;
+    }
+    if TRANSIENT.iter().any(|pattern| error.contains(pattern)) {
+        return true;
+    }
+
+    HTTP_5XX.is_match(&error)
+}
+
+/// Arguments of `git fetch` from `origin` with the `options`.
+fn get_origin_fetch_args(options: &[&str]) -> Vec<String> {
+    let mut args = vec!["fetch".to_string(), "--progress".to_string()];
+    args.extend(options.iter().map(|option| option.to_string()));
+    args.push("origin".to_string());
+    args
+}
+
+/// Execute a git command in a repository. Return stdout on success, stderr otherwise.
+async fn run_git(
+    repo_path: &Path,
+    timeout: Option<Duration>,
+    args: &[&str],
+) -> Result<String, String> {
+    let output = output(git_command().current_dir(repo_path).args(args), timeout)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
 }
 
-async fn is_branch<P: AsRef<Path>>(manifest_dir: P, project: &Project) -> bool {
+async fn is_branch<P: AsRef<Path>>(
+    manifest_dir: P,
+    project: &Project,
+    timeout: Option<Duration>,
+) -> bool {
     let repo_path = manifest_dir.as_ref().join(project.get_path());
-    let output = Command::new("git")
-        .current_dir(repo_path)
-        .args(["status"])
-        .output()
-        .await;
+    let output = output(
+        git_command().current_dir(repo_path).args(["status"]),
+        timeout,
+    )
+    .await;
     match output {
         Ok(value) => {
             let message = String::from_utf8_lossy(&value.stdout).to_string();
@@ -356,7 +1539,7 @@ async fn is_branch<P: AsRef<Path>>(manifest_dir: P, project: &Project) -> bool {
     }
 }
 
-async fn init_repository(path: &Path) -> Result<(), ManifestError> {
+async fn init_repository(path: &Path, timeout: Option<Duration>) -> Result<(), ManifestError> {
     // Create directory if it does not exist
     if !path.exists() {
         tokio::fs::create_dir_all(path)
@@ -364,12 +1547,12 @@ async fn init_repository(path: &Path) -> Result<(), ManifestError> {
             .map_err(|e| ManifestError::FailedToInitialize(e.to_string()))?;
     }
     // Init git repository
-    let out = Command::new("git")
-        .current_dir(path)
-        .args(["init", "--quiet"])
-        .output()
-        .await
-        .map_err(|e| ManifestError::FailedToInitialize(e.to_string()))?;
+    let out = output(
+        git_command().current_dir(path).args(["init", "--quiet"]),
+        timeout,
+    )
+    .await
+    .map_err(|e| ManifestError::FailedToInitialize(e.to_string()))?;
     if out.status.success() {
         Ok(())
     } else {
@@ -379,24 +1562,71 @@ async fn init_repository(path: &Path) -> Result<(), ManifestError> {
     }
 }
 
-async fn init_origin(path: &Path, url: &str) -> Result<(), ManifestError> {
+/// Create the bare mirror if needed and set the URL it mirrors.
+/// Path of `path` relative to the directory `base`.
+fn get_relative_path(base: &Path, path: &Path) -> PathBuf {
+    let base = std::path::absolute(base).unwrap_or(base.to_path_buf());
+    let path = std::path::absolute(path).unwrap_or(path.to_path_buf());
+    let base: Vec<Component> = base.components().collect();
+    let path: Vec<Component> = path.components().collect();
+    let common = base
+        .iter()
+        .zip(path.iter())
+        .take_while(|(base, path)| base == path)
+        .count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    relative.extend(&path[common..]);
+    relative
+}
+
+async fn init_mirror(mirror: &Path, url: &str, timeout: Option<Duration>) -> Result<(), String> {
+    if !mirror.join("HEAD").exists() {
+        tokio::fs::create_dir_all(mirror)
+            .await
+            .map_err(|e| e.to_string())?;
+        run_git(mirror, timeout, &["init", "--bare", "--quiet"]).await?;
+    }
+    run_git(mirror, timeout, &["config", "remote.origin.url", url]).await?;
+    run_git(
+        mirror,
+        timeout,
+        &["config", "remote.origin.fetch", "+refs/*:refs/*"],
+    )
+    .await?;
+    run_git(mirror, timeout, &["config", "remote.origin.mirror", "true"]).await?;
+    Ok(())
+}
+
+async fn init_origin(
+    path: &Path,
+    url: &str,
+    timeout: Option<Duration>,
+) -> Result<(), ManifestError> {
     // check if origin exists
-    let exists = Command::new("git")
-        .current_dir(path)
-        .args(["remote", "get-url", "origin"])
-        .output()
-        .await
-        .map(|o| o.status.success())
-        .map_err(|e| ManifestError::FailedToInitialize(e.to_string()))?;
+    let exists = output(
+        git_command()
+            .current_dir(path)
+            .args(["remote", "get-url", "origin"]),
+        timeout,
+    )
+    .await
+    .map(|o| o.status.success())
+    .map_err(|e| ManifestError::FailedToInitialize(e.to_string()))?;
 
     if exists {
         // update existing origin
-        let out = Command::new("git")
-            .current_dir(path)
-            .args(["remote", "set-url", "origin", url])
-            .output()
-            .await
-            .map_err(|e| ManifestError::FailedToInitialize(e.to_string()))?;
+        let out = output(
+            git_command()
+                .current_dir(path)
+                .args(["remote", "set-url", "origin", url]),
+            timeout,
+        )
+        .await
+        .map_err(|e| ManifestError::FailedToInitialize(e.to_string()))?;
         if out.status.success() {
             Ok(())
         } else {
@@ -406,12 +1636,14 @@ async fn init_origin(path: &Path, url: &str) -> Result<(), ManifestError> {
         }
     } else {
         // add new origin
-        let out = Command::new("git")
-            .current_dir(path)
-            .args(["remote", "add", "origin", url])
-            .output()
-            .await
-            .map_err(|e| ManifestError::FailedToInitialize(e.to_string()))?;
+        let out = output(
+            git_command()
+                .current_dir(path)
+                .args(["remote", "add", "origin", url]),
+            timeout,
+        )
+        .await
+        .map_err(|e| ManifestError::FailedToInitialize(e.to_string()))?;
         if out.status.success() {
             Ok(())
         } else {
@@ -422,18 +1654,68 @@ async fn init_origin(path: &Path, url: &str) -> Result<(), ManifestError> {
     }
 }
 
+pub(crate) fn get_url(project: &Project, mode: &DwlMode) -> String {
+    match mode {
+        DwlMode::HTTPS => project.get_uri_https(),
+        DwlMode::SSH => project.get_uri_ssh(),
+        DwlMode::Mirror(dir) => get_mirror_path(dir, project).display().to_string(),
+    }
+}
+
+/// Synchronized from a mirror, push with SSH to the push URI of the project, if any.
+pub(crate) fn get_push_url(project: &Project, mode: &DwlMode) -> Option<String> {
+    match mode {
+        DwlMode::HTTPS => project.get_push_uri_https(),
+        DwlMode::SSH | DwlMode::Mirror(_) => project.get_push_uri_ssh(),
+    }
+}
+
+/// Configure the push URL of origin, or remove it to push where we fetch from.
+async fn init_push_url(
+    path: &Path,
+    url: Option<&str>,
+    timeout: Option<Duration>,
+) -> Result<(), String> {
+    match url {
+        Some(url) => run_git(path, timeout, &["config", "remote.origin.pushurl", url])
+            .await
+            .map(|_| ()),
+        None => {
+            // Exit code 5 means the key was not set, which is fine
+            let output = output(
+                git_command().current_dir(path).args([
+                    "config",
+                    "--unset",
+                    "remote.origin.pushurl",
+                ]),
+                timeout,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+            match output.status.code() {
+                Some(0) | Some(5) => Ok(()),
+                _ => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+            }
+        }
+    }
+}
+
 async fn get_fetch_args(
     manifest_dir: &Path,
     lightweight: bool,
     revision: &str,
+    clone_depth: Option<u32>,
+    timeout: Option<Duration>,
 ) -> Result<Vec<String>, ManifestError> {
     // Is shallow?
-    let out = Command::new("git")
-        .current_dir(manifest_dir)
-        .args(["rev-parse", "--is-shallow-repository"])
-        .output()
-        .await
-        .map_err(|e| ManifestError::FailedToCheckoutRepository(e.to_string()))?;
+    let out = output(
+        git_command()
+            .current_dir(manifest_dir)
+            .args(["rev-parse", "--is-shallow-repository"]),
+        timeout,
+    )
+    .await
+    .map_err(|e| ManifestError::FailedToCheckoutRepository(e.to_string()))?;
 
     if !out.status.success() {
         return Err(ManifestError::FailedToCheckoutRepository(
@@ -446,7 +1728,18 @@ async fn get_fetch_args(
         .contains("true");
 
     // Set args
-    if lightweight {
+    if lightweight && is_commit_id(revision) {
+        // The tags would download their commits
+        Ok(vec![
+            "fetch".to_string(),
+            "--progress".to_string(),
+            "--no-tags".to_string(),
+            "--depth".to_string(),
+            "1".to_string(),
+            "origin".to_string(),
+            revision.to_string(),
+        ])
+    } else if lightweight {
         Ok(vec![
             "fetch".to_string(),
             "--progress".to_string(),
@@ -457,6 +1750,21 @@ async fn get_fetch_args(
             "origin".to_string(),
             revision.to_string(),
         ])
+    } else if let Some(depth) = clone_depth {
+        let mut args = vec![
+            "fetch".to_string(),
+            "--progress".to_string(),
+            "--tags".to_string(),
+            "--prune".to_string(),
+            "--depth".to_string(),
+            depth.to_string(),
+            "origin".to_string(),
+        ];
+        // The history of the branches may not reach the commit
+        if is_commit_id(revision) {
+            args.push(revision.to_string());
+        }
+        Ok(args)
     } else if is_shallow {
         Ok(vec![
             "fetch".to_string(),
@@ -478,32 +1786,77 @@ async fn get_fetch_args(
     }
 }
 
-async fn filter_out_blob(repo_path: &Path) -> Result<(), ManifestError> {
-    let out = Command::new("git")
-        .current_dir(repo_path)
-        .args(["config", "remote.origin.promisor", "true"])
-        .output()
+/// Configure a cone mode sparse checkout of `paths`, or disable it if there is none.
+async fn set_sparse_paths(
+    repo_path: &Path,
+    paths: &[String],
+    timeout: Option<Duration>,
+) -> Result<(), String> {
+    if paths.is_empty() {
+        let sparse = run_git(
+            repo_path,
+            timeout,
+            &["config", "--bool", "core.sparseCheckout"],
+        )
         .await
-        .map_err(|e| ManifestError::FailedToCheckoutRepository(e.to_string()))?;
-
-    if !out.status.success() {
-        return Err(ManifestError::FailedToCheckoutRepository(
-            "git config remote.origin.promisor failed".to_string(),
-        ));
+        .is_ok_and(|value| value == "true");
+        if sparse {
+            run_git(repo_path, timeout, &["sparse-checkout", "disable"]).await?;
+        }
+        return Ok(());
     }
 
-    let out = Command::new("git")
-        .current_dir(repo_path)
-        .args(["config", "remote.origin.partialclonefilter", "blob:none"])
-        .output()
-        .await
-        .map_err(|e| ManifestError::FailedToCheckoutRepository(e.to_string()))?;
+    let mut args = vec!["sparse-checkout", "set", "--cone"];
+    args.extend(paths.iter().map(|path| path.as_str()));
+    run_git(repo_path, timeout, &args).await?;
+    Ok(())
+}
 
-    if !out.status.success() {
-        return Err(ManifestError::FailedToCheckoutRepository(
-            "git config remote.origin.partialclonefilter failed".to_string(),
-        ));
+/// Make origin a promisor remote: the objects excluded by `filter` are downloaded when needed.
+async fn set_partial_clone_filter(
+    repo_path: &Path,
+    filter: &str,
+    timeout: Option<Duration>,
+) -> Result<(), ManifestError> {
+    for (key, value) in [
+        ("remote.origin.promisor", "true"),
+        ("remote.origin.partialclonefilter", filter),
+    ] {
+        run_git(repo_path, timeout, &["config", key, value])
+            .await
+            .map_err(|e| {
+                let msg = format!("git config {key} failed: {e}");
+                ManifestError::FailedToCheckoutRepository(msg)
+            })?;
     }
-
     Ok(())
 }
+
+/// Filter of the fetches from origin, if it is a promisor remote.
+async fn get_partial_clone_filter(repo_path: &Path, timeout: Option<Duration>) -> Option<String> {
+    let promisor = run_git(
+        repo_path,
+        timeout,
+        &["config", "--bool", "remote.origin.promisor"],
+    )
+    .await
+    .is_ok_and(|value| value == "true");
+    if !promisor {
+        return None;
+    }
+    let filter = run_git(
+        repo_path,
+        timeout,
+        &["config", "remote.origin.partialclonefilter"],
+    )
+    .await;
+    Some(filter.unwrap_or_default())
+}
+
+/// Stop filtering the fetches from origin.
+async fn unset_partial_clone_filter(repo_path: &Path, timeout: Option<Duration>) {
+    for key in ["remote.origin.promisor", "remote.origin.partialclonefilter"] {
+        // Exit code 5 when the key is not set
+        let _ = run_git(repo_path, timeout, &["config", "--unset-all", key]).await;
+    }
+}
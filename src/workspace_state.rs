@@ -0,0 +1,213 @@
+use crate::application::{ManifestError, STATE_DIR};
+use crate::manifest_diff::normalize_path;
+use crate::project::{Project, ProjectAction, ProjectFileAction, Vcs};
+use crate::topic::Topics;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const STATE_FILE: &str = "state.json";
+
+/// State of a project after its last synchronization.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectState {
+    pub uri: String,
+    pub name: String,
+    /// Revision requested by the manifest
+    pub revision: String,
+    pub path: String,
+    /// Version control system, git in the states recorded before Mercurial was supported
+    #[serde(default)]
+    pub vcs: Vcs,
+    /// Commit checked out by the synchronization
+    pub commit_id: Option<String>,
+    /// Seconds since the Unix epoch
+    pub synced_at: u64,
+    /// Files and directories created by the project actions, relative to the manifest directory
+    pub outputs: Vec<String>,
+}
+
+impl ProjectState {
+    pub fn new(project: &Project, commit_id: Option<String>) -> Self {
+        Self {
+            uri: project.get_uri().clone(),
+            name: project.get_name().clone(),
+            revision: project.get_revision().clone(),
+            path: normalize_path(project.get_path()),
+            vcs: project.get_vcs(),
+            commit_id,
+            synced_at: now(),
+            outputs: get_action_outputs(project),
+        }
+    }
+
+    /// Project as described by the manifest it was synced from, without its actions.
+    pub fn to_project(&self) -> Project {
+        let mut project = Project::new(
+            self.uri.clone(),
+            self.name.clone(),
+            self.revision.clone(),
+            self.path.clone(),
+        );
+        project.set_vcs(self.vcs);
+        project
+    }
+}
+
+/// What Colligo did in the workspace, stored in the `.colligo/` directory next to the manifest.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct WorkspaceState {
+    /// Content of the manifest used by the last synchronization
+    #[serde(default)]
+    manifest: Option<String>,
+    /// Seconds since the Unix epoch
+    #[serde(default)]
+    synced_at: Option<u64>,
+    /// Projects synchronized in the workspace, including those since removed from the manifest
+    #[serde(default)]
+    projects: Vec<ProjectState>,
+    /// Paths of the projects whose last synchronization failed or did not finish
+    #[serde(default)]
+    failed: Vec<String>,
+    #[serde(default)]
+    topics: Topics,
+}
+
+impl WorkspaceState {
+    /// Load the state of the workspace. An empty state is returned if nothing was recorded.
+    pub fn load(manifest_dir: &Path) -> Result<Self, ManifestError> {
+        let filename = get_state_file(manifest_dir);
+        if !filename.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&filename).map_err(|e| {
+            let msg = format!("Failed to read {}: {e}", filename.display());
+            ManifestError::FailedToAccessState(msg)
+        })?;
+        serde_json::from_str(&content).map_err(|e| {
+            let msg = format!("Failed to parse {}: {e}", filename.display());
+            ManifestError::FailedToAccessState(msg)
+        })
+    }
+
+    pub fn save(&self, manifest_dir: &Path) -> Result<(), ManifestError> {
+        let filename = get_state_file(manifest_dir);
+        let parent = filename.parent().unwrap_or("./".as_ref());
+        fs::create_dir_all(parent).map_err(|e| {
+            let msg = format!("Failed to create {}: {e}", parent.display());
+            ManifestError::FailedToAccessState(msg)
+        })?;
+
+        let content =
+            serde_json::to_string_pretty(self).expect("Failed to serialize workspace state");
+        fs::write(&filename, content).map_err(|e| {
+            let msg = format!("Failed to write {}: {e}", filename.display());
+            ManifestError::FailedToAccessState(msg)
+        })
+    }
+
+    /// Record a synchronization of `manifest`. The synced projects are added or updated, and no
+    /// longer failed. The `failed` paths are added to the failed projects.
+    pub fn record_sync(
+        &mut self,
+        manifest: &str,
+        projects: Vec<ProjectState>,
+        failed: Vec<String>,
+    ) {
+        self.manifest = Some(manifest.to_string());
+        self.synced_at = Some(now());
+
+        self.failed
+            .retain(|path| !projects.iter().any(|p| &p.path == path));
+        for path in failed {
+            let path = normalize_path(&path);
+            if !self.failed.contains(&path) {
+                self.failed.push(path);
+            }
+        }
+
+        for project in projects {
+            match self.projects.iter_mut().find(|p| p.path == project.path) {
+                Some(existing) => *existing = project,
+                None => self.projects.push(project),
+            }
+        }
+    }
+
+    /// Content of the manifest used by the last synchronization.
+    pub fn get_manifest(&self) -> Option<&String> {
+        self.manifest.as_ref()
+    }
+
+    pub fn get_synced_at(&self) -> Option<u64> {
+        self.synced_at
+    }
+
+    pub fn get_projects(&self) -> &Vec<ProjectState> {
+        &self.projects
+    }
+
+    pub fn get_project(&self, path: &str) -> Option<&ProjectState> {
+        let path = normalize_path(path);
+        self.projects.iter().find(|p| p.path == path)
+    }
+
+    pub fn remove_project(&mut self, path: &str) {
+        let path = normalize_path(path);
+        self.projects.retain(|p| p.path != path);
+        self.failed.retain(|p| p != &path);
+    }
+
+    /// Paths of the projects whose last synchronization failed or did not finish.
+    pub fn get_failed(&self) -> &Vec<String> {
+        &self.failed
+    }
+
+    /// Synced projects whose path is no longer in the manifest.
+    pub fn get_stale_projects(&self, manifest: &[Project]) -> Vec<&ProjectState> {
+        self.projects
+            .iter()
+            .filter(|synced| {
+                !manifest
+                    .iter()
+                    .any(|p| normalize_path(p.get_path()) == synced.path)
+            })
+            .collect()
+    }
+
+    pub fn get_topics(&self) -> &Topics {
+        &self.topics
+    }
+
+    pub fn set_topics(&mut self, topics: Topics) {
+        self.topics = topics;
+    }
+}
+
+fn get_action_outputs(project: &Project) -> Vec<String> {
+    project
+        .get_actions()
+        .iter()
+        .filter_map(|action| match action {
+            ProjectAction::FileAction(ProjectFileAction::LinkFile(_, dest))
+            | ProjectAction::FileAction(ProjectFileAction::CopyFile(_, dest))
+            | ProjectAction::FileAction(ProjectFileAction::CopyDir(_, dest)) => {
+                Some(normalize_path(dest))
+            }
+            ProjectAction::DeleteProject => None,
+        })
+        .collect()
+}
+
+fn get_state_file(manifest_dir: &Path) -> PathBuf {
+    manifest_dir.join(STATE_DIR).join(STATE_FILE)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
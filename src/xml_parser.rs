@@ -1,5 +1,7 @@
 use crate::application::{ManifestError, ManifestParser};
-use crate::project::{Project, ProjectAction, ProjectFileAction, DEFAULT_HOST, DEFAULT_REVISION};
+use crate::project::{
+    Archive, Project, ProjectAction, ProjectFileAction, Vcs, DEFAULT_HOST, DEFAULT_REVISION,
+};
 use log::warn;
 use roxmltree::{Document, Node};
 
@@ -12,6 +14,8 @@ pub struct XmlParser {}
 struct DefaultParameters {
     revision: String,
     uri: String,
+    push_uri: Option<String>,
+    vcs: Option<String>,
 }
 
 impl DefaultParameters {
@@ -30,7 +34,22 @@ impl DefaultParameters {
             .unwrap_or(DEFAULT_HOST)
             .to_string();
 
-        Self { revision, uri }
+        let push_uri = default
+            .as_ref()
+            .and_then(|n| n.attribute("pushuri"))
+            .map(|uri| uri.to_string());
+
+        let vcs = default
+            .as_ref()
+            .and_then(|n| n.attribute("vcs"))
+            .map(|vcs| vcs.to_string());
+
+        Self {
+            revision,
+            uri,
+            push_uri,
+            vcs,
+        }
     }
 }
 
@@ -53,17 +72,34 @@ impl ManifestParser for XmlParser {
         let parsed_xml = parse_xml_file(file)?;
         let default = DefaultParameters::new(&parsed_xml);
 
-        // Parse projects
+        // Parse projects and archives, in the manifest order
         for project in parsed_xml
             .root()
             .descendants()
-            .filter(|n| n.has_tag_name("project"))
+            .filter(|n| n.has_tag_name("project") || n.has_tag_name("archive"))
         {
+            if project.has_tag_name("archive") {
+                let mut instance = get_archive(&project)?;
+                add_actions(&mut instance, &project)?;
+                projects.push(instance);
+                continue;
+            }
+
             let name = get_name(&project)?;
             let path = get_path(&project)?;
             let revision = get_revision(&project, &default);
             let uri = get_uri(&project, &default);
             let mut instance = Project::new(uri, name, revision, path);
+            if let Some(push_uri) = get_push_uri(&project, &default) {
+                instance.set_push_uri(push_uri);
+            }
+            instance.set_vcs(get_vcs(&project, &default)?);
+            if let Some(clone_depth) = get_clone_depth(&project)? {
+                instance.set_clone_depth(clone_depth);
+            }
+            if let Some(filter) = get_filter(&project)? {
+                instance.set_filter(filter);
+            }
 
             add_actions(&mut instance, &project)?;
             projects.push(instance);
@@ -135,6 +171,84 @@ fn get_uri(node: &Node, default: &DefaultParameters) -> String {
         .to_string()
 }
 
+fn get_push_uri(node: &Node, default: &DefaultParameters) -> Option<String> {
+    node.attribute("pushuri")
+        .or(default.push_uri.as_deref())
+        .map(|uri| uri.trim().to_string())
+}
+
+fn get_vcs(node: &Node, default: &DefaultParameters) -> Result<Vcs, ManifestError> {
+    match node.attribute("vcs").or(default.vcs.as_deref()) {
+        Some(value) => Vcs::from_attribute(value.trim()).ok_or_else(|| {
+            let msg = format!("<project vcs=\"{value}\"/> is not supported, expected git or hg");
+            ManifestError::FailedToParseManifest(msg)
+        }),
+        None => Ok(Vcs::default()),
+    }
+}
+
+fn get_clone_depth(node: &Node) -> Result<Option<u32>, ManifestError> {
+    match node.attribute("clone-depth") {
+        Some(value) => match value.trim().parse() {
+            Ok(depth) if depth > 0 => Ok(Some(depth)),
+            _ => {
+                let msg = format!("<project clone-depth=\"{value}\"/> is not a positive number");
+                Err(ManifestError::FailedToParseManifest(msg))
+            }
+        },
+        None => Ok(None),
+    }
+}
+
+fn get_filter(node: &Node) -> Result<Option<String>, ManifestError> {
+    const FILTERS: [&str; 5] = ["blob:", "tree:", "object:", "sparse:", "combine:"];
+
+    match node.attribute("filter").map(|filter| filter.trim()) {
+        Some(filter) if FILTERS.iter().any(|kind| filter.starts_with(kind)) => {
+            Ok(Some(filter.to_string()))
+        }
+        Some(filter) => {
+            let msg = format!("<project filter=\"{filter}\"/> is not a git object filter");
+            Err(ManifestError::FailedToParseManifest(msg))
+        }
+        None => Ok(None),
+    }
+}
+
+fn get_archive(node: &Node) -> Result<Project, ManifestError> {
+    let attribute = |name: &str| match node.attribute(name) {
+        Some(value) => Ok(value.trim().to_string()),
+        None => {
+            let msg = format!("<archive --> {name}= <-- /> is missing");
+            Err(ManifestError::FailedToParseManifest(msg))
+        }
+    };
+
+    let url = attribute("url")?;
+    let sha256 = attribute("sha256")?.to_lowercase();
+    let path = attribute("path")?;
+
+    if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        let msg = format!("<archive sha256=\"{sha256}\"/> is not a SHA-256 hash");
+        return Err(ManifestError::FailedToParseManifest(msg));
+    }
+
+    let strip_components = match node.attribute("strip-components") {
+        Some(value) => value.trim().parse().map_err(|_| {
+            let msg = format!("<archive strip-components=\"{value}\"/> is not a number");
+            ManifestError::FailedToParseManifest(msg)
+        })?,
+        None => 0,
+    };
+
+    let archive = Archive {
+        url,
+        sha256,
+        strip_components,
+    };
+    Ok(Project::new_archive(archive, path))
+}
+
 fn add_actions(instance: &mut Project, node: &Node) -> Result<(), ManifestError> {
     if let Some(child) = node.first_element_child() {
         for action in child.next_siblings().filter(|n| n.is_element()) {
@@ -149,6 +263,16 @@ fn add_actions(instance: &mut Project, node: &Node) -> Result<(), ManifestError>
                     let msg = "<[linkfile or copyfile] /> is missing src or dest".to_string();
                     return Err(ManifestError::FailedToParseManifest(msg));
                 }
+            } else if instance.is_sparse(&action_name) {
+                match action.attribute("path").map(|path| path.trim()) {
+                    Some(path) if !path.is_empty() => {
+                        instance.add_sparse_path(path.trim_matches('/').to_string())
+                    }
+                    _ => {
+                        let msg = "<sparse /> is missing path".to_string();
+                        return Err(ManifestError::FailedToParseManifest(msg));
+                    }
+                }
             } else if instance.is_delete_project(&action_name) {
                 instance.add_delete_project();
             } else {
@@ -161,25 +285,20 @@ fn add_actions(instance: &mut Project, node: &Node) -> Result<(), ManifestError>
 }
 
 fn project_to_xml(project: &Project) -> String {
-    const PROJECT_END: &str = "    </project>\n";
+    let (tag, attributes) = match project.get_archive() {
+        Some(archive) => ("archive", archive_attributes(archive, project.get_path())),
+        None => ("project", project_attributes(project)),
+    };
 
     let mut xml: String;
-    if project.get_actions().is_empty() {
-        xml = format!(
-            "    <project uri=\"{uri}\" name=\"{name}\" path=\"{path}\" revision=\"{revision}\"/>\n",
-            uri = project.get_uri(),
-            name = project.get_name(),
-            path = project.get_path(),
-            revision = project.get_revision(),
-        )
+    if project.get_actions().is_empty() && project.get_sparse_paths().is_empty() {
+        xml = format!("    <{tag} {attributes}/>\n")
     } else {
-        xml = format!(
-            "    <project uri=\"{uri}\" name=\"{name}\" path=\"{path}\" revision=\"{revision}\">\n",
-            uri = project.get_uri(),
-            name = project.get_name(),
-            path = project.get_path(),
-            revision = project.get_revision(),
-        );
+        xml = format!("    <{tag} {attributes}>\n");
+
+        for path in project.get_sparse_paths() {
+            xml.push_str(&format!("        <sparse path=\"{path}\"/>\n"));
+        }
 
         for action in project.get_actions() {
             let action_xml = match action {
@@ -196,7 +315,45 @@ fn project_to_xml(project: &Project) -> String {
             };
             xml.push_str(&action_xml);
         }
-        xml.push_str(PROJECT_END);
+        xml.push_str(&format!("    </{tag}>\n"));
     }
     xml
 }
+
+fn project_attributes(project: &Project) -> String {
+    let mut attributes = format!(
+        "uri=\"{uri}\" name=\"{name}\" path=\"{path}\" revision=\"{revision}\"",
+        uri = project.get_uri(),
+        name = project.get_name(),
+        path = project.get_path(),
+        revision = project.get_revision(),
+    );
+    if let Some(push_uri) = project.get_push_uri() {
+        attributes.push_str(&format!(" pushuri=\"{push_uri}\""));
+    }
+    if project.get_vcs() != Vcs::default() {
+        attributes.push_str(&format!(" vcs=\"{}\"", project.get_vcs()));
+    }
+    if let Some(clone_depth) = project.get_clone_depth() {
+        attributes.push_str(&format!(" clone-depth=\"{clone_depth}\""));
+    }
+    if let Some(filter) = project.get_filter() {
+        attributes.push_str(&format!(" filter=\"{filter}\""));
+    }
+    attributes
+}
+
+fn archive_attributes(archive: &Archive, path: &str) -> String {
+    let mut attributes = format!(
+        "url=\"{url}\" sha256=\"{sha256}\" path=\"{path}\"",
+        url = archive.url,
+        sha256 = archive.sha256,
+    );
+    if archive.strip_components > 0 {
+        attributes.push_str(&format!(
+            " strip-components=\"{}\"",
+            archive.strip_components
+        ));
+    }
+    attributes
+}
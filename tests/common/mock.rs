@@ -0,0 +1,133 @@
+use colligo::application::{DwlMode, ManifestError, XVersionControl};
+use colligo::project::Project;
+use colligo::version_control::FetchStats;
+use indicatif::ProgressBar;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Projects checked out in memory, at the commit of their revision on the remote.
+#[derive(Default)]
+pub struct MockVersionControl {
+    pub remote: HashMap<String, String>,
+    pub checked_out: Mutex<HashMap<String, String>>,
+    pub failing: Vec<String>,
+    pub modified: Vec<String>,
+    /// Duration of each fetch and update, for those of the projects to overlap
+    pub delay: Duration,
+    calls: Mutex<Vec<String>>,
+    /// Fetches and updates in progress, and their maximum, by operation
+    in_flight: Mutex<HashMap<&'static str, (usize, usize)>>,
+}
+
+impl MockVersionControl {
+    /// Mock whose remote has the `commits` of each project path.
+    pub fn new(commits: &[(&str, &str)]) -> Self {
+        let mut vcs = Self::default();
+        for (path, commit) in commits {
+            vcs.remote.insert(path.to_string(), commit.to_string());
+        }
+        vcs
+    }
+
+    fn call(&self, name: &str, project: &Project) {
+        let call = format!("{name} {}", project.get_path());
+        self.calls.lock().unwrap().push(call);
+    }
+
+    pub fn get_calls(&self, path: &str) -> Vec<String> {
+        let calls = self.calls.lock().unwrap();
+        calls
+            .iter()
+            .filter_map(|call| call.strip_suffix(&format!(" {path}")))
+            .map(String::from)
+            .collect()
+    }
+
+    /// Maximum number of `name` operations run at the same time.
+    pub fn get_peak(&self, name: &str) -> usize {
+        let in_flight = self.in_flight.lock().unwrap();
+        in_flight.get(name).map(|(_, peak)| *peak).unwrap_or(0)
+    }
+
+    async fn run(&self, name: &'static str) {
+        {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            let (current, peak) = in_flight.entry(name).or_default();
+            *current += 1;
+            *peak = (*peak).max(*current);
+        }
+        tokio::time::sleep(self.delay).await;
+        let mut in_flight = self.in_flight.lock().unwrap();
+        in_flight.entry(name).or_default().0 -= 1;
+    }
+}
+
+#[async_trait::async_trait]
+impl XVersionControl for MockVersionControl {
+    async fn init(
+        &self,
+        _manifest_dir: &Path,
+        project: &Project,
+        _mode: &DwlMode,
+    ) -> Result<(), ManifestError> {
+        self.call("init", project);
+        Ok(())
+    }
+
+    async fn fetch(
+        &self,
+        _manifest_dir: &Path,
+        project: &Project,
+        _pb: Option<&ProgressBar>,
+        _lightweight: bool,
+    ) -> Result<FetchStats, ManifestError> {
+        self.call("fetch", project);
+        self.run("fetch").await;
+        if self.failing.contains(project.get_path()) {
+            let msg = format!("{}\nfatal: the remote end hung up\n", project.get_path());
+            return Err(ManifestError::FailedToCheckoutRepository(msg));
+        }
+        Ok(FetchStats {
+            attempts: 1,
+            bytes: Some(1024),
+        })
+    }
+
+    async fn update(
+        &self,
+        _manifest_dir: &Path,
+        project: &Project,
+        _pb: Option<&ProgressBar>,
+        _force: bool,
+        _lightweight: bool,
+    ) -> Result<(), ManifestError> {
+        self.call("update", project);
+        self.run("update").await;
+        let commit = self.remote[project.get_path()].clone();
+        let mut checked_out = self.checked_out.lock().unwrap();
+        checked_out.insert(project.get_path().clone(), commit);
+        Ok(())
+    }
+
+    async fn get_commit_id(
+        &self,
+        _manifest_dir: &Path,
+        project: &Project,
+    ) -> Result<String, ManifestError> {
+        let checked_out = self.checked_out.lock().unwrap();
+        checked_out
+            .get(project.get_path())
+            .cloned()
+            .ok_or_else(|| ManifestError::FailedToGetCommitId(project.get_path().clone()))
+    }
+
+    async fn is_modified(
+        &self,
+        _manifest_dir: &Path,
+        project: &Project,
+    ) -> Result<bool, ManifestError> {
+        Ok(self.modified.contains(project.get_path()))
+    }
+}
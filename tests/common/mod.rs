@@ -0,0 +1,111 @@
+//! Helpers shared by the integration tests, each test file using some of them.
+#![allow(dead_code)]
+
+pub mod mock;
+
+use colligo::application::ManifestInstance;
+use std::path::{Path, PathBuf};
+
+/// Run `git` in `dir` with the test identity and return its trimmed stdout.
+pub fn git(dir: &Path, args: &[&str]) -> String {
+    let output = std::process::Command::new("git")
+        .current_dir(dir)
+        .args([
+            "-c",
+            "user.name=Colligo",
+            "-c",
+            "user.email=colligo@test.com",
+        ])
+        .args(args)
+        .output()
+        .expect("Failed to execute git");
+    assert!(output.status.success(), "git {:?} failed", args);
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// Commit `README.md` with `content` in `repo` and return the commit id.
+pub fn commit(repo: &Path, content: &str) -> String {
+    std::fs::write(repo.join("README.md"), content).expect("Failed to write file");
+    git(repo, &["add", "README.md"]);
+    git(repo, &["commit", "--quiet", "-m", content]);
+    git(repo, &["rev-parse", "HEAD"])
+}
+
+/// Create a repository on the `main` branch with a commit of `README.md` for each of the
+/// `contents`. The test identity is configured, for the commits and tags created by Colligo.
+pub fn create_repository(repo: &Path, contents: &[&str]) {
+    std::fs::create_dir_all(repo).expect("Failed to create repository directory");
+    git(repo, &["init", "--quiet", "--initial-branch", "main"]);
+    git(repo, &["config", "user.name", "Colligo"]);
+    git(repo, &["config", "user.email", "colligo@test.com"]);
+    for content in contents {
+        commit(repo, content);
+    }
+}
+
+/// Write the manifest `content` in `dir` and parse it.
+pub fn load_manifest(dir: &Path, content: &str) -> ManifestInstance {
+    std::fs::create_dir_all(dir).expect("Failed to create manifest directory");
+    let manifest = dir.join("manifest.xml");
+    std::fs::write(&manifest, content).expect("Failed to write manifest");
+    let mut manifest =
+        ManifestInstance::try_from(manifest).expect("Failed to create manifest instance");
+    manifest.parse().expect("Unable to parse manifest");
+    manifest
+}
+
+/// Create a repository with one commit for each of the `projects` in `path`, and return the
+/// parsed `manifest` of this workspace.
+pub fn create_workspace(path: &Path, projects: &[&str], manifest: &str) -> ManifestInstance {
+    for project in projects {
+        create_repository(&path.join(project), &[project]);
+    }
+    load_manifest(path, manifest)
+}
+
+/// Bare clone of the repository `work` in `<path>/remote/<name>.git`, served as
+/// `https://example.com/<name>.git` through `url.insteadOf`. Return the bare repository.
+pub fn create_remote(path: &Path, name: &str, work: &Path) -> PathBuf {
+    let remote = path.join("remote");
+    let bare = remote.join(format!("{name}.git"));
+    std::fs::create_dir_all(bare.parent().unwrap()).expect("Failed to create remote directory");
+    git(
+        path,
+        &[
+            "clone",
+            "--quiet",
+            "--bare",
+            work.to_str().unwrap(),
+            bare.to_str().unwrap(),
+        ],
+    );
+
+    // Process-wide: the test files setting it hold a single test doing so
+    std::env::set_var("GIT_CONFIG_COUNT", "1");
+    std::env::set_var(
+        "GIT_CONFIG_KEY_0",
+        format!("url.file://{}/.insteadOf", remote.display()),
+    );
+    std::env::set_var("GIT_CONFIG_VALUE_0", "https://example.com/");
+    bare
+}
+
+/// Manifest of the git project `app`, the archive `pkg` and the Mercurial project `legacy`.
+pub const MIXED_MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <project uri="github.com" name="team/app" path="app" revision="main"/>
+    <archive url="https://example.com/pkg-1.0.tar.gz" sha256="e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855" path="pkg"/>
+    <project uri="example.com" name="team/legacy" path="legacy" revision="default" vcs="hg"/>
+</manifest>
+"#;
+
+/// Create the workspace of `MIXED_MANIFEST`, in which the archive and the Mercurial project are
+/// directories holding a modified `README.md` but no git repository.
+pub fn create_mixed_workspace(path: &Path) -> ManifestInstance {
+    for project in ["pkg", "legacy"] {
+        let dir = path.join(project);
+        std::fs::create_dir_all(&dir).expect("Failed to create project directory");
+        std::fs::write(dir.join("README.md"), project).expect("Failed to write file");
+    }
+    create_workspace(path, &["app"], MIXED_MANIFEST)
+}
@@ -0,0 +1,279 @@
+mod common;
+
+#[cfg(test)]
+mod test_archive {
+
+    use crate::common::load_manifest;
+    use colligo::application::{DwlMode, ManifestError, ManifestInstance, ManifestParser};
+    use colligo::archive_version_control::{hash_file, unpack, ARCHIVE_MARKER};
+    use colligo::sync_report::SyncAction;
+    use colligo::xml_parser::XmlParser;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+
+    const SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    const FILES: [(&str, &str); 2] = [
+        ("pkg-1.0/README.md", "Hello"),
+        ("pkg-1.0/src/lib.rs", "pub fn hello() {}"),
+    ];
+
+    fn create_tar<W: Write>(writer: W) -> W {
+        let mut builder = tar::Builder::new(writer);
+        for (path, content) in FILES {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .expect("Failed to append file");
+        }
+        builder.into_inner().expect("Failed to write tar")
+    }
+
+    fn create_tar_gz(path: &Path) -> PathBuf {
+        let file = path.join("pkg-1.0.tar.gz");
+        let encoder = GzEncoder::new(File::create(&file).unwrap(), Compression::default());
+        create_tar(encoder)
+            .finish()
+            .expect("Failed to write tar.gz");
+        file
+    }
+
+    fn create_manifest(path: &Path, archive: &Path, sha256: &str) -> ManifestInstance {
+        let xml = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <archive url="file://{}" sha256="{sha256}" path="third_party/pkg" strip-components="1">
+        <copyfile src="README.md" dest="README.pkg"/>
+    </archive>
+</manifest>
+"#,
+            archive.display()
+        );
+        load_manifest(path, &xml)
+    }
+
+    #[test]
+    fn parse_archive() {
+        let manifest = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <project uri="github.com" name="team/app" path="app" revision="main"/>
+    <archive url="https://example.com/pkg-1.0.tar.gz" sha256="{SHA256}" path="pkg" strip-components="1">
+        <linkfile src="README.md" dest="README.pkg"/>
+    </archive>
+    <archive url="https://example.com/tool.zip" sha256="{SHA256}" path="tool"/>
+</manifest>
+"#
+        );
+
+        let parser = XmlParser::new();
+        let projects = parser.parse(&manifest).expect("Unable to parse XML");
+
+        assert_eq!(projects.len(), 3);
+        assert!(projects[0].get_archive().is_none());
+        let archive = projects[1].get_archive().expect("Archive expected");
+        assert_eq!(archive.url, "https://example.com/pkg-1.0.tar.gz");
+        assert_eq!(archive.sha256, SHA256);
+        assert_eq!(archive.strip_components, 1);
+        assert_eq!(projects[1].get_path(), "pkg");
+        assert_eq!(projects[1].get_actions().len(), 1);
+        assert_eq!(projects[2].get_archive().unwrap().strip_components, 0);
+
+        let composed = parser.compose(&projects).expect("Unable to compose XML");
+        assert_eq!(composed, manifest);
+    }
+
+    #[test]
+    fn invalid_archive() {
+        let parser = XmlParser::new();
+        for archive in [
+            r#"<archive url="https://example.com/a.zip" sha256="1234" path="a"/>"#.to_string(),
+            r#"<archive url="https://example.com/a.zip" path="a"/>"#.to_string(),
+            format!(r#"<archive url="https://example.com/a.zip" sha256="{SHA256}"/>"#),
+            format!(
+                r#"<archive url="https://example.com/a.zip" sha256="{SHA256}" path="a" strip-components="-1"/>"#
+            ),
+        ] {
+            let manifest = format!("<manifest>{archive}</manifest>");
+            let result = parser.parse(&manifest);
+            assert!(
+                matches!(result, Err(ManifestError::FailedToParseManifest(_))),
+                "{archive}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn sync_archive() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let archive = create_tar_gz(temp_dir.path());
+        let sha256 = hash_file(&archive).unwrap();
+        let manifest = create_manifest(temp_dir.path(), &archive, &sha256);
+        let pkg = temp_dir.path().join("third_party/pkg");
+
+        let report = manifest
+            .sync_report(&DwlMode::HTTPS, false, true, false)
+            .await;
+        assert!(report.is_success(), "{:?}", report.projects[0].error);
+        assert_eq!(report.projects[0].action, SyncAction::Cloned);
+        assert_eq!(report.projects[0].new_commit.as_ref(), Some(&sha256));
+        assert_eq!(fs::read_to_string(pkg.join("README.md")).unwrap(), "Hello");
+        assert!(pkg.join("src/lib.rs").exists());
+        assert_eq!(
+            fs::read_to_string(pkg.join(ARCHIVE_MARKER)).unwrap(),
+            sha256
+        );
+        assert!(temp_dir.path().join("README.pkg").exists());
+
+        // The archive is neither downloaded nor unpacked again
+        fs::write(pkg.join("README.md"), "Modified").unwrap();
+        let report = manifest
+            .sync_report(&DwlMode::HTTPS, false, true, false)
+            .await;
+        assert!(report.is_success());
+        assert_eq!(report.projects[0].action, SyncAction::UpToDate);
+        assert_eq!(report.projects[0].attempts, 0);
+        assert_eq!(
+            fs::read_to_string(pkg.join("README.md")).unwrap(),
+            "Modified"
+        );
+
+        // Unless forced, from the downloaded archive
+        fs::remove_file(&archive).unwrap();
+        let report = manifest
+            .sync_report(&DwlMode::HTTPS, false, true, true)
+            .await;
+        assert!(report.is_success(), "{:?}", report.projects[0].error);
+        assert_eq!(fs::read_to_string(pkg.join("README.md")).unwrap(), "Hello");
+
+        let pinned = manifest.pin().await.expect("Failed to pin");
+        assert_eq!(
+            pinned.get_projects()[0].get_archive().unwrap().sha256,
+            sha256
+        );
+    }
+
+    #[tokio::test]
+    async fn checksum_mismatch() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let archive = create_tar_gz(temp_dir.path());
+        let manifest = create_manifest(temp_dir.path(), &archive, SHA256);
+
+        let report = manifest
+            .sync_report(&DwlMode::HTTPS, false, true, false)
+            .await;
+
+        let error = report.projects[0].error.as_ref().expect("Error expected");
+        assert!(matches!(error, ManifestError::FailedToDownload(_)));
+        assert!(error.to_string().contains("Checksum mismatch"));
+        assert!(!temp_dir.path().join("third_party/pkg/README.md").exists());
+        let archives = temp_dir.path().join(".colligo/archives");
+        assert_eq!(fs::read_dir(archives).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn refuse_to_replace_directory() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let archive = create_tar_gz(temp_dir.path());
+        let sha256 = hash_file(&archive).unwrap();
+        let manifest = create_manifest(temp_dir.path(), &archive, &sha256);
+        let pkg = temp_dir.path().join("third_party/pkg");
+        fs::create_dir_all(&pkg).unwrap();
+        fs::write(pkg.join("notes.txt"), "Mine").unwrap();
+
+        let report = manifest
+            .sync_report(&DwlMode::HTTPS, false, true, false)
+            .await;
+
+        let error = report.projects[0].error.as_ref().expect("Error expected");
+        assert!(matches!(error, ManifestError::FailedToUnpack(_)));
+        assert!(pkg.join("notes.txt").exists());
+    }
+
+    #[test]
+    fn unpack_formats() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+
+        let tar_xz = temp_dir.path().join("pkg.tar.xz");
+        let encoder = xz2::write::XzEncoder::new(File::create(&tar_xz).unwrap(), 6);
+        create_tar(encoder).finish().unwrap();
+
+        let zip = temp_dir.path().join("pkg.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&zip).unwrap());
+        for (path, content) in FILES {
+            let options = zip::write::SimpleFileOptions::default();
+            writer.start_file(path, options).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        for (file, strip_components, readme) in [
+            (&tar_xz, 1, "README.md"),
+            (&zip, 1, "README.md"),
+            (&zip, 0, "pkg-1.0/README.md"),
+        ] {
+            let dest = tempfile::TempDir::new().unwrap();
+            unpack(file, dest.path(), strip_components).expect("Failed to unpack");
+            let content = fs::read_to_string(dest.path().join(readme)).unwrap();
+            assert_eq!(content, "Hello");
+        }
+
+        let text = temp_dir.path().join("pkg.txt");
+        fs::write(&text, "Not an archive").unwrap();
+        assert!(unpack(&text, temp_dir.path(), 0).is_err());
+    }
+
+    #[test]
+    fn unpack_refuses_unsafe_path() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let zip = temp_dir.path().join("evil.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&zip).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        writer.start_file("../evil.txt", options).unwrap();
+        writer.write_all(b"evil").unwrap();
+        writer.finish().unwrap();
+
+        let dest = temp_dir.path().join("dest");
+        fs::create_dir(&dest).unwrap();
+        assert!(unpack(&zip, &dest, 0).is_err());
+        assert!(!temp_dir.path().join("evil.txt").exists());
+    }
+
+    #[test]
+    fn unpack_refuses_symlink_escape() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let outside = temp_dir.path().join("outside");
+        fs::create_dir(&outside).unwrap();
+
+        // A symbolic link to a directory outside of the destination, then a file written through it
+        let tar_gz = temp_dir.path().join("evil.tar.gz");
+        let encoder = GzEncoder::new(File::create(&tar_gz).unwrap(), Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_mode(0o777);
+        builder
+            .append_link(&mut header, "link", &outside)
+            .expect("Failed to append link");
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "link/evil.txt", &b"evil"[..])
+            .expect("Failed to append file");
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let dest = temp_dir.path().join("dest");
+        fs::create_dir(&dest).unwrap();
+        assert!(unpack(&tar_gz, &dest, 0).is_err());
+        assert!(!outside.join("evil.txt").exists());
+    }
+}
@@ -0,0 +1,125 @@
+mod common;
+
+#[cfg(test)]
+mod test_cache {
+
+    use crate::common::{create_remote, create_repository, git, load_manifest};
+    use colligo::application::DwlMode;
+    use colligo::cache::{gc, Cache, CACHE_ENV};
+    use colligo::project::Project;
+    use colligo::sync_report::SyncAction;
+    use std::path::PathBuf;
+
+    // Both projects are clones of the same repository
+    const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <project uri="example.com" name="team/app" path="app" revision="main"/>
+    <project uri="example.com" name="team/app" path="app-copy" revision="main"/>
+</manifest>
+"#;
+
+    #[tokio::test]
+    async fn sync_borrows_objects_from_mirror() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let work = temp_dir.path().join("work");
+        create_repository(&work, &["app"]);
+        create_remote(temp_dir.path(), "team/app", &work);
+        let workspace = temp_dir.path().join("workspace");
+        let mut manifest = load_manifest(&workspace, MANIFEST);
+        let cache_dir = temp_dir.path().join("cache");
+        manifest.set_cache_dir(Some(cache_dir.clone()));
+
+        let report = manifest
+            .sync_report(&DwlMode::HTTPS, false, true, false)
+            .await;
+        assert!(report.is_success(), "{:?}", report.projects);
+        assert_eq!(report.count(SyncAction::Cloned), 2);
+
+        let mirror = cache_dir.join("example.com/team/app.git");
+        let head = git(&mirror, &["rev-parse", "main"]);
+        assert_eq!(git(&mirror, &["config", "gc.auto"]), "0");
+        for path in ["app", "app-copy"] {
+            let repo = workspace.join(path);
+            let alternates = std::fs::read_to_string(repo.join(".git/objects/info/alternates"))
+                .expect("Missing alternates");
+            assert_eq!(
+                alternates.trim(),
+                mirror.join("objects").display().to_string()
+            );
+            assert_eq!(git(&repo, &["rev-parse", "HEAD"]), head);
+            assert!(repo.join("README.md").exists());
+        }
+
+        // The alternates are not duplicated
+        let report = manifest
+            .sync_report(&DwlMode::HTTPS, false, true, false)
+            .await;
+        assert!(report.is_success(), "{:?}", report.projects);
+        let alternates =
+            std::fs::read_to_string(workspace.join("app/.git/objects/info/alternates")).unwrap();
+        assert_eq!(alternates.lines().count(), 1);
+
+        let mirrors = gc(&cache_dir).await.expect("Failed to clean cache");
+        assert_eq!(mirrors, vec![mirror]);
+        git(&workspace.join("app"), &["fsck", "--no-dangling"]);
+
+        // Without the cache, the projects share a mirror in the workspace
+        let workspace = temp_dir.path().join("workspace-without-cache");
+        let manifest = load_manifest(&workspace, MANIFEST);
+
+        let report = manifest
+            .sync_report(&DwlMode::HTTPS, false, true, false)
+            .await;
+        assert!(report.is_success(), "{:?}", report.projects);
+        assert_eq!(report.count(SyncAction::Cloned), 2);
+
+        let store = workspace.join(".colligo/objects/example.com/team/app.git");
+        assert_eq!(git(&store, &["rev-parse", "main"]), head);
+        assert_eq!(git(&store, &["config", "gc.auto"]), "0");
+        for path in ["app", "app-copy"] {
+            let repo = workspace.join(path);
+            let alternates = std::fs::read_to_string(repo.join(".git/objects/info/alternates"))
+                .expect("Missing alternates");
+            assert_eq!(
+                alternates.trim(),
+                "../../../.colligo/objects/example.com/team/app.git/objects"
+            );
+            assert_eq!(git(&repo, &["rev-parse", "HEAD"]), head);
+            git(&repo, &["fsck", "--no-dangling"]);
+        }
+
+        // The projects still find the objects of the store once the workspace is moved
+        let moved = temp_dir.path().join("workspace-moved");
+        std::fs::rename(&workspace, &moved).expect("Failed to move workspace");
+        for path in ["app", "app-copy"] {
+            let repo = moved.join(path);
+            assert_eq!(git(&repo, &["status", "--porcelain"]), "");
+            assert_eq!(git(&repo, &["log", "-1", "--format=%H"]), head);
+            git(&repo, &["fsck", "--no-dangling"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn mirror_path() {
+        std::env::set_var(CACHE_ENV, "/tmp/colligo-cache");
+        assert_eq!(
+            Cache::get_default_dir(),
+            Some(PathBuf::from("/tmp/colligo-cache"))
+        );
+
+        let cache = Cache::new(PathBuf::from("/tmp/colligo-cache"));
+        let project = Project::new(
+            "github.com".to_string(),
+            "chrisdsa/colligo".to_string(),
+            "main".to_string(),
+            "colligo".to_string(),
+        );
+        assert_eq!(
+            cache.get_mirror_path(&project),
+            PathBuf::from("/tmp/colligo-cache/github.com/chrisdsa/colligo.git")
+        );
+
+        let empty = tempfile::TempDir::new().expect("failed to create temp dir");
+        assert!(gc(&empty.path().join("missing")).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,116 @@
+mod common;
+
+#[cfg(test)]
+mod test_changelog {
+
+    use crate::common::{commit, create_repository};
+    use colligo::changelog::Changelog;
+    use colligo::project::{Archive, Project, Vcs};
+    use std::path::Path;
+
+    /// Create a repository with 3 commits and return their ids, oldest first.
+    fn create_history(path: &Path) -> Vec<String> {
+        create_repository(path, &[]);
+        ["First commit", "Second commit", "Third commit"]
+            .map(|message| commit(path, message))
+            .to_vec()
+    }
+
+    fn project(revision: &str) -> Project {
+        Project::new(
+            "github.com".to_string(),
+            "chrisdsa/colligo".to_string(),
+            revision.to_string(),
+            "./dev".to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn log_new_commits() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let commits = create_history(&temp_dir.path().join("dev"));
+
+        let old = [project(&commits[0])];
+        let new = [project(&commits[2])];
+        let changelog = Changelog::new(temp_dir.path(), &old, &new).await;
+
+        let projects = changelog.get_projects();
+        assert_eq!(projects.len(), 1);
+        assert_eq!(projects[0].error, None);
+        assert!(!projects[0].rewind);
+        assert_eq!(projects[0].commits.len(), 2);
+        assert_eq!(projects[0].commits[0].id, commits[2]);
+        assert_eq!(projects[0].commits[0].summary, "Third commit");
+        assert_eq!(projects[0].commits[1].summary, "Second commit");
+        assert!(projects[0].removed_commits.is_empty());
+
+        let markdown = changelog.to_markdown();
+        assert!(markdown.contains("## dev (github.com/chrisdsa/colligo)"));
+        assert!(markdown.contains(&format!("- {} Third commit (Colligo)", &commits[2][..7])));
+    }
+
+    #[tokio::test]
+    async fn log_flags_rewind() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let commits = create_history(&temp_dir.path().join("dev"));
+
+        let old = [project(&commits[2])];
+        let new = [project(&commits[0])];
+        let changelog = Changelog::new(temp_dir.path(), &old, &new).await;
+
+        let projects = changelog.get_projects();
+        assert!(projects[0].rewind);
+        assert!(projects[0].commits.is_empty());
+        assert_eq!(projects[0].removed_commits.len(), 2);
+        assert!(changelog
+            .to_markdown()
+            .contains("non-fast-forward change, 2 commit(s) removed"));
+    }
+
+    #[tokio::test]
+    async fn log_unchanged_and_missing_projects() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+
+        // Unchanged revision is not reported
+        let old = [project("main")];
+        let changelog = Changelog::new(temp_dir.path(), &old, &old).await;
+        assert!(changelog.get_projects().is_empty());
+
+        // Project not checked out is reported with an error
+        let new = [project("dev")];
+        let changelog = Changelog::new(temp_dir.path(), &old, &new).await;
+        assert!(changelog.get_projects()[0].error.is_some());
+    }
+
+    #[tokio::test]
+    async fn log_skips_archives_and_hg_projects() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+
+        let archive = |sha256: &str| {
+            let archive = Archive {
+                url: "https://example.com/pkg.tar.gz".to_string(),
+                sha256: sha256.to_string(),
+                strip_components: 0,
+            };
+            Project::new_archive(archive, "pkg".to_string())
+        };
+        let hg = |revision: &str| {
+            let mut project = Project::new(
+                "example.com".to_string(),
+                "team/legacy".to_string(),
+                revision.to_string(),
+                "legacy".to_string(),
+            );
+            project.set_vcs(Vcs::Hg);
+            project
+        };
+
+        let old = [project("main"), archive(&"a".repeat(64)), hg("1.0")];
+        let new = [project("dev"), archive(&"b".repeat(64)), hg("2.0")];
+        let changelog = Changelog::new(temp_dir.path(), &old, &new).await;
+
+        let projects = changelog.get_projects();
+        assert_eq!(projects.len(), 1);
+        assert_eq!(projects[0].path, "dev");
+    }
+}
@@ -0,0 +1,99 @@
+mod common;
+
+#[cfg(test)]
+mod test_commit {
+
+    use crate::common::{create_mixed_workspace, create_repository, create_workspace, git};
+    use colligo::application::commit_projects;
+
+    const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <project uri="github.com" name="team/app" path="app" revision="main"/>
+    <project uri="github.com" name="team/lib" path="lib" revision="main"/>
+    <project uri="github.com" name="team/doc" path="doc" revision="main"/>
+</manifest>
+"#;
+
+    const PROJECTS: [&str; 3] = ["app", "lib", "doc"];
+
+    #[tokio::test]
+    async fn commit_modified_projects_only() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let manifest = create_workspace(temp_dir.path(), &PROJECTS, MANIFEST);
+
+        // Modify tracked file in app and lib, add untracked file in doc
+        let app = temp_dir.path().join("app");
+        let lib = temp_dir.path().join("lib");
+        let doc = temp_dir.path().join("doc");
+        std::fs::write(app.join("README.md"), "New README").expect("Failed to write file");
+        std::fs::write(lib.join("README.md"), "New README").expect("Failed to write file");
+        std::fs::write(doc.join("untracked.md"), "Untracked").expect("Failed to write file");
+
+        let commits = commit_projects(&manifest, "Coordinated change", &[])
+            .await
+            .expect("Unable to commit");
+
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].path, "app");
+        assert_eq!(commits[0].commit_id, git(&app, &["rev-parse", "HEAD"]));
+        assert_eq!(commits[1].path, "lib");
+        assert_eq!(commits[1].commit_id, git(&lib, &["rev-parse", "HEAD"]));
+        assert_eq!(
+            git(&app, &["log", "-1", "--format=%s"]),
+            "Coordinated change"
+        );
+        assert_eq!(git(&doc, &["rev-list", "--count", "HEAD"]), "1");
+
+        // The commits are used by pin
+        let pinned = manifest.pin().await.expect("Unable to pin manifest");
+        assert_eq!(
+            pinned.get_projects()[0].get_revision(),
+            &commits[0].commit_id
+        );
+    }
+
+    #[tokio::test]
+    async fn commit_selected_projects() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let manifest = create_workspace(temp_dir.path(), &PROJECTS, MANIFEST);
+
+        let app = temp_dir.path().join("app");
+        let lib = temp_dir.path().join("lib");
+        std::fs::write(app.join("README.md"), "New README").expect("Failed to write file");
+        std::fs::write(lib.join("README.md"), "New README").expect("Failed to write file");
+
+        let commits = commit_projects(&manifest, "Only lib", &["lib".to_string()])
+            .await
+            .expect("Unable to commit");
+
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].path, "lib");
+        assert_eq!(git(&app, &["rev-list", "--count", "HEAD"]), "1");
+    }
+
+    #[tokio::test]
+    async fn commit_skips_archives_and_hg_projects() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let manifest = create_mixed_workspace(temp_dir.path());
+
+        // The workspace is in the repository of the manifest, which tracks the archive and the
+        // Mercurial project
+        let workspace = temp_dir.path();
+        create_repository(workspace, &[]);
+        git(workspace, &["add", "pkg", "legacy"]);
+        git(workspace, &["commit", "--quiet", "-m", "Vendor"]);
+
+        for project in ["app", "pkg", "legacy"] {
+            let readme = workspace.join(project).join("README.md");
+            std::fs::write(readme, "New README").expect("Failed to write file");
+        }
+
+        let commits = commit_projects(&manifest, "Git only", &[])
+            .await
+            .expect("Unable to commit");
+
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].path, "app");
+        assert_eq!(git(workspace, &["rev-list", "--count", "HEAD"]), "1");
+    }
+}
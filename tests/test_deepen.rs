@@ -0,0 +1,122 @@
+mod common;
+
+#[cfg(test)]
+mod test_deepen {
+
+    use crate::common::{create_remote, create_repository, git, load_manifest};
+    use colligo::application::{deepen_projects, DwlMode, ManifestError, ManifestParser};
+    use colligo::xml_parser::XmlParser;
+    use std::path::Path;
+
+    const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <project uri="example.com" name="team/app" path="app" revision="main" clone-depth="1" filter="blob:none"/>
+</manifest>
+"#;
+
+    fn count_missing_objects(repo: &Path) -> usize {
+        let objects = git(repo, &["rev-list", "--objects", "--missing=print", "HEAD"]);
+        objects.lines().filter(|line| line.starts_with('?')).count()
+    }
+
+    #[test]
+    fn parse_depth_and_filter() {
+        let parser = XmlParser::new();
+        let projects = parser.parse(MANIFEST).expect("Unable to parse XML");
+
+        assert_eq!(projects[0].get_clone_depth(), Some(1));
+        assert_eq!(projects[0].get_filter().unwrap(), "blob:none");
+        let composed = parser.compose(&projects).expect("Unable to compose XML");
+        assert_eq!(composed, MANIFEST);
+
+        for attribute in [
+            r#"clone-depth="0""#,
+            r#"clone-depth="deep""#,
+            r#"filter="big""#,
+        ] {
+            let manifest =
+                format!(r#"<manifest><project name="a" path="a" {attribute}/></manifest>"#);
+            assert!(
+                matches!(
+                    parser.parse(&manifest),
+                    Err(ManifestError::FailedToParseManifest(_))
+                ),
+                "{attribute}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn deepen_shallow_partial_clone() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let work = temp_dir.path().join("work");
+        create_repository(&work, &["first", "second", "third"]);
+        let remote = create_remote(temp_dir.path(), "team/app", &work);
+        git(&remote, &["config", "uploadpack.allowFilter", "true"]);
+        let manifest = load_manifest(temp_dir.path(), MANIFEST);
+
+        let report = manifest
+            .sync_report(&DwlMode::HTTPS, false, true, false)
+            .await;
+        assert!(report.is_success(), "{:?}", report.projects);
+
+        let app = temp_dir.path().join("app");
+        assert_eq!(git(&app, &["rev-parse", "--is-shallow-repository"]), "true");
+        assert_eq!(git(&app, &["rev-list", "--count", "HEAD"]), "1");
+        assert_eq!(
+            git(&app, &["config", "remote.origin.partialclonefilter"]),
+            "blob:none"
+        );
+
+        let results = deepen_projects(&manifest, Some(1), &[])
+            .await
+            .expect("Failed to deepen");
+        assert_eq!(results[0].result, Ok(true));
+        assert_eq!(git(&app, &["rev-list", "--count", "HEAD"]), "2");
+        assert!(count_missing_objects(&app) > 0);
+        assert_eq!(
+            git(&app, &["config", "remote.origin.partialclonefilter"]),
+            "blob:none"
+        );
+
+        // The whole history and the blobs filtered out are fetched
+        let results = deepen_projects(&manifest, None, &["app".to_string()])
+            .await
+            .expect("Failed to deepen");
+        assert_eq!(results[0].result, Ok(true));
+        assert_eq!(
+            git(&app, &["rev-parse", "--is-shallow-repository"]),
+            "false"
+        );
+        assert_eq!(git(&app, &["rev-list", "--count", "HEAD"]), "3");
+        assert_eq!(count_missing_objects(&app), 0);
+        assert_eq!(
+            git(&app, &["config", "--get-regexp", "^remote.origin"])
+                .lines()
+                .count(),
+            2
+        );
+
+        let results = deepen_projects(&manifest, None, &[]).await.unwrap();
+        assert_eq!(results[0].result, Ok(false));
+        let results = deepen_projects(&manifest, Some(1), &[]).await.unwrap();
+        assert_eq!(results[0].result, Ok(false));
+
+        // A commit older than the depth of the branches is fetched by id. Without filter, a
+        // missing commit is not downloaded on demand
+        let first = git(&work, &["rev-parse", "HEAD~2"]);
+        let pinned = MANIFEST
+            .replace(r#"revision="main""#, &format!(r#"revision="{first}""#))
+            .replace(r#" filter="blob:none""#, "");
+        let workspace = temp_dir.path().join("pinned");
+        let manifest = load_manifest(&workspace, &pinned);
+        let report = manifest
+            .sync_report(&DwlMode::HTTPS, false, true, false)
+            .await;
+        assert!(report.is_success(), "{:?}", report.projects);
+
+        let app = workspace.join("app");
+        assert_eq!(git(&app, &["rev-parse", "HEAD"]), first);
+        assert_eq!(git(&app, &["rev-list", "--count", "HEAD"]), "1");
+    }
+}
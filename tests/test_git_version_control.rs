@@ -1,10 +1,14 @@
+mod common;
+
 #[cfg(test)]
 mod test_git_version_control {
 
-    use colligo::application::DwlMode;
+    use crate::common::{commit, create_repository, git};
+    use colligo::application::{Cancellation, DwlMode, ManifestError, XVersionControl};
     use colligo::project::Project;
-    use colligo::version_control::GitVersionControl;
+    use colligo::version_control::{is_retryable, GitVersionControl, Progress, Retry};
     use git2::Repository;
+    use std::time::Duration;
 
     #[tokio::test]
     async fn clone_project_ssh() {
@@ -103,4 +107,159 @@ mod test_git_version_control {
             .await;
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn fetch_then_update() {
+        // Fetch does not change the working tree, update does not need the remote
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let upstream = temp_dir.path().join("upstream");
+        let checkout = temp_dir.path().join("app");
+
+        create_repository(&upstream, &["First"]);
+        git(
+            temp_dir.path(),
+            &["clone", "--quiet", upstream.to_str().unwrap(), "app"],
+        );
+        let second = commit(&upstream, "Second");
+
+        let project = Project::new(
+            "github.com".to_string(),
+            "team/app".to_string(),
+            "main".to_string(),
+            "app".to_string(),
+        );
+
+        let vcs = GitVersionControl::new();
+        vcs.fetch(temp_dir.path(), &project, None, false)
+            .await
+            .expect("fetch failed");
+        assert_ne!(git(&checkout, &["rev-parse", "HEAD"]), second);
+
+        std::fs::remove_dir_all(&upstream).expect("Failed to remove upstream");
+        vcs.update(temp_dir.path(), &project, None, false, false)
+            .await
+            .expect("update failed");
+        assert_eq!(git(&checkout, &["rev-parse", "HEAD"]), second);
+    }
+
+    #[test]
+    fn classify_retryable_errors() {
+        assert!(is_retryable(
+            "fatal: unable to access 'https://github.com/team/app.git/': The requested URL returned error: 503"
+        ));
+        assert!(is_retryable("error: RPC failed; HTTP 502 curl 22"));
+        assert!(is_retryable("fatal: the remote end hung up unexpectedly"));
+        assert!(is_retryable(
+            "ssh: connect to host github.com port 22: Connection timed out"
+        ));
+        assert!(is_retryable("read: Connection reset by peer"));
+
+        assert!(!is_retryable(
+            "fatal: Authentication failed for 'https://github.com/team/app.git/'"
+        ));
+        assert!(!is_retryable(
+            "git@github.com: Permission denied (publickey)."
+        ));
+        assert!(!is_retryable("fatal: couldn't find remote ref v9.9.9"));
+        assert!(!is_retryable("The requested URL returned error: 404"));
+    }
+
+    #[tokio::test]
+    async fn fetch_retries_transient_errors() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let project = Project::new(
+            "github.com".to_string(),
+            "team/app".to_string(),
+            "main".to_string(),
+            "app".to_string(),
+        );
+
+        let repo = temp_dir.path().join("app");
+        create_repository(&repo, &[]);
+
+        let mut vcs = GitVersionControl::new();
+        vcs.set_retry(Retry {
+            retries: 2,
+            delay: Duration::from_millis(1),
+        });
+
+        // Nothing listens on port 1: connection refused is retried
+        let url = "http://127.0.0.1:1/team/app.git";
+        git(&repo, &["remote", "add", "origin", url]);
+        let error = vcs
+            .fetch(temp_dir.path(), &project, None, false)
+            .await
+            .expect_err("fetch should fail");
+        assert!(error.to_string().contains("Failed after 3 attempts"));
+
+        // Missing repository fails immediately
+        let missing = temp_dir.path().join("missing");
+        git(
+            &repo,
+            &["remote", "set-url", "origin", missing.to_str().unwrap()],
+        );
+        let error = vcs
+            .fetch(temp_dir.path(), &project, None, false)
+            .await
+            .expect_err("fetch should fail");
+        assert!(!error.to_string().contains("attempts"));
+    }
+
+    #[tokio::test]
+    async fn fetch_timeout_and_cancellation() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let project = Project::new(
+            "github.com".to_string(),
+            "team/app".to_string(),
+            "main".to_string(),
+            "app".to_string(),
+        );
+
+        // Accept the connection but never answer
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("Failed to bind");
+        let url = format!("http://{}/team/app.git", listener.local_addr().unwrap());
+
+        let repo = temp_dir.path().join("app");
+        create_repository(&repo, &[]);
+        git(&repo, &["remote", "add", "origin", url.as_str()]);
+
+        let mut git = GitVersionControl::new();
+        git.set_retry(Retry {
+            retries: 0,
+            delay: Duration::from_millis(1),
+        });
+        git.set_timeout(Some(Duration::from_millis(500)));
+        let error = git
+            .fetch(temp_dir.path(), &project, None, false)
+            .await
+            .expect_err("fetch should time out");
+        assert!(error.to_string().contains("timed out"));
+
+        let cancellation = Cancellation::default();
+        cancellation.cancel();
+        git.set_timeout(None);
+        git.set_cancellation(cancellation);
+        let error = git
+            .fetch(temp_dir.path(), &project, None, false)
+            .await
+            .expect_err("fetch should be interrupted");
+        assert!(matches!(error, ManifestError::Interrupted(path) if path == "app"));
+    }
+
+    #[test]
+    fn progress_percent() {
+        let receiving = Progress::Receiving {
+            received: 5,
+            total: 20,
+            bytes: 1024,
+        };
+        assert_eq!(receiving.get_percent(), 25);
+        assert_eq!(receiving.to_string(), "Receiving objects");
+
+        let checkout = Progress::Checkout {
+            completed: 0,
+            total: 0,
+        };
+        assert_eq!(checkout.get_percent(), 100);
+    }
 }
@@ -0,0 +1,171 @@
+mod common;
+
+#[cfg(test)]
+mod test_hg_version_control {
+
+    use crate::common::load_manifest;
+    use colligo::application::{DwlMode, ManifestParser, XVersionControl};
+    use colligo::hg_version_control::{set_paths, HgVersionControl};
+    use colligo::project::{Project, Vcs};
+    use colligo::version_control::Retry;
+    use colligo::xml_parser::XmlParser;
+    use std::path::Path;
+    use std::process::Command;
+    use std::time::Duration;
+
+    fn hg(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("hg")
+            .current_dir(dir)
+            .env("HGPLAIN", "1")
+            .args(["--config", "ui.username=Colligo <colligo@test.com>"])
+            .args(args)
+            .output()
+            .expect("Failed to run hg");
+        assert!(output.status.success(), "hg {args:?} failed");
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    fn is_hg_installed() -> bool {
+        Command::new("hg").arg("--version").output().is_ok()
+    }
+
+    #[test]
+    fn parse_vcs() {
+        const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <default vcs="hg"/>
+    <project name="team/app" path="app"/>
+    <project name="team/lib" path="lib" vcs="git"/>
+</manifest>
+"#;
+
+        let parser = XmlParser::new();
+        let manifest = parser.parse(MANIFEST).expect("Unable to parse XML");
+
+        assert_eq!(manifest[0].get_vcs(), Vcs::Hg);
+        assert_eq!(
+            manifest[0].get_uri_hg_https(),
+            "https://github.com/team/app"
+        );
+        assert_eq!(manifest[0].get_uri_hg_ssh(), "ssh://hg@github.com/team/app");
+        assert_eq!(manifest[1].get_vcs(), Vcs::Git);
+
+        let composed = parser.compose(&manifest).expect("Unable to compose XML");
+        assert!(composed.contains(
+            r#"<project uri="github.com" name="team/app" path="app" revision="main" vcs="hg"/>"#
+        ));
+        assert!(composed
+            .contains(r#"<project uri="github.com" name="team/lib" path="lib" revision="main"/>"#));
+    }
+
+    #[test]
+    fn unknown_vcs() {
+        const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <project name="team/app" path="app" vcs="svn"/>
+</manifest>
+"#;
+
+        let result = XmlParser::new().parse(MANIFEST);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn hgrc_paths() {
+        const HGRC: &str = "[ui]\nusername = me\n\n[paths]\ndefault = https://old.com/app\n\n[extensions]\nshelve =\n";
+
+        let hgrc = set_paths(HGRC, "https://new.com/app", Some("ssh://hg@fork.com/app"));
+        assert_eq!(
+            hgrc,
+            "[ui]\nusername = me\n\n[extensions]\nshelve =\n\n[paths]\ndefault = https://new.com/app\ndefault-push = ssh://hg@fork.com/app\n"
+        );
+        assert_eq!(
+            set_paths("", "https://new.com/app", None),
+            "[paths]\ndefault = https://new.com/app\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn sync_uses_hg_for_hg_projects() {
+        // Nothing listens on port 1: the pull fails, with or without hg installed
+        const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <project uri="127.0.0.1:1" name="team/app" path="app" vcs="hg"/>
+</manifest>
+"#;
+
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let mut manifest = load_manifest(temp_dir.path(), MANIFEST);
+        manifest.set_retry(Retry {
+            retries: 0,
+            delay: Duration::from_millis(1),
+        });
+
+        let report = manifest
+            .sync_report(&DwlMode::HTTPS, false, true, false)
+            .await;
+
+        assert!(!report.is_success());
+        let error = report.projects[0].error.as_ref().unwrap().to_string();
+        assert!(error.contains("hg"), "{error}");
+        assert!(!temp_dir.path().join("app/.git").exists());
+    }
+
+    #[tokio::test]
+    async fn checkout_revision() {
+        if !is_hg_installed() {
+            eprintln!("hg is not installed, skipping");
+            return;
+        }
+
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let remote = temp_dir.path().join("remote");
+        std::fs::create_dir(&remote).unwrap();
+        hg(&remote, &["init"]);
+        std::fs::write(remote.join("README.md"), "first").unwrap();
+        hg(&remote, &["commit", "--addremove", "-m", "first"]);
+        hg(&remote, &["tag", "v1"]);
+        std::fs::write(remote.join("README.md"), "second").unwrap();
+        hg(&remote, &["commit", "-m", "second"]);
+        let first = hg(&remote, &["log", "-r", "v1", "--template", "{node}"]);
+
+        let workspace = temp_dir.path().join("workspace");
+        let project = Project::new(
+            "localhost".to_string(),
+            "team/app".to_string(),
+            "v1".to_string(),
+            "app".to_string(),
+        );
+        let vcs = HgVersionControl::new();
+        vcs.init(&workspace, &project, &DwlMode::HTTPS)
+            .await
+            .expect("init failed");
+
+        // Pull from the local repository instead of the https URL
+        let hgrc = workspace.join("app/.hg/hgrc");
+        let config = std::fs::read_to_string(&hgrc).unwrap();
+        assert!(config.contains("default = https://localhost/team/app"));
+        std::fs::write(
+            &hgrc,
+            set_paths(&config, &remote.display().to_string(), None),
+        )
+        .unwrap();
+
+        vcs.checkout(&workspace, &project, None, false, false)
+            .await
+            .expect("checkout failed");
+        let commit_id = vcs.get_commit_id(&workspace, &project).await.unwrap();
+        assert_eq!(commit_id, first);
+        assert!(!vcs.is_modified(&workspace, &project).await.unwrap());
+
+        // Local changes are kept unless forced
+        std::fs::write(workspace.join("app/README.md"), "local").unwrap();
+        assert!(vcs.is_modified(&workspace, &project).await.unwrap());
+        let result = vcs.update(&workspace, &project, None, false, false).await;
+        assert!(result.is_err());
+        vcs.update(&workspace, &project, None, true, false)
+            .await
+            .expect("forced update failed");
+        assert!(!vcs.is_modified(&workspace, &project).await.unwrap());
+    }
+}
@@ -0,0 +1,106 @@
+mod common;
+
+#[cfg(test)]
+mod test_import {
+
+    use crate::common::{create_repository, git};
+    use colligo::import::{import_projects, parse_remote_url};
+    use std::path::Path;
+
+    /// Repository with a commit, with `remote` as origin when set.
+    fn create_checkout(path: &Path, remote: Option<&str>) {
+        create_repository(path, &["First commit"]);
+        if let Some(url) = remote {
+            git(path, &["remote", "add", "origin", url]);
+        }
+    }
+
+    #[test]
+    fn parse_url() {
+        let expected = Some(("github.com".to_string(), "team/app".to_string()));
+        assert_eq!(
+            parse_remote_url("https://github.com/team/app.git"),
+            expected
+        );
+        assert_eq!(parse_remote_url("https://github.com/team/app/"), expected);
+        assert_eq!(parse_remote_url("git@github.com:team/app.git"), expected);
+        assert_eq!(
+            parse_remote_url("ssh://git@github.com/team/app.git"),
+            expected
+        );
+        assert_eq!(
+            parse_remote_url("ssh://git@github.com:22/team/app.git"),
+            expected
+        );
+        assert_eq!(
+            parse_remote_url("https://user@github.com/team/app.git"),
+            expected
+        );
+    }
+
+    #[test]
+    fn parse_unsupported_url() {
+        assert_eq!(parse_remote_url("/path/to/repo.git"), None);
+        assert_eq!(parse_remote_url("file:///path/to/repo.git"), None);
+        assert_eq!(parse_remote_url("https://github.com"), None);
+    }
+
+    #[tokio::test]
+    async fn import_checkouts() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let root = temp_dir.path();
+
+        create_checkout(&root.join("app"), Some("git@github.com:team/app.git"));
+        create_checkout(
+            &root.join("libs/core"),
+            Some("https://gitlab.com/team/core.git"),
+        );
+        create_checkout(&root.join("local"), None);
+
+        // Detached HEAD is imported as a commit
+        let core = root.join("libs/core");
+        let commit = git(&core, &["rev-parse", "HEAD"]);
+        git(&core, &["checkout", "--quiet", "--detach"]);
+
+        // Nested repository is not imported
+        create_checkout(
+            &root.join("app/vendor/nested"),
+            Some("git@github.com:team/nested.git"),
+        );
+
+        let (projects, skipped) = import_projects(root, root, "origin")
+            .await
+            .expect("Unable to import projects");
+
+        assert_eq!(projects.len(), 2);
+        assert_eq!(projects[0].get_uri(), "github.com");
+        assert_eq!(projects[0].get_name(), "team/app");
+        assert_eq!(projects[0].get_path(), "app");
+        assert_eq!(projects[0].get_revision(), "main");
+        assert_eq!(projects[1].get_uri(), "gitlab.com");
+        assert_eq!(projects[1].get_name(), "team/core");
+        assert_eq!(projects[1].get_path(), "libs/core");
+        assert_eq!(projects[1].get_revision(), &commit);
+
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].starts_with("local: "));
+    }
+
+    #[tokio::test]
+    async fn import_named_remote() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let root = temp_dir.path();
+
+        let app = root.join("app");
+        create_checkout(&app, Some("git@github.com:me/app.git"));
+        git(
+            &app,
+            &["remote", "add", "upstream", "git@github.com:team/app.git"],
+        );
+
+        let (projects, _) = import_projects(root, root, "upstream")
+            .await
+            .expect("Unable to import projects");
+        assert_eq!(projects[0].get_name(), "team/app");
+    }
+}
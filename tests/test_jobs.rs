@@ -0,0 +1,115 @@
+mod common;
+
+#[cfg(test)]
+mod test_jobs {
+
+    use crate::common::mock::MockVersionControl;
+    use crate::common::{create_workspace, git, load_manifest};
+    use colligo::application::{get_projects_status, Cancellation, DwlMode, Jobs, ManifestError};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <project uri="github.com" name="team/app" path="app" revision="main"/>
+    <project uri="github.com" name="team/lib" path="lib" revision="main"/>
+    <project uri="github.com" name="team/doc" path="doc" revision="main"/>
+</manifest>
+"#;
+
+    const PROJECTS: [&str; 3] = ["app", "lib", "doc"];
+
+    #[test]
+    fn jobs_at_least_one() {
+        assert_eq!(Jobs::new(0), Jobs::new(1));
+        assert!(Jobs::default().network >= 1);
+        assert!(Jobs::default().checkout >= 1);
+    }
+
+    #[tokio::test]
+    async fn sync_limits_concurrent_operations() {
+        for jobs in [
+            Jobs {
+                network: 1,
+                checkout: 1,
+            },
+            Jobs {
+                network: 2,
+                checkout: 1,
+            },
+            Jobs {
+                network: 3,
+                checkout: 2,
+            },
+        ] {
+            let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+            let mut vcs = MockVersionControl::new(&[("app", "a1"), ("lib", "b1"), ("doc", "c1")]);
+            vcs.delay = Duration::from_millis(100);
+            let vcs = Arc::new(vcs);
+            let mut manifest = load_manifest(temp_dir.path(), MANIFEST);
+            manifest.set_version_control(vcs.clone());
+            manifest.set_jobs(jobs);
+
+            let report = manifest
+                .sync_report(&DwlMode::HTTPS, false, true, false)
+                .await;
+            assert!(report.is_success(), "{:?}", report.projects);
+
+            assert!(vcs.get_peak("fetch") <= jobs.network, "{jobs:?}");
+            assert!(vcs.get_peak("update") <= jobs.checkout, "{jobs:?}");
+            if jobs.network == PROJECTS.len() {
+                // The fetches of all the projects overlap when the limit allows it
+                assert_eq!(vcs.get_peak("fetch"), jobs.network);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn pin_and_status_keep_manifest_order() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let mut manifest = create_workspace(temp_dir.path(), &PROJECTS, MANIFEST);
+        manifest.set_jobs(Jobs::new(2));
+
+        std::fs::write(temp_dir.path().join("lib/README.md"), "Modified")
+            .expect("Failed to write file");
+
+        let status = get_projects_status(&manifest, temp_dir.path()).await;
+        assert_eq!(status, vec!["app", "lib (modified)", "doc"]);
+
+        let pinned = manifest.pin().await.expect("Unable to pin manifest");
+        assert_eq!(pinned.get_jobs(), Jobs::new(2));
+        for (project, path) in pinned.get_projects().iter().zip(["app", "lib", "doc"]) {
+            assert_eq!(project.get_path(), path);
+            let repo = temp_dir.path().join(path);
+            assert_eq!(project.get_revision(), &git(&repo, &["rev-parse", "HEAD"]));
+        }
+    }
+
+    #[tokio::test]
+    async fn cancelled_sync_reports_unfinished_projects() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let mut manifest = create_workspace(temp_dir.path(), &PROJECTS, MANIFEST);
+        std::fs::remove_dir_all(temp_dir.path().join("doc")).expect("Failed to remove doc");
+
+        let cancellation = Cancellation::default();
+        cancellation.cancel();
+        manifest.set_cancellation(cancellation);
+
+        let error = manifest
+            .sync(&DwlMode::HTTPS, false, true, false)
+            .await
+            .expect_err("sync should be interrupted");
+        match error {
+            ManifestError::Interrupted(msg) => {
+                for path in ["app", "lib", "doc"] {
+                    assert!(msg.lines().any(|line| line == path), "{path} not in {msg}");
+                }
+            }
+            e => panic!("Unexpected error {e}"),
+        }
+
+        // Existing checkouts are left untouched, nothing is created
+        assert!(temp_dir.path().join("app/README.md").exists());
+        assert!(!temp_dir.path().join("doc").exists());
+    }
+}
@@ -0,0 +1,111 @@
+mod common;
+
+#[cfg(all(test, feature = "libgit2"))]
+mod test_libgit2_version_control {
+
+    use crate::common::{commit, git};
+    use colligo::application::{DwlMode, ManifestError, XVersionControl};
+    use colligo::libgit2_version_control::Libgit2VersionControl;
+    use colligo::project::Project;
+
+    #[tokio::test]
+    async fn sync_without_git_command() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let upstream = temp_dir.path().join("upstream");
+        std::fs::create_dir_all(&upstream).expect("Failed to create upstream");
+        git(&upstream, &["init", "--quiet", "--initial-branch", "main"]);
+        let first = commit(&upstream, "First");
+
+        let project = Project::new(
+            "github.com".to_string(),
+            "team/app".to_string(),
+            "main".to_string(),
+            "app".to_string(),
+        );
+        let checkout = temp_dir.path().join("app");
+
+        let vcs = Libgit2VersionControl::new();
+        vcs.init(temp_dir.path(), &project, &DwlMode::HTTPS)
+            .await
+            .expect("init failed");
+        assert_eq!(
+            git(&checkout, &["remote", "get-url", "origin"]),
+            "https://github.com/team/app.git"
+        );
+
+        // Fetch from the local upstream instead
+        git(
+            &checkout,
+            &["remote", "set-url", "origin", upstream.to_str().unwrap()],
+        );
+        let stats = vcs
+            .fetch(temp_dir.path(), &project, None, false)
+            .await
+            .expect("fetch failed");
+        assert_eq!(stats.attempts, 1);
+        assert!(stats.bytes.unwrap_or_default() > 0);
+
+        vcs.update(temp_dir.path(), &project, None, false, false)
+            .await
+            .expect("update failed");
+        assert_eq!(
+            git(&checkout, &["rev-parse", "--abbrev-ref", "HEAD"]),
+            "main"
+        );
+        assert_eq!(
+            vcs.get_commit_id(temp_dir.path(), &project).await,
+            Ok(first)
+        );
+        assert_eq!(
+            std::fs::read_to_string(checkout.join("README.md")).unwrap(),
+            "First"
+        );
+
+        // The branch is fast-forwarded to origin
+        let second = commit(&upstream, "Second");
+        vcs.checkout(temp_dir.path(), &project, None, false, false)
+            .await
+            .expect("checkout failed");
+        assert_eq!(git(&checkout, &["rev-parse", "HEAD"]), second);
+        assert_eq!(
+            std::fs::read_to_string(checkout.join("README.md")).unwrap(),
+            "Second"
+        );
+
+        assert_eq!(vcs.is_modified(temp_dir.path(), &project).await, Ok(false));
+        std::fs::write(checkout.join("README.md"), "Modified").expect("Failed to write file");
+        assert_eq!(vcs.is_modified(temp_dir.path(), &project).await, Ok(true));
+    }
+
+    #[tokio::test]
+    async fn refuse_git_only_attributes() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let vcs = Libgit2VersionControl::new();
+
+        let project = |attribute: &str| {
+            let mut project = Project::new(
+                "github.com".to_string(),
+                "team/app".to_string(),
+                "main".to_string(),
+                "app".to_string(),
+            );
+            match attribute {
+                "<sparse>" => project.add_sparse_path("src".to_string()),
+                "clone-depth" => project.set_clone_depth(1),
+                _ => project.set_filter("blob:none".to_string()),
+            }
+            project
+        };
+
+        for attribute in ["<sparse>", "clone-depth", "filter"] {
+            let result = vcs
+                .init(temp_dir.path(), &project(attribute), &DwlMode::HTTPS)
+                .await;
+            assert!(
+                matches!(&result, Err(ManifestError::UnsupportedBackend(e)) if e.contains(attribute)),
+                "{result:?}"
+            );
+        }
+        assert!(!temp_dir.path().join("app").exists());
+    }
+}
@@ -0,0 +1,60 @@
+mod common;
+
+#[cfg(test)]
+mod test_light_fetch {
+
+    use crate::common::{create_remote, create_repository, git, load_manifest};
+    use colligo::application::DwlMode;
+    use colligo::version_control::is_commit_id;
+    use std::path::Path;
+
+    async fn sync_light(workspace: &Path, revision: &str) {
+        let xml = format!(
+            r#"<manifest>
+    <project uri="example.com" name="team/app" path="app" revision="{revision}"/>
+</manifest>"#
+        );
+        let manifest = load_manifest(workspace, &xml);
+
+        let report = manifest
+            .sync_report(&DwlMode::HTTPS, true, true, false)
+            .await;
+        assert!(report.is_success(), "{:?}", report.projects);
+        assert_eq!(report.projects[0].new_commit.as_deref(), Some(revision));
+    }
+
+    #[test]
+    fn commit_id() {
+        assert!(is_commit_id("0123456789abcdef0123456789ABCDEF01234567"));
+        assert!(!is_commit_id("0123456"));
+        assert!(!is_commit_id("main"));
+        assert!(!is_commit_id("v1.0.0"));
+    }
+
+    #[tokio::test]
+    async fn fetch_commit_id() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let work = temp_dir.path().join("work");
+        create_repository(&work, &["first", "second", "third"]);
+        create_remote(temp_dir.path(), "team/app", &work);
+        let commit_id = git(&work, &["rev-parse", "HEAD~1"]);
+
+        // Protocol v2 servers let the clients fetch any reachable commit
+        std::env::set_var("GIT_CONFIG_COUNT", "1");
+        let workspace = temp_dir.path().join("v2");
+        sync_light(&workspace, &commit_id).await;
+        let app = workspace.join("app");
+        assert_eq!(git(&app, &["rev-parse", "HEAD"]), commit_id);
+        assert_eq!(git(&app, &["rev-list", "--count", "HEAD"]), "1");
+
+        // Protocol v0 servers refuse the commits not advertised, all the branches are fetched
+        std::env::set_var("GIT_CONFIG_COUNT", "2");
+        std::env::set_var("GIT_CONFIG_KEY_1", "protocol.version");
+        std::env::set_var("GIT_CONFIG_VALUE_1", "0");
+        let workspace = temp_dir.path().join("v0");
+        sync_light(&workspace, &commit_id).await;
+        let app = workspace.join("app");
+        assert_eq!(git(&app, &["rev-parse", "HEAD"]), commit_id);
+        assert_eq!(git(&app, &["rev-list", "--count", "HEAD"]), "2");
+    }
+}
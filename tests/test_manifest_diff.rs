@@ -0,0 +1,106 @@
+#[cfg(test)]
+mod test_manifest_diff {
+
+    use colligo::application::ManifestParser;
+    use colligo::manifest_diff::{ManifestDiff, ProjectChange};
+    use colligo::xml_parser::XmlParser;
+
+    const OLD_MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <project uri="github.com" name="chrisdsa/colligo" path="./dev" revision="dev"/>
+    <project uri="github.com" name="chrisdsa/colligo" path="release/v0" revision="v0.0.0"/>
+    <project uri="github.com" name="team/tools" path="tools" revision="main"/>
+    <project uri="github.com" name="team/removed" path="removed" revision="main"/>
+    <project uri="github.com" name="team/docs" path="docs" revision="main">
+        <linkfile src="./README.md" dest="./ln_README.md"/>
+    </project>
+</manifest>
+"#;
+
+    const NEW_MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <project uri="github.com" name="chrisdsa/colligo" path="dev" revision="dev"/>
+    <project uri="github.com" name="chrisdsa/colligo" path="release/v0" revision="v0.1.0"/>
+    <project uri="github.com" name="team/tools" path="third_party/tools" revision="main"/>
+    <project uri="gitlab.com" name="team/docs" path="docs" revision="main">
+        <copyfile src="./README.md" dest="./cp_README.md"/>
+    </project>
+    <project uri="github.com" name="team/added" path="added" revision="v1.0.0"/>
+</manifest>
+"#;
+
+    fn diff(old: &str, new: &str) -> ManifestDiff {
+        let parser = XmlParser::new();
+        let old = parser.parse(old).expect("Unable to parse old manifest");
+        let new = parser.parse(new).expect("Unable to parse new manifest");
+        ManifestDiff::new(&old, &new)
+    }
+
+    #[test]
+    fn identical_manifests() {
+        let diff = diff(OLD_MANIFEST, OLD_MANIFEST);
+
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_text(), "No differences\n");
+    }
+
+    #[test]
+    fn all_kind_of_changes() {
+        let diff = diff(OLD_MANIFEST, NEW_MANIFEST);
+
+        assert_eq!(
+            diff.get_changes(),
+            &vec![
+                ProjectChange::Removed {
+                    path: "removed".to_string(),
+                    uri: "github.com/team/removed".to_string(),
+                    revision: "main".to_string(),
+                },
+                ProjectChange::Added {
+                    path: "added".to_string(),
+                    uri: "github.com/team/added".to_string(),
+                    revision: "v1.0.0".to_string(),
+                },
+                ProjectChange::Moved {
+                    from: "tools".to_string(),
+                    to: "third_party/tools".to_string(),
+                    uri: "github.com/team/tools".to_string(),
+                },
+                ProjectChange::Revision {
+                    path: "release/v0".to_string(),
+                    old: "v0.0.0".to_string(),
+                    new: "v0.1.0".to_string(),
+                },
+                ProjectChange::Uri {
+                    path: "docs".to_string(),
+                    old: "github.com/team/docs".to_string(),
+                    new: "gitlab.com/team/docs".to_string(),
+                },
+                ProjectChange::Actions {
+                    path: "docs".to_string(),
+                    old: vec!["linkfile ./README.md -> ./ln_README.md".to_string()],
+                    new: vec!["copyfile ./README.md -> ./cp_README.md".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn text_and_json_output() {
+        let diff = diff(OLD_MANIFEST, NEW_MANIFEST);
+
+        let text = diff.to_text();
+        assert!(text.contains("- removed (github.com/team/removed @ main)\n"));
+        assert!(text.contains("+ added (github.com/team/added @ v1.0.0)\n"));
+        assert!(text.contains("> tools -> third_party/tools (github.com/team/tools)\n"));
+        assert!(text.contains("~ release/v0 revision: v0.0.0 -> v0.1.0\n"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&diff.to_json()).expect("Invalid JSON output");
+        let changes = json["changes"].as_array().expect("Missing changes");
+        assert_eq!(changes.len(), 6);
+        assert_eq!(changes[3]["change"], "revision");
+        assert_eq!(changes[3]["path"], "release/v0");
+        assert_eq!(changes[3]["new"], "v0.1.0");
+    }
+}
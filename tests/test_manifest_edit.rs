@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod test_manifest_edit {
+
+    use colligo::manifest_edit::{
+        add_project, parse_assignments, remove_project, set_project_attributes, split_project_spec,
+    };
+
+    const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <!-- Application -->
+    <project uri="github.com" name="team/app" path="app" revision="main">
+        <linkfile src="README.md" dest="README.md"/>
+    </project>
+
+    <!-- Library pinned to a release -->
+    <project uri="github.com" name="team/lib" path="libs/lib" revision="v1.0.0"/>
+</manifest>
+"#;
+
+    #[test]
+    fn split_spec() {
+        assert_eq!(
+            split_project_spec("github.com/team/app"),
+            Some(("github.com".to_string(), "team/app".to_string()))
+        );
+        assert_eq!(split_project_spec("github.com"), None);
+    }
+
+    #[test]
+    fn add_after_last_project() {
+        let edited = add_project(MANIFEST, "gitlab.com", "team/doc", "doc", Some("main"))
+            .expect("Unable to add project");
+
+        let expected = MANIFEST.replace(
+            r#"revision="v1.0.0"/>"#,
+            "revision=\"v1.0.0\"/>\n    <project uri=\"gitlab.com\" name=\"team/doc\" path=\"doc\" revision=\"main\"/>",
+        );
+        assert_eq!(edited, expected);
+    }
+
+    #[test]
+    fn add_to_empty_manifest() {
+        let manifest = "<manifest>\n    <!-- Empty -->\n</manifest>\n";
+        let edited =
+            add_project(manifest, "github.com", "team/app", "app", None).expect("Unable to add");
+        assert_eq!(
+            edited,
+            "<manifest>\n    <!-- Empty -->\n    <project uri=\"github.com\" name=\"team/app\" path=\"app\"/>\n</manifest>\n"
+        );
+    }
+
+    #[test]
+    fn add_existing_path() {
+        let result = add_project(MANIFEST, "github.com", "team/other", "./app", None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn remove_keeps_comments() {
+        let edited = remove_project(MANIFEST, "app/").expect("Unable to remove project");
+        let expected = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <!-- Application -->
+
+    <!-- Library pinned to a release -->
+    <project uri="github.com" name="team/lib" path="libs/lib" revision="v1.0.0"/>
+</manifest>
+"#;
+        assert_eq!(edited, expected);
+        assert!(remove_project(MANIFEST, "unknown").is_err());
+    }
+
+    #[test]
+    fn set_attributes() {
+        let attributes = parse_assignments(&[
+            "revision=v2.0.0".to_string(),
+            "pushuri=gitlab.com".to_string(),
+        ])
+        .expect("Invalid assignments");
+        let edited =
+            set_project_attributes(MANIFEST, "libs/lib", &attributes).expect("Unable to set");
+
+        assert_eq!(
+            edited,
+            MANIFEST.replace(
+                r#"revision="v1.0.0"/>"#,
+                r#"revision="v2.0.0" pushuri="gitlab.com"/>"#
+            )
+        );
+    }
+
+    #[test]
+    fn set_invalid_attributes() {
+        let unknown = vec![("branch".to_string(), "main".to_string())];
+        assert!(set_project_attributes(MANIFEST, "app", &unknown).is_err());
+
+        let duplicate = vec![("path".to_string(), "app".to_string())];
+        assert!(set_project_attributes(MANIFEST, "libs/lib", &duplicate).is_err());
+
+        assert!(parse_assignments(&["revision".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,74 @@
+mod common;
+
+#[cfg(test)]
+mod test_mirror {
+
+    use crate::common::{commit, create_remote, create_repository, git, load_manifest};
+    use colligo::application::DwlMode;
+    use colligo::sync_report::SyncAction;
+
+    // Both projects are clones of the same repository, the archive is not mirrored
+    const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <project uri="example.com" name="team/app" path="app" revision="main"/>
+    <project uri="example.com" name="team/app" path="app-release" revision="v1"/>
+    <archive url="https://example.com/pkg.tar.gz" sha256="e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855" path="pkg"/>
+</manifest>
+"#;
+
+    #[tokio::test]
+    async fn sync_from_mirror() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let work = temp_dir.path().join("work");
+        create_repository(&work, &["v1"]);
+        git(&work, &["tag", "v1"]);
+        let remote = create_remote(temp_dir.path(), "team/app", &work);
+        git(
+            &work,
+            &["remote", "add", "origin", remote.to_str().unwrap()],
+        );
+        let manifest = load_manifest(&temp_dir.path().join("build"), MANIFEST);
+        let mirror_dir = temp_dir.path().join("mirror");
+
+        let report = manifest.mirror_report(&mirror_dir, &DwlMode::HTTPS).await;
+        assert!(report.is_success(), "{:?}", report.projects);
+        assert_eq!(report.projects.len(), 1);
+        assert_eq!(report.projects[0].path, "example.com/team/app.git");
+        assert_eq!(report.projects[0].action, SyncAction::Cloned);
+
+        let mirror = mirror_dir.join("example.com/team/app.git");
+        let v1 = git(&work, &["rev-parse", "v1"]);
+        assert_eq!(git(&mirror, &["rev-parse", "main"]), v1);
+        assert_eq!(git(&mirror, &["rev-parse", "v1^{commit}"]), v1);
+
+        let report = manifest.mirror_report(&mirror_dir, &DwlMode::HTTPS).await;
+        assert_eq!(report.projects[0].action, SyncAction::UpToDate);
+
+        let v2 = commit(&work, "v2");
+        git(&work, &["push", "--quiet", "origin", "main"]);
+        let report = manifest.mirror_report(&mirror_dir, &DwlMode::HTTPS).await;
+        assert_eq!(report.projects[0].action, SyncAction::Updated);
+        assert_eq!(git(&mirror, &["rev-parse", "main"]), v2);
+
+        // The remote is no longer reachable, the workspace is synchronized from the mirror
+        std::env::set_var("GIT_CONFIG_COUNT", "0");
+        let workspace = temp_dir.path().join("workspace");
+        let mut manifest = load_manifest(&workspace, MANIFEST);
+        manifest.retain_projects(&["app".to_string(), "app-release".to_string()]);
+        let report = manifest
+            .sync_report(&DwlMode::Mirror(mirror_dir.clone()), false, true, false)
+            .await;
+        assert!(report.is_success(), "{:?}", report.projects);
+
+        let app = workspace.join("app");
+        assert_eq!(git(&app, &["rev-parse", "HEAD"]), v2);
+        assert_eq!(
+            git(&app, &["remote", "get-url", "origin"]),
+            mirror.display().to_string()
+        );
+        assert_eq!(
+            git(&workspace.join("app-release"), &["rev-parse", "HEAD"]),
+            v1
+        );
+    }
+}
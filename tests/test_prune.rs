@@ -0,0 +1,181 @@
+mod common;
+
+#[cfg(test)]
+mod test_prune {
+
+    use crate::common::{create_repository, git, load_manifest};
+    use colligo::application::ManifestInstance;
+    use colligo::project::Vcs;
+    use colligo::prune::prune_projects;
+    use colligo::workspace_state::{ProjectState, WorkspaceState};
+    use std::path::Path;
+
+    const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <project uri="github.com" name="team/app" path="app" revision="main"/>
+    <project uri="github.com" name="team/lib" path="lib" revision="main"/>
+</manifest>
+"#;
+
+    const MANIFEST_WITHOUT_LIB: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <project uri="github.com" name="team/app" path="app" revision="main"/>
+</manifest>
+"#;
+
+    /// Create the repositories pushed to a bare remote and record them as synced.
+    fn create_workspace(path: &Path) -> ManifestInstance {
+        for project in ["app", "lib"] {
+            let repo = path.join(project);
+            let remote = path.join(format!("{project}.git"));
+            create_repository(&repo, &[project]);
+            git(
+                path,
+                &["init", "--quiet", "--bare", remote.to_str().unwrap()],
+            );
+            git(
+                &repo,
+                &["remote", "add", "origin", remote.to_str().unwrap()],
+            );
+            git(&repo, &["push", "--quiet", "origin", "main"]);
+        }
+
+        record_sync(path, MANIFEST);
+        load_manifest(path, MANIFEST_WITHOUT_LIB)
+    }
+
+    /// Record the projects of the manifest `content` as synced.
+    fn record_sync(path: &Path, content: &str) {
+        let manifest = load_manifest(path, content);
+        let synced = manifest
+            .get_projects()
+            .iter()
+            .map(|project| ProjectState::new(project, None))
+            .collect();
+        let mut state = WorkspaceState::load(path).expect("Unable to load workspace state");
+        state.record_sync(manifest.get_file(), synced, Vec::new());
+        state.save(path).expect("Unable to save workspace state");
+    }
+
+    #[tokio::test]
+    async fn prune_removed_project() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let manifest = create_workspace(temp_dir.path());
+
+        let pruned = prune_projects(&manifest, false)
+            .await
+            .expect("Unable to prune projects");
+        assert_eq!(pruned, vec!["lib".to_string()]);
+        assert!(!temp_dir.path().join("lib").exists());
+        assert!(temp_dir.path().join("app").exists());
+
+        // Nothing left to prune
+        let pruned = prune_projects(&manifest, false)
+            .await
+            .expect("Unable to prune projects");
+        assert!(pruned.is_empty());
+    }
+
+    #[tokio::test]
+    async fn prune_refused_with_local_changes() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let manifest = create_workspace(temp_dir.path());
+
+        let lib = temp_dir.path().join("lib");
+        std::fs::write(lib.join("untracked.md"), "Untracked").expect("Failed to write file");
+
+        assert!(prune_projects(&manifest, false).await.is_err());
+        assert!(lib.exists());
+
+        let pruned = prune_projects(&manifest, true)
+            .await
+            .expect("Unable to prune projects");
+        assert_eq!(pruned, vec!["lib".to_string()]);
+        assert!(!lib.exists());
+    }
+
+    #[tokio::test]
+    async fn prune_refused_with_unpushed_commits() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let manifest = create_workspace(temp_dir.path());
+
+        let lib = temp_dir.path().join("lib");
+        git(
+            &lib,
+            &["commit", "--allow-empty", "--quiet", "-m", "Unpushed"],
+        );
+
+        assert!(prune_projects(&manifest, false).await.is_err());
+        assert!(lib.exists());
+    }
+
+    #[tokio::test]
+    async fn prune_refused_with_commits_on_detached_head() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let manifest = create_workspace(temp_dir.path());
+
+        // As synchronized to a tag or a commit id
+        let lib = temp_dir.path().join("lib");
+        git(&lib, &["checkout", "--quiet", "--detach"]);
+        git(
+            &lib,
+            &["commit", "--allow-empty", "--quiet", "-m", "Unpushed"],
+        );
+
+        assert!(prune_projects(&manifest, false).await.is_err());
+        assert!(lib.exists());
+    }
+
+    #[tokio::test]
+    async fn prune_keeps_directory_of_nested_project() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let path = temp_dir.path();
+        create_repository(&path.join("vendor"), &["vendor"]);
+        create_repository(&path.join("vendor/lib"), &["lib"]);
+        record_sync(
+            path,
+            r#"<manifest>
+    <project uri="github.com" name="team/vendor" path="vendor" revision="main"/>
+    <project uri="github.com" name="team/lib" path="vendor/lib" revision="main"/>
+</manifest>"#,
+        );
+        let manifest = load_manifest(
+            path,
+            r#"<manifest>
+    <project uri="github.com" name="team/lib" path="vendor/lib" revision="main"/>
+</manifest>"#,
+        );
+
+        assert!(prune_projects(&manifest, true).await.is_err());
+        assert!(path.join("vendor/lib/README.md").exists());
+    }
+
+    #[tokio::test]
+    async fn prune_checks_hg_projects_with_hg() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let path = temp_dir.path();
+        create_workspace(path);
+
+        // Not a valid repository for hg, if installed: its changes cannot be checked
+        let legacy = path.join("legacy");
+        std::fs::create_dir_all(legacy.join(".hg")).expect("Failed to create .hg");
+        std::fs::write(legacy.join("README.md"), "Not committed").expect("Failed to write file");
+        record_sync(
+            path,
+            r#"<manifest>
+    <project uri="example.com" name="team/legacy" path="legacy" vcs="hg"/>
+    <project uri="github.com" name="team/app" path="app" revision="main"/>
+    <project uri="github.com" name="team/lib" path="lib" revision="main"/>
+</manifest>"#,
+        );
+        let manifest = load_manifest(path, MANIFEST_WITHOUT_LIB);
+
+        // The other projects are still pruned
+        assert!(prune_projects(&manifest, false).await.is_err());
+        assert!(legacy.join("README.md").exists());
+        assert!(!path.join("lib").exists());
+
+        let state = WorkspaceState::load(path).expect("Unable to load workspace state");
+        assert_eq!(state.get_project("legacy").unwrap().vcs, Vcs::Hg);
+    }
+}
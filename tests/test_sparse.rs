@@ -0,0 +1,89 @@
+mod common;
+
+#[cfg(test)]
+mod test_sparse {
+
+    use crate::common::{create_remote, git, load_manifest};
+    use colligo::application::{DwlMode, ManifestError, ManifestParser};
+    use colligo::xml_parser::XmlParser;
+    use std::path::Path;
+
+    const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <project uri="example.com" name="team/monorepo" path="sdk" revision="main">
+        <sparse path="sdk/include"/>
+        <linkfile src="sdk/include/sdk.h" dest="sdk.h"/>
+    </project>
+</manifest>
+"#;
+
+    /// Repository served as `https://example.com/team/monorepo.git`, allowing the partial clones.
+    fn create_monorepo(path: &Path) {
+        let work = path.join("work");
+        for dir in ["sdk/include", "sdk/src", "app"] {
+            std::fs::create_dir_all(work.join(dir)).unwrap();
+        }
+        std::fs::write(work.join("README.md"), "monorepo").unwrap();
+        std::fs::write(work.join("sdk/include/sdk.h"), "void sdk();").unwrap();
+        std::fs::write(work.join("sdk/src/sdk.c"), "void sdk() {}").unwrap();
+        std::fs::write(work.join("app/main.c"), "int main() {}").unwrap();
+        git(&work, &["init", "--quiet", "--initial-branch", "main"]);
+        git(&work, &["add", "."]);
+        git(&work, &["commit", "--quiet", "-m", "monorepo"]);
+        let remote = create_remote(path, "team/monorepo", &work);
+        git(&remote, &["config", "uploadpack.allowFilter", "true"]);
+    }
+
+    #[test]
+    fn parse_sparse() {
+        let parser = XmlParser::new();
+        let projects = parser.parse(MANIFEST).expect("Unable to parse XML");
+
+        assert_eq!(projects[0].get_sparse_paths(), &vec!["sdk/include"]);
+        assert_eq!(projects[0].get_actions().len(), 1);
+
+        let composed = parser.compose(&projects).expect("Unable to compose XML");
+        assert_eq!(composed, MANIFEST);
+
+        let missing_path = r#"<manifest><project name="a" path="a"><sparse/></project></manifest>"#;
+        assert!(matches!(
+            parser.parse(missing_path),
+            Err(ManifestError::FailedToParseManifest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn sync_sparse_checkout() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        create_monorepo(temp_dir.path());
+        let workspace = temp_dir.path().join("workspace");
+        let manifest = load_manifest(&workspace, MANIFEST);
+
+        let report = manifest
+            .sync_report(&DwlMode::HTTPS, false, true, false)
+            .await;
+        assert!(report.is_success(), "{:?}", report.projects);
+
+        let sdk = workspace.join("sdk");
+        assert!(sdk.join("README.md").exists());
+        assert!(sdk.join("sdk/include/sdk.h").exists());
+        assert!(!sdk.join("sdk/src").exists());
+        assert!(!sdk.join("app").exists());
+        assert!(workspace.join("sdk.h").exists());
+
+        // Only the blobs of the sparse checkout were downloaded
+        let objects = git(&sdk, &["rev-list", "--objects", "--missing=print", "HEAD"]);
+        let missing = objects.lines().filter(|line| line.starts_with('?'));
+        assert_eq!(missing.count(), 2);
+
+        // Without sparse paths, everything is checked out
+        let full = MANIFEST.replace("        <sparse path=\"sdk/include\"/>\n", "");
+        let manifest = load_manifest(&workspace, &full);
+        let report = manifest
+            .sync_report(&DwlMode::HTTPS, false, true, false)
+            .await;
+        assert!(report.is_success(), "{:?}", report.projects);
+        assert!(sdk.join("sdk/src/sdk.c").exists());
+        assert!(sdk.join("app/main.c").exists());
+    }
+}
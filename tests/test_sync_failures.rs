@@ -0,0 +1,90 @@
+mod common;
+
+#[cfg(test)]
+mod test_sync_failures {
+
+    use crate::common::load_manifest;
+    use colligo::application::{DwlMode, Jobs, ManifestError, ManifestInstance};
+    use colligo::sync_report::SyncAction;
+    use colligo::version_control::Retry;
+    use colligo::workspace_state::WorkspaceState;
+    use std::path::Path;
+    use std::time::Duration;
+
+    // Nothing listens on port 1: every fetch fails without network access
+    const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <project uri="127.0.0.1:1" name="team/app" path="app" revision="main"/>
+    <project uri="127.0.0.1:1" name="team/lib" path="lib" revision="main"/>
+    <project uri="127.0.0.1:1" name="team/doc" path="doc" revision="main"/>
+</manifest>
+"#;
+
+    fn create_manifest(path: &Path) -> ManifestInstance {
+        let mut manifest = load_manifest(path, MANIFEST);
+        manifest.set_retry(Retry {
+            retries: 0,
+            delay: Duration::from_millis(1),
+        });
+        manifest
+    }
+
+    fn get_failed(path: &Path) -> Vec<String> {
+        let state = WorkspaceState::load(path).expect("Unable to load state");
+        let mut failed = state.get_failed().clone();
+        failed.sort();
+        failed
+    }
+
+    #[tokio::test]
+    async fn keep_going_records_failed_projects() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let manifest = create_manifest(temp_dir.path());
+
+        let error = manifest
+            .sync(&DwlMode::HTTPS, false, true, false)
+            .await
+            .expect_err("sync should fail");
+        match error {
+            ManifestError::FailedToSync(msg) => assert!(!msg.contains("Cancelled")),
+            e => panic!("Unexpected error {e}"),
+        }
+        assert_eq!(get_failed(temp_dir.path()), vec!["app", "doc", "lib"]);
+
+        let report = manifest
+            .sync_report(&DwlMode::HTTPS, false, true, false)
+            .await;
+        let paths: Vec<&str> = report.projects.iter().map(|p| p.path.as_str()).collect();
+        assert_eq!(paths, vec!["app", "lib", "doc"]);
+        for project in report.projects {
+            assert_eq!(project.action, SyncAction::Failed);
+            assert!(project.error.is_some());
+            assert_eq!(project.new_commit, None);
+        }
+    }
+
+    #[tokio::test]
+    async fn fail_fast_cancels_other_projects() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let mut manifest = create_manifest(temp_dir.path());
+        manifest.set_jobs(Jobs::new(1));
+        manifest.set_fail_fast(true);
+
+        let error = manifest
+            .sync(&DwlMode::HTTPS, false, true, false)
+            .await
+            .expect_err("sync should fail");
+        match error {
+            ManifestError::FailedToSync(msg) => assert!(msg.contains("Cancelled: "), "{msg}"),
+            e => panic!("Unexpected error {e}"),
+        }
+
+        // The cancelled projects are retried with the failed one
+        assert_eq!(get_failed(temp_dir.path()), vec!["app", "doc", "lib"]);
+
+        // Only the repositories created by the interrupted projects are removed
+        assert!(temp_dir.path().join("app/.git").exists());
+        assert!(!temp_dir.path().join("lib").exists());
+        assert!(!temp_dir.path().join("doc").exists());
+    }
+}
@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod test_sync_report {
+
+    use colligo::application::ManifestError;
+    use colligo::sync_report::{ProjectReport, SyncAction, SyncReport};
+    use colligo::version_control::FetchStats;
+    use std::time::Duration;
+
+    fn create_report() -> SyncReport {
+        let mut app = ProjectReport::new("app".to_string());
+        app.action = SyncAction::Updated;
+        app.old_commit = Some("1111111111111111".to_string());
+        app.new_commit = Some("2222222222222222".to_string());
+        app.duration = Duration::from_millis(1500);
+        app.bytes_fetched = Some(3 * 1024 * 1024);
+        app.attempts = 2;
+        app.actions = vec!["linkfile README.md -> doc/README.md".to_string()];
+
+        let mut lib = ProjectReport::new("lib".to_string());
+        lib.error = Some(ManifestError::FailedToCheckoutRepository(
+            "lib\nfatal: <remote> & \"origin\" hung up\n".to_string(),
+        ));
+        lib.attempts = 3;
+
+        let mut doc = ProjectReport::new("doc".to_string());
+        doc.action = SyncAction::Cancelled;
+        doc.error = Some(ManifestError::Interrupted("doc".to_string()));
+
+        SyncReport {
+            projects: vec![app, lib, doc],
+            duration: Duration::from_secs(2),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn summary_table() {
+        let report = create_report();
+        assert!(!report.is_success());
+        assert_eq!(report.get_failed(), vec!["lib", "doc"]);
+
+        let table = report.to_table();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(
+            lines[0],
+            "PROJECT  RESULT     OLD       NEW       TIME  FETCHED  ATTEMPTS  ACTIONS"
+        );
+        assert_eq!(
+            lines[1],
+            "app      updated    11111111  22222222  1.5s  3.0 MiB  2         1"
+        );
+        assert_eq!(
+            lines[2],
+            "lib      failed     -         -         0.0s  -        3         0"
+        );
+        assert!(table.ends_with(
+            "3 projects in 2.0s: 0 cloned, 1 updated, 0 up to date, 1 failed, 1 cancelled\n"
+        ));
+    }
+
+    #[test]
+    fn junit_export() {
+        let junit = create_report().to_junit();
+        assert!(junit.contains(r#"<testsuite name="sync" tests="3" failures="1" skipped="1""#));
+        assert!(junit.contains(r#"<testcase classname="sync" name="app" time="1.500">"#));
+        assert!(junit.contains("<system-out>updated 2222222222222222\nattempts: 2\nlinkfile README.md -&gt; doc/README.md</system-out>"));
+        assert!(junit.contains(
+            r#"<failure message="fatal: &lt;remote&gt; &amp; &quot;origin&quot; hung up">"#
+        ));
+        assert!(junit.contains("hung up\nattempts: 3</failure>"));
+        assert!(junit.contains(r#"<skipped message="cancelled"/>"#));
+        roxmltree::Document::parse(&junit).expect("Invalid JUnit XML");
+    }
+
+    #[test]
+    fn errors_folded_into_result() {
+        match create_report().into_result() {
+            Err(ManifestError::FailedToSync(msg)) => {
+                assert!(msg.contains("hung up"));
+                assert!(msg.ends_with("Cancelled: doc\n"));
+            }
+            result => panic!("Unexpected result {result:?}"),
+        }
+
+        let mut report = create_report();
+        report.projects.remove(1);
+        assert!(matches!(
+            report.into_result(),
+            Err(ManifestError::Interrupted(msg)) if msg.trim() == "doc"
+        ));
+    }
+
+    #[test]
+    fn fetch_attempts() {
+        let mut report = ProjectReport::new("app".to_string());
+        let error = ManifestError::FailedToCheckoutRepository(
+            "app\nfatal: unable to access\nFailed after 4 attempts\n".to_string(),
+        );
+        report.record_fetch(Err(&error));
+        assert_eq!(report.attempts, 4);
+
+        let error = ManifestError::FailedToCheckoutRepository("app\nfatal: not found\n".into());
+        report.record_fetch(Err(&error));
+        assert_eq!(report.attempts, 1);
+
+        let stats = FetchStats {
+            attempts: 2,
+            bytes: Some(1024),
+        };
+        report.record_fetch(Ok(&stats));
+        assert_eq!(report.attempts, 2);
+        assert_eq!(report.bytes_fetched, Some(1024));
+    }
+}
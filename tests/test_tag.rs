@@ -0,0 +1,145 @@
+mod common;
+
+#[cfg(test)]
+mod test_tag {
+
+    use crate::common::{create_mixed_workspace, create_workspace, git};
+    use colligo::application::{push_tag, tag_projects, DwlMode};
+
+    const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <project uri="github.com" name="team/app" path="app" revision="main"/>
+    <project uri="github.com" name="team/lib" path="lib" revision="main"/>
+</manifest>
+"#;
+
+    const PROJECTS: [&str; 2] = ["app", "lib"];
+
+    #[tokio::test]
+    async fn tag_all_projects() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let manifest = create_workspace(temp_dir.path(), &PROJECTS, MANIFEST);
+
+        let pinned = tag_projects(&manifest, "v1.0.0", Some("Release 1.0.0"), &[], false)
+            .await
+            .expect("Unable to tag projects");
+
+        for project in ["app", "lib"] {
+            let repo = temp_dir.path().join(project);
+            assert_eq!(git(&repo, &["cat-file", "-t", "v1.0.0"]), "tag");
+            assert_eq!(
+                git(&repo, &["rev-parse", "v1.0.0^{commit}"]),
+                git(&repo, &["rev-parse", "HEAD"])
+            );
+        }
+
+        assert_eq!(pinned.get_projects()[0].get_revision(), "v1.0.0");
+        assert_eq!(pinned.get_projects()[1].get_revision(), "v1.0.0");
+        assert!(pinned.get_file().contains(
+            r#"<project uri="github.com" name="team/app" path="app" revision="v1.0.0"/>"#
+        ));
+    }
+
+    #[tokio::test]
+    async fn tag_refused_when_dirty() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let manifest = create_workspace(temp_dir.path(), &PROJECTS, MANIFEST);
+
+        let lib = temp_dir.path().join("lib");
+        std::fs::write(lib.join("README.md"), "New README").expect("Failed to write file");
+
+        let result = tag_projects(&manifest, "v1.0.0", None, &[], false).await;
+        assert!(result.is_err());
+
+        // No project is tagged
+        let app = temp_dir.path().join("app");
+        assert_eq!(git(&app, &["tag", "--list"]), "");
+    }
+
+    #[tokio::test]
+    async fn tag_deleted_when_a_project_fails() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let manifest = create_workspace(temp_dir.path(), &PROJECTS, MANIFEST);
+
+        // lib is tagged after app, where the tag already exists
+        let lib = temp_dir.path().join("lib");
+        git(&lib, &["tag", "v1.0.0"]);
+
+        let result = tag_projects(&manifest, "v1.0.0", None, &[], false).await;
+        assert!(result.is_err());
+
+        let app = temp_dir.path().join("app");
+        assert_eq!(git(&app, &["tag", "--list"]), "");
+        assert_eq!(git(&lib, &["tag", "--list"]), "v1.0.0");
+
+        // Once fixed, the command succeeds
+        git(&lib, &["tag", "--delete", "v1.0.0"]);
+        tag_projects(&manifest, "v1.0.0", None, &[], false)
+            .await
+            .expect("Unable to tag projects");
+        assert_eq!(git(&app, &["tag", "--list"]), "v1.0.0");
+    }
+
+    #[tokio::test]
+    async fn tag_manifest_revision_and_push() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let manifest = create_workspace(temp_dir.path(), &PROJECTS, MANIFEST);
+
+        // Move HEAD of app away from the manifest revision
+        let app = temp_dir.path().join("app");
+        let main = git(&app, &["rev-parse", "main"]);
+        git(&app, &["checkout", "--quiet", "-b", "feature"]);
+        git(
+            &app,
+            &["commit", "--allow-empty", "--quiet", "-m", "Feature"],
+        );
+
+        let remote = temp_dir.path().join("remote.git");
+        git(
+            temp_dir.path(),
+            &["init", "--quiet", "--bare", remote.to_str().unwrap()],
+        );
+        git(&app, &["remote", "add", "origin", remote.to_str().unwrap()]);
+
+        let selection = ["app".to_string()];
+        let pinned = tag_projects(&manifest, "v1.0.0", None, &selection, true)
+            .await
+            .expect("Unable to tag projects");
+        push_tag(&manifest, "v1.0.0", &selection, &DwlMode::SSH)
+            .await
+            .expect("Unable to push tag");
+
+        assert_eq!(git(&app, &["rev-parse", "v1.0.0^{commit}"]), main);
+        assert_eq!(git(&remote, &["rev-parse", "v1.0.0^{commit}"]), main);
+
+        // Only the selected project is pinned to the tag
+        assert_eq!(pinned.get_projects()[0].get_revision(), "v1.0.0");
+        assert_eq!(pinned.get_projects()[1].get_revision(), "main");
+    }
+
+    #[tokio::test]
+    async fn tag_skips_archives_and_hg_projects() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let manifest = create_mixed_workspace(temp_dir.path());
+
+        let pinned = tag_projects(&manifest, "v1.0.0", None, &[], false)
+            .await
+            .expect("Unable to tag projects");
+
+        let app = temp_dir.path().join("app");
+        assert_eq!(git(&app, &["tag", "--list"]), "v1.0.0");
+        let revisions: Vec<&String> = pinned
+            .get_projects()
+            .iter()
+            .map(|project| project.get_revision())
+            .collect();
+        assert_eq!(
+            revisions,
+            vec![
+                "v1.0.0",
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+                "default"
+            ]
+        );
+    }
+}
@@ -0,0 +1,169 @@
+mod common;
+
+#[cfg(test)]
+mod test_topic {
+
+    use crate::common::{create_mixed_workspace, create_workspace, git};
+    use colligo::application::{get_projects_status, DwlMode};
+    use colligo::topic::{abandon_topic, push_topic, start_topic, Topics};
+
+    const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <project uri="github.com" name="team/app" path="./app" revision="main"/>
+    <project uri="github.com" name="team/lib" path="lib" revision="main"/>
+</manifest>
+"#;
+
+    const PROJECTS: [&str; 2] = ["app", "lib"];
+
+    #[tokio::test]
+    async fn start_and_abandon_topic() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let manifest = create_workspace(temp_dir.path(), &PROJECTS, MANIFEST);
+
+        // Start topic in a single project
+        let started = start_topic(&manifest, "feature", &["lib".to_string()])
+            .await
+            .expect("Unable to start topic");
+        assert_eq!(started, vec!["lib".to_string()]);
+
+        let lib = temp_dir.path().join("lib");
+        let app = temp_dir.path().join("app");
+        assert_eq!(git(&lib, &["symbolic-ref", "--short", "HEAD"]), "feature");
+        assert_eq!(git(&app, &["symbolic-ref", "--short", "HEAD"]), "main");
+
+        let topics = Topics::load(temp_dir.path()).expect("Unable to load topics");
+        assert_eq!(
+            topics.get_projects("feature"),
+            Some(&vec!["lib".to_string()])
+        );
+
+        let status = get_projects_status(&manifest, temp_dir.path()).await;
+        assert_eq!(status, vec!["app".to_string(), "lib [feature]".to_string()]);
+
+        // Abandon topic in all participating projects
+        let abandoned = abandon_topic(&manifest, "feature", &[], false)
+            .await
+            .expect("Unable to abandon topic");
+        assert_eq!(abandoned, vec!["lib".to_string()]);
+        assert_eq!(git(&lib, &["symbolic-ref", "--short", "HEAD"]), "main");
+        assert_eq!(git(&lib, &["branch", "--list", "feature"]), "");
+
+        let topics = Topics::load(temp_dir.path()).expect("Unable to load topics");
+        assert_eq!(topics.get_projects("feature"), None);
+    }
+
+    #[tokio::test]
+    async fn abandon_refused_with_unpushed_commits() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let manifest = create_workspace(temp_dir.path(), &PROJECTS, MANIFEST);
+        start_topic(&manifest, "feature", &["lib".to_string()])
+            .await
+            .expect("Unable to start topic");
+
+        let lib = temp_dir.path().join("lib");
+        git(
+            &lib,
+            &["commit", "--allow-empty", "--quiet", "-m", "Unpushed"],
+        );
+
+        assert!(abandon_topic(&manifest, "feature", &[], false)
+            .await
+            .is_err());
+        assert_eq!(git(&lib, &["symbolic-ref", "--short", "HEAD"]), "feature");
+
+        let abandoned = abandon_topic(&manifest, "feature", &[], true)
+            .await
+            .expect("Unable to abandon topic");
+        assert_eq!(abandoned, vec!["lib".to_string()]);
+        assert_eq!(git(&lib, &["branch", "--list", "feature"]), "");
+    }
+
+    #[tokio::test]
+    async fn start_topic_on_all_projects() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let manifest = create_workspace(temp_dir.path(), &PROJECTS, MANIFEST);
+
+        let started = start_topic(&manifest, "feature", &[])
+            .await
+            .expect("Unable to start topic");
+        assert_eq!(started, vec!["./app".to_string(), "lib".to_string()]);
+
+        let topics = Topics::load(temp_dir.path()).expect("Unable to load topics");
+        assert_eq!(topics.get_topics("app"), vec!["feature"]);
+        assert_eq!(topics.get_topics("./lib"), vec!["feature"]);
+    }
+
+    #[tokio::test]
+    async fn start_topic_unknown_project() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let manifest = create_workspace(temp_dir.path(), &PROJECTS, MANIFEST);
+
+        let result = start_topic(&manifest, "feature", &["unknown".to_string()]).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn push_current_topic() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let manifest = create_workspace(temp_dir.path(), &PROJECTS, MANIFEST);
+
+        // Use a bare repository as origin of lib
+        let remote = temp_dir.path().join("remote.git");
+        git(
+            temp_dir.path(),
+            &["init", "--quiet", "--bare", remote.to_str().unwrap()],
+        );
+        let lib = temp_dir.path().join("lib");
+        git(&lib, &["remote", "add", "origin", remote.to_str().unwrap()]);
+
+        start_topic(&manifest, "feature", &["lib".to_string()])
+            .await
+            .expect("Unable to start topic");
+
+        // Dry run does not create the branch
+        let (branch, results) = push_topic(&manifest, None, &DwlMode::SSH, true)
+            .await
+            .expect("Unable to push topic");
+        assert_eq!(branch, "feature");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, "lib");
+        assert_eq!(results[0].result, Ok("[new branch]".to_string()));
+        assert_eq!(git(&remote, &["branch", "--list", "feature"]), "");
+
+        // Push
+        let (_, results) = push_topic(&manifest, Some("feature"), &DwlMode::SSH, false)
+            .await
+            .expect("Unable to push topic");
+        assert_eq!(results[0].result, Ok("[new branch]".to_string()));
+        assert_eq!(
+            git(&remote, &["rev-parse", "feature"]),
+            git(&lib, &["rev-parse", "HEAD"])
+        );
+    }
+
+    #[tokio::test]
+    async fn push_without_topic() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let manifest = create_workspace(temp_dir.path(), &PROJECTS, MANIFEST);
+
+        let result = push_topic(&manifest, None, &DwlMode::SSH, false).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn topic_skips_archives_and_hg_projects() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let manifest = create_mixed_workspace(temp_dir.path());
+
+        let started = start_topic(&manifest, "feature", &[])
+            .await
+            .expect("Unable to start topic");
+        assert_eq!(started, vec!["app".to_string()]);
+
+        let abandoned = abandon_topic(&manifest, "feature", &[], false)
+            .await
+            .expect("Unable to abandon topic");
+        assert_eq!(abandoned, vec!["app".to_string()]);
+    }
+}
@@ -0,0 +1,108 @@
+mod common;
+
+#[cfg(test)]
+mod test_version_control_mock {
+
+    use crate::common::load_manifest;
+    use crate::common::mock::MockVersionControl;
+    use colligo::application::{get_projects_status, DwlMode, ManifestError, ManifestInstance};
+    use colligo::sync_report::SyncAction;
+    use colligo::workspace_state::WorkspaceState;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <project uri="github.com" name="team/app" path="app" revision="main"/>
+    <project uri="github.com" name="team/lib" path="lib" revision="main"/>
+    <project uri="github.com" name="team/doc" path="doc" revision="main"/>
+</manifest>
+"#;
+
+    fn create_manifest(path: &Path, vcs: Arc<MockVersionControl>) -> ManifestInstance {
+        let mut manifest = load_manifest(path, MANIFEST);
+        manifest.set_version_control(vcs);
+        manifest
+    }
+
+    fn create_mock() -> MockVersionControl {
+        MockVersionControl::new(&[("app", "a2"), ("lib", "b2"), ("doc", "c1")])
+    }
+
+    #[tokio::test]
+    async fn sync_reports_each_project() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let vcs = create_mock();
+        {
+            // lib is behind the remote, doc is up to date, app is new
+            let mut checked_out = vcs.checked_out.lock().unwrap();
+            checked_out.insert("lib".to_string(), "b1".to_string());
+            checked_out.insert("doc".to_string(), "c1".to_string());
+        }
+        for path in ["lib", "doc"] {
+            std::fs::create_dir(temp_dir.path().join(path)).expect("Failed to create project");
+        }
+        let vcs = Arc::new(vcs);
+        let manifest = create_manifest(temp_dir.path(), vcs.clone());
+
+        let report = manifest
+            .sync_report(&DwlMode::HTTPS, false, true, false)
+            .await;
+        assert!(report.is_success());
+
+        let actions: Vec<(&str, SyncAction)> = report
+            .projects
+            .iter()
+            .map(|p| (p.path.as_str(), p.action))
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                ("app", SyncAction::Cloned),
+                ("lib", SyncAction::Updated),
+                ("doc", SyncAction::UpToDate),
+            ]
+        );
+        assert_eq!(report.projects[1].old_commit, Some("b1".to_string()));
+        assert_eq!(report.projects[1].new_commit, Some("b2".to_string()));
+        assert_eq!(report.projects[1].bytes_fetched, Some(1024));
+        assert_eq!(vcs.get_calls("app"), vec!["init", "fetch", "update"]);
+
+        let state = WorkspaceState::load(temp_dir.path()).expect("Unable to load state");
+        let app = state.get_project("app").expect("Project not recorded");
+        assert_eq!(app.commit_id, Some("a2".to_string()));
+
+        let pinned = manifest.pin().await.expect("Unable to pin manifest");
+        let revisions: Vec<&String> = pinned
+            .get_projects()
+            .iter()
+            .map(|p| p.get_revision())
+            .collect();
+        assert_eq!(revisions, vec!["a2", "b2", "c1"]);
+    }
+
+    #[tokio::test]
+    async fn failed_fetch_skips_update() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let mut vcs = create_mock();
+        vcs.failing.push("lib".to_string());
+        vcs.modified.push("doc".to_string());
+        let vcs = Arc::new(vcs);
+        let manifest = create_manifest(temp_dir.path(), vcs.clone());
+
+        let report = manifest
+            .sync_report(&DwlMode::HTTPS, false, true, false)
+            .await;
+        assert_eq!(report.get_failed(), vec!["lib"]);
+        assert_eq!(report.projects[1].action, SyncAction::Failed);
+        assert_eq!(vcs.get_calls("lib"), vec!["init", "fetch"]);
+
+        match report.into_result() {
+            Err(ManifestError::FailedToSync(msg)) => assert!(msg.contains("hung up")),
+            result => panic!("Unexpected result {result:?}"),
+        }
+
+        let status = get_projects_status(&manifest, temp_dir.path()).await;
+        assert_eq!(status, vec!["app", "lib", "doc (modified)"]);
+    }
+}
@@ -0,0 +1,106 @@
+mod common;
+
+#[cfg(test)]
+mod test_workspace_state {
+
+    use crate::common::load_manifest;
+    use colligo::topic::Topics;
+    use colligo::workspace_state::{ProjectState, WorkspaceState};
+
+    const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <project uri="github.com" name="team/app" path="./app" revision="main">
+        <linkfile src="README.md" dest="doc/README.md"/>
+        <copydir src="config" dest="config/"/>
+    </project>
+    <project uri="github.com" name="team/lib" path="lib" revision="v1.0.0"/>
+</manifest>
+"#;
+
+    #[test]
+    fn empty_state() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+
+        let state = WorkspaceState::load(temp_dir.path()).expect("Unable to load state");
+        assert_eq!(state.get_manifest(), None);
+        assert_eq!(state.get_synced_at(), None);
+        assert!(state.get_projects().is_empty());
+    }
+
+    #[test]
+    fn record_sync_and_reload() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let manifest = load_manifest(temp_dir.path(), MANIFEST);
+
+        let synced = vec![
+            ProjectState::new(&manifest.get_projects()[0], Some("1234abcd".to_string())),
+            ProjectState::new(&manifest.get_projects()[1], None),
+        ];
+        let mut state = WorkspaceState::load(temp_dir.path()).expect("Unable to load state");
+        state.record_sync(manifest.get_file(), synced, Vec::new());
+        state.save(temp_dir.path()).expect("Unable to save state");
+
+        assert!(temp_dir.path().join(".colligo/state.json").exists());
+
+        let state = WorkspaceState::load(temp_dir.path()).expect("Unable to load state");
+        assert_eq!(state.get_manifest(), Some(manifest.get_file()));
+        assert!(state.get_synced_at().is_some());
+        assert_eq!(state.get_projects().len(), 2);
+
+        let app = state.get_project("app/").expect("Project not recorded");
+        assert_eq!(app.path, "app");
+        assert_eq!(app.revision, "main");
+        assert_eq!(app.commit_id, Some("1234abcd".to_string()));
+        assert_eq!(app.outputs, vec!["doc/README.md", "config"]);
+
+        let lib = state.get_project("lib").expect("Project not recorded");
+        assert_eq!(lib.revision, "v1.0.0");
+        assert!(lib.outputs.is_empty());
+    }
+
+    #[test]
+    fn topics_are_kept_by_sync() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let manifest = load_manifest(temp_dir.path(), MANIFEST);
+
+        let topics: Topics =
+            serde_json::from_str(r#"{"branches": {"feature": ["lib"]}}"#).expect("Invalid topics");
+        topics.save(temp_dir.path()).expect("Unable to save topics");
+
+        let mut state = WorkspaceState::load(temp_dir.path()).expect("Unable to load state");
+        let synced = vec![ProjectState::new(&manifest.get_projects()[1], None)];
+        state.record_sync(manifest.get_file(), synced, Vec::new());
+        state.save(temp_dir.path()).expect("Unable to save state");
+
+        let topics = Topics::load(temp_dir.path()).expect("Unable to load topics");
+        assert_eq!(
+            topics.get_projects("feature"),
+            Some(&vec!["lib".to_string()])
+        );
+    }
+
+    #[test]
+    fn failed_projects() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let mut manifest = load_manifest(temp_dir.path(), MANIFEST);
+
+        let mut state = WorkspaceState::default();
+        state.record_sync(
+            manifest.get_file(),
+            Vec::new(),
+            vec!["./app".to_string(), "lib".to_string()],
+        );
+        assert_eq!(state.get_failed(), &vec!["app", "lib"]);
+
+        // Only the failed projects are synchronized again
+        manifest.retain_projects(state.get_failed());
+        assert_eq!(manifest.get_projects().len(), 2);
+
+        let synced = vec![ProjectState::new(&manifest.get_projects()[0], None)];
+        state.record_sync(manifest.get_file(), synced, vec!["lib".to_string()]);
+        assert_eq!(state.get_failed(), &vec!["lib"]);
+
+        state.remove_project("lib");
+        assert!(state.get_failed().is_empty());
+    }
+}
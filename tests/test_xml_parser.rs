@@ -81,4 +81,36 @@ mod test_xml_parser {
 
         assert_eq!(composed, file);
     }
+
+    #[test]
+    fn parse_push_uri() {
+        const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+    <default uri="mirror.com" pushuri="github.com"/>
+    <project name="chrisdsa/colligo" path="dev"/>
+    <project name="chrisdsa/colligo" path="fork" pushuri="gitlab.com"/>
+</manifest>
+"#;
+
+        let parser = XmlParser::new();
+        let manifest = parser.parse(MANIFEST).expect("Unable to parse XML");
+
+        assert_eq!(
+            manifest[0].get_uri_https(),
+            "https://mirror.com/chrisdsa/colligo.git"
+        );
+        assert_eq!(
+            manifest[0].get_push_uri_ssh(),
+            Some("git@github.com:chrisdsa/colligo.git".to_string())
+        );
+        assert_eq!(
+            manifest[1].get_push_uri_https(),
+            Some("https://gitlab.com/chrisdsa/colligo.git".to_string())
+        );
+
+        let composed = parser.compose(&manifest).expect("Unable to compose XML");
+        assert!(composed.contains(
+            r#"<project uri="mirror.com" name="chrisdsa/colligo" path="fork" revision="main" pushuri="gitlab.com"/>"#
+        ));
+    }
 }